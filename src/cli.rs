@@ -1,4 +1,5 @@
 use clap::{Parser, ValueEnum};
+use std::path::PathBuf;
 
 /// Command-line arguments for msft-recon-rs.
 #[derive(Parser, Debug)]
@@ -15,6 +16,35 @@ pub struct Cli {
     /// Output results in JSON format
     #[clap(long)]
     pub json: bool,
+
+    /// Probe MX hosts over SMTP (banner, EHLO, STARTTLS) and the SMTP AUTH, POP and IMAP authentication mechanisms.
+    /// Connects directly to the target's mail servers
+    #[clap(long)]
+    pub smtp: bool,
+
+    /// Enumerate subdomains from the bundled Microsoft-flavored wordlist
+    #[clap(long)]
+    pub subdomains: bool,
+
+    /// Additional subdomain wordlist (one label per line); may be repeated. Implies --subdomains
+    #[clap(long, value_name = "FILE")]
+    pub wordlist: Vec<PathBuf>,
+
+    /// Discover subdomains passively from Certificate Transparency logs (crt.sh by default)
+    #[clap(long)]
+    pub ct: bool,
+
+    /// Attempt an AXFR zone transfer against each authoritative name server
+    #[clap(long)]
+    pub axfr: bool,
+
+    /// Azure service tags or Microsoft 365 endpoints JSON file used to attribute IPs; may be repeated
+    #[clap(long, value_name = "FILE")]
+    pub service_tags: Vec<PathBuf>,
+
+    /// MaxMind/IPinfo .mmdb database used to add ASN and location to resolved IPs; may be repeated
+    #[clap(long, value_name = "FILE")]
+    pub mmdb: Vec<PathBuf>,
     // Add other arguments like verbosity, output file etc. later if needed
 }
 
@@ -1,4 +1,4 @@
-use crate::cli::CloudTarget; // Import the new Cli and CloudTarget
+use crate::cli::CloudTarget;
 use crate::error::ReconError;
 use config::{Config, File, FileFormat};
 use serde::Deserialize;
@@ -10,7 +10,7 @@ pub struct CloudConfig {
     pub login_endpoint: String,
     pub login_microsoftonline_host: String,
     pub user_realm_endpoint: String,
-    pub openid_config_endpoint: String, // Path relative to login_endpoint
+    pub openid_config_endpoint: String, // Path relative to login_endpoint/<domain>
     pub azure_ad_connect_check_url: String,
     pub sharepoint_host_suffix: String,
     pub cdn_host_suffix: String,
@@ -18,18 +18,63 @@ pub struct CloudConfig {
     pub activesync_endpoint_host: String, // ActiveSync endpoint
     pub app_service_host_suffix: String, // For Azure App Services (.azurewebsites.net)
     pub storage_account_host_suffix: String, // For Azure Storage (.blob.core.windows.net)
+    #[serde(default)]
+    pub onmicrosoft_suffix: String, // Initial tenant domain suffix (.onmicrosoft.com)
+    #[serde(default)]
+    pub exchange_spf_include: String, // SPF include for Exchange Online (spf.protection.outlook.com)
+    #[serde(default)]
+    pub eop_host_suffix: String, // Exchange Online Protection MX suffix (.mail.protection.outlook.com)
+    #[serde(default)]
+    pub autodiscover_endpoint: String, // Exchange Online Autodiscover (POX) URL
+    #[serde(default)]
+    pub smtp_submission_host: String, // Exchange Online SMTP AUTH submission host (smtp.office365.com)
+    #[serde(default)]
+    pub lyncdiscover_host: String, // Skype for Business Online discovery host (webdir.online.lync.com)
+    #[serde(default)]
+    pub power_pages_host_suffixes: Vec<String>, // Power Apps portals / Power Pages (.powerappsportals.com)
+    #[serde(default)]
+    pub dynamics_regions: Vec<DynamicsRegion>, // Dynamics 365 / Dataverse regional hosts
+    #[serde(default)]
+    pub mdm_discovery_url: String, // Intune MDM discovery service (enrollment.manage.microsoft.com)
+    #[serde(default)]
+    pub device_registration_host: String, // Entra device registration service (enterpriseregistration.windows.net)
     // Add other endpoint URLs as needed based on default.toml and checks
     // pub graph_endpoint: String, 
-    // pub autodiscover_endpoint: String,
     // ... etc
 }
 
+/// Dynamics 365 / Dataverse regional host suffix and the geography it serves.
+#[derive(Debug, Clone, Deserialize)]
+pub struct DynamicsRegion {
+    pub host_suffix: String, // e.g., .crm4.dynamics.com
+    pub geography: String,   // e.g., EMEA
+}
+
+/// DKIM selectors commonly used by third-party senders.
+/// Probed in addition to Microsoft 365's `selector1`/`selector2`.
+pub const DEFAULT_DKIM_SELECTORS: &[&str] = &[
+    "google", "k1", "k2", "k3", "s1", "s2", "mandrill", "mxvault", "dkim", "default",
+    "mail", "smtp", "everlytickey1", "everlytickey2", "zendesk1", "zendesk2", "mailjet", "pm", "cm",
+];
+
+/// DNS queries per second for per-host lookups, unless configured.
+pub const DEFAULT_SUBDOMAIN_QPS: u32 = 50;
+
+/// Certificate Transparency search API used with `--ct`, unless configured.
+pub const DEFAULT_CT_LOG_URL: &str = "https://crt.sh";
+
 /// Represents the overall application configuration.
 #[derive(Debug, Deserialize)]
 pub struct AppConfig {
     pub clouds: Clouds,
     pub request_timeout_seconds: Option<u64>,
     pub default_user_agent: String,
+    pub dkim_selectors: Option<Vec<String>>, // Overrides DEFAULT_DKIM_SELECTORS
+    pub no_smtp: Option<bool>, // Safety switch: never open SMTP connections, even with --smtp
+    pub subdomain_qps: Option<u32>, // DNS queries per second for per-host lookups (subdomains, CNAME chains, addresses)
+    pub ct_log_url: Option<String>, // crt.sh-compatible CT search API
+    pub service_tags_files: Option<Vec<String>>, // Azure service tag / M365 endpoint JSON files
+    pub mmdb_files: Option<Vec<String>>, // MaxMind/IPinfo ASN and geolocation databases
 }
 
 /// Container for different cloud environment configurations.
@@ -71,7 +116,7 @@ pub fn select_cloud_config<'a>(app_config: &'a AppConfig, cloud_target: &CloudTa
 #[allow(dead_code)]
 /// Gets the configured request timeout as a Duration.
 pub fn get_timeout_duration(app_config: &AppConfig) -> Duration {
-    app_config.request_timeout_seconds.map_or(Duration::from_secs(0), |s| Duration::from_secs(s))
+    app_config.request_timeout_seconds.map_or(Duration::from_secs(0), Duration::from_secs)
 }
 
 #[cfg(test)]
@@ -95,6 +140,14 @@ mod tests {
         assert_eq!(config.clouds.commercial.cdn_host_suffix, ".azureedge.net");
     }
 
+    #[test]
+    fn test_load_config_dynamics_regions() {
+        ensure_config_file_exists();
+        let config = load_config().expect("Failed to load config");
+        let crm4 = config.clouds.commercial.dynamics_regions.iter().find(|region| region.host_suffix == ".crm4.dynamics.com");
+        assert_eq!(crm4.map(|region| region.geography.as_str()), Some("EMEA"));
+    }
+
     #[test]
     fn test_load_config_gov() {
         ensure_config_file_exists();
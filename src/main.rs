@@ -15,13 +15,15 @@ use crate::config::{load_config, select_cloud_config};
 use crate::error::ReconError;
 use crate::output::print_results;
 use crate::recon::client::new_client;
-use crate::recon::run_all_checks;
+use crate::recon::{build_recon_options, run_all_checks};
 
 #[tokio::main]
 async fn main() -> Result<(), ReconError> {
     // 1. Setup Logging
     let subscriber = fmt::Subscriber::builder()
         .with_env_filter(EnvFilter::from_default_env().add_directive(Level::INFO.into()))
+        // Keep stdout clean for the report itself (notably `--json`)
+        .with_writer(std::io::stderr)
         .finish();
     tracing::subscriber::set_global_default(subscriber)
         .map_err(|e| ReconError::Other(format!("Failed to set tracing subscriber: {}", e)))?;
@@ -55,6 +57,16 @@ async fn main() -> Result<(), ReconError> {
         }
     };
     info!("Using cloud configuration: {:?}", cli.cloud);
+    let recon_options = match build_recon_options(&app_config, &cli) {
+        Ok(options) => options,
+        Err(e) => {
+            error!("Failed to build reconnaissance options: {}", e);
+            return Err(e);
+        }
+    };
+    if cli.smtp && !recon_options.smtp_enabled {
+        info!("SMTP probing disabled by the no_smtp configuration setting");
+    }
 
     // Initialize HTTP Client
     let client = match new_client(&app_config) {
@@ -68,7 +80,7 @@ async fn main() -> Result<(), ReconError> {
 
     // --- Run Reconnaissance Checks ---
     info!(target = &cli.domain, "Starting reconnaissance...");
-    match run_all_checks(client, cli.domain.clone(), cloud_config.clone()).await {
+    match run_all_checks(client, cli.domain.clone(), cloud_config.clone(), recon_options).await {
         Ok(results) => {
             info!(target = &cli.domain, "Reconnaissance finished.");
 
@@ -26,6 +26,8 @@ pub struct FederationInfo {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub auth_url: Option<String>, // Authentication URL if federated
     #[serde(skip_serializing_if = "Option::is_none")]
+    pub mex_url: Option<String>, // WS-Trust metadata exchange URL if federated
+    #[serde(skip_serializing_if = "Option::is_none")]
     pub cloud_instance_name: Option<String>, // e.g., MicrosoftOnline.com
     // Add other fields from getuserrealm.srf as needed
 }
@@ -55,6 +57,433 @@ pub enum AadConnectStatus {
 
 // --- Service Check Results ---
 
+/// Where a DNS target is hosted, judged from its host name.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum HostingType {
+    MicrosoftHosted, // e.g., sipfed.online.lync.com
+    OnPremises,      // Anything outside Microsoft-owned zones
+}
+
+/// Mail provider identified from an MX host name.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum MailProvider {
+    ExchangeOnline, // Exchange Online Protection
+    GoogleWorkspace,
+    Zoho,
+    Proofpoint,
+    Mimecast,
+    Barracuda,
+    CiscoSecureEmail, // Cisco IronPort / Secure Email Cloud Gateway
+    Sophos,
+    TrendMicro,
+    Symantec, // Broadcom Email Security.cloud (MessageLabs)
+    Forcepoint,
+    Hornetsecurity,
+    OnPremises, // No known hosted-provider signature
+}
+
+/// An MX record with its classified provider.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct MxHost {
+    pub host: String,
+    pub priority: u16,
+    pub provider: MailProvider,
+    pub secure_email_gateway: bool, // Third-party filtering service rather than a mailbox host
+}
+
+/// How inbound mail reaches the tenant, based on MX and other M365 evidence.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum MailRouting {
+    ExchangeOnlineDirect,    // MX points straight at Exchange Online Protection
+    GatewayToExchangeOnline, // Third-party gateway in front of Exchange Online
+    ThirdPartyGateway,       // Gateway with no evidence of Exchange Online behind it
+    OtherProvider,           // Non-Microsoft mailbox host or on-premises
+    NoMx,
+}
+
+/// Direct-to-EOP delivery check for the tenant's `<domain-with-dashes>.mail.protection.*` host.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct EopDirectDelivery {
+    pub eop_host: String,
+    pub resolves: bool,
+    pub addresses: Vec<String>,
+    pub mx_points_elsewhere: bool,
+    pub gateway_bypass_exposed: bool, // EOP host resolves while MX points elsewhere
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smtp_accepts_connections: Option<bool>, // Confirmed by the SMTP probe, when enabled
+}
+
+/// A single SRV record discovered for the target domain.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct SrvRecord {
+    pub service: String, // e.g., _sipfederationtls._tcp
+    pub target: String,
+    pub port: u16,
+    pub priority: u16,
+    pub weight: u16,
+    pub hosting: HostingType,
+}
+
+/// CNAME chain followed from a host name to its final target.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct CnameChain {
+    pub hostname: String,
+    pub chain: Vec<String>, // Host name first, then each CNAME target in order
+    pub addresses: Vec<String>, // A/AAAA records of the final target
+    pub nxdomain: bool, // A name in the chain does not exist
+    pub loop_detected: bool,
+}
+
+/// Host name whose CNAME chain points at an unclaimed Azure resource.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TakeoverCandidate {
+    pub hostname: String,
+    pub chain: Vec<String>, // Evidence: the full CNAME chain
+    pub azure_service: String, // e.g., App Service
+    pub dangling_target: String, // The Azure name that no longer exists
+    pub reason: String,
+}
+
+/// Where a discovered subdomain came from.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum DiscoverySource {
+    Wordlist,
+    CertificateTransparency,
+}
+
+/// Subdomain found by wordlist enumeration or Certificate Transparency.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DiscoveredSubdomain {
+    pub hostname: String,
+    pub source: DiscoverySource,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cname_target: Option<String>,
+    pub addresses: Vec<String>,
+    pub hosting: HostingType, // Microsoft-hosted if the host or its CNAME target is a Microsoft name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub azure_service: Option<String>, // Azure service the CNAME target belongs to
+}
+
+/// Results of subdomain discovery (wordlist enumeration and Certificate Transparency).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SubdomainResults {
+    pub words_tried: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ct_names_found: Option<usize>, // Only when CT logs were queried
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ct_error: Option<String>,
+    pub wildcard_detected: bool,
+    pub wildcard_targets: Vec<String>, // Addresses/CNAME targets returned for random labels
+    pub filtered_wildcard_matches: usize,
+    pub subdomains: Vec<DiscoveredSubdomain>,
+}
+
+/// DNS hosting provider, identified from the authoritative name server host names.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum DnsProvider {
+    AzureDns,
+    Microsoft365, // Domain managed in the Microsoft 365 admin center
+    Route53,
+    Cloudflare,
+    GoogleCloudDns,
+    Akamai,
+    UltraDns,
+    Ns1,
+    OracleDyn,
+    GoDaddy,
+    Namecheap,
+    SelfHosted, // Name servers under the target domain
+    Other,
+}
+
+/// Start of Authority record of the target zone.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SoaRecord {
+    pub primary_ns: String,
+    pub responsible_mailbox: String,
+    pub serial: u32,
+    pub refresh: i32,
+    pub retry: i32,
+    pub expire: i32,
+    pub minimum: u32,
+}
+
+/// Result of an AXFR zone transfer attempt against one name server.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ZoneTransferResult {
+    pub allowed: bool,
+    pub record_count: usize,
+    pub records: Vec<String>, // Capped; record_count has the full total
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// An authoritative name server and the answers it gave directly.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NameServer {
+    pub host: String,
+    pub addresses: Vec<String>,
+    pub provider: DnsProvider,
+    pub responded: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soa_serial: Option<u32>,
+    pub ns_records: Vec<String>, // NS set as served by this server
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub zone_transfer: Option<ZoneTransferResult>, // Only when AXFR was attempted
+}
+
+/// Authoritative name servers, SOA and DNS hosting provider of the target zone.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NameServerResults {
+    pub nameservers: Vec<NameServer>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub soa: Option<SoaRecord>,
+    pub providers: Vec<DnsProvider>, // Where DNS changes for the domain are made
+    pub consistent: bool, // All servers answered with the same serial and NS set
+    pub issues: Vec<String>,
+}
+
+/// Published Microsoft IP range (Azure service tag or M365 endpoint set) containing an address.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ServiceTagMatch {
+    pub address: String,
+    pub tag: String, // e.g., AppService.WestEurope, AzureFrontDoor.Frontend, Exchange Online
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub system_service: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub region: Option<String>,
+    pub prefix: String,
+}
+
+/// ASN and location of an address, from offline MMDB databases.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct IpEnrichment {
+    pub address: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub asn: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub as_organization: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country_code: Option<String>, // ISO 3166-1 alpha-2
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub country: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub city: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub continent_code: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub outside_tenant_region: Option<bool>, // Located outside the tenant's region scope
+}
+
+/// Addresses of a discovered host name, attributed to service tags and enriched when databases are loaded.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ResolvedHost {
+    pub hostname: String,
+    pub ipv4: Vec<String>,
+    pub ipv6: Vec<String>,
+    pub service_tag_matches: Vec<ServiceTagMatch>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub enrichment: Vec<IpEnrichment>,
+}
+
+/// SPF qualifier, as used on mechanisms such as `all`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum SpfQualifier {
+    Pass,     // +
+    Fail,     // -
+    SoftFail, // ~
+    Neutral,  // ?
+}
+
+/// One SPF record in the include/redirect tree.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SpfNode {
+    pub domain: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub reached_via: Option<String>, // include or redirect; None for the target domain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub all_qualifier: Option<SpfQualifier>,
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub ip_ranges: Vec<String>, // From ip4/ip6/a/mx in this record only
+    #[serde(skip_serializing_if = "Vec::is_empty", default)]
+    pub children: Vec<SpfNode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Result of recursively evaluating the SPF policy of the target domain.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SpfAnalysis {
+    pub dns_lookup_count: u32,
+    pub void_lookup_count: u32,
+    pub lookup_limit_exceeded: bool, // More than the RFC 7208 limit of 10
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub all_qualifier: Option<SpfQualifier>, // Effective qualifier after redirects
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub includes_exchange_online: Option<bool>, // spf.protection.outlook.com (or cloud equivalent)
+    pub ip_ranges: Vec<String>,
+    pub weaknesses: Vec<String>,
+    pub tree: SpfNode,
+}
+
+/// DMARC disposition requested for failing mail (`p=` / `sp=`).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum DmarcDisposition {
+    None,
+    Quarantine,
+    Reject,
+}
+
+/// DMARC identifier alignment mode (`adkim=` / `aspf=`).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum DmarcAlignment {
+    Relaxed,
+    Strict,
+}
+
+/// A single `rua=` or `ruf=` report destination.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DmarcReportDestination {
+    pub uri: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub domain: Option<String>,
+    pub external: bool, // Outside the policy's organizational domain
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authorized: Option<bool>, // <policy-domain>._report._dmarc.<destination> record, external only
+}
+
+/// Parsed DMARC record, with RFC 7489 defaults applied to optional tags.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DmarcPolicy {
+    pub record: String,
+    pub queried_name: String, // e.g., _dmarc.contoso.com
+    pub from_organizational_domain: bool, // Found via organizational-domain fallback
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy: Option<DmarcDisposition>, // p (required; None if missing or invalid)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subdomain_policy: Option<DmarcDisposition>, // sp (falls back to p when absent)
+    pub percentage: u8, // pct
+    pub aggregate_reports: Vec<DmarcReportDestination>, // rua
+    pub forensic_reports: Vec<DmarcReportDestination>, // ruf
+    pub dkim_alignment: DmarcAlignment, // adkim
+    pub spf_alignment: DmarcAlignment, // aspf
+    pub failure_options: String, // fo
+    pub report_interval: u32, // ri, in seconds
+    pub issues: Vec<String>,
+}
+
+/// A DKIM selector found under `<selector>._domainkey.<domain>`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DkimSelector {
+    pub selector: String,
+    pub name: String, // e.g., selector1._domainkey.contoso.com
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cname_target: Option<String>, // M365: selector1-contoso-com._domainkey.contoso.onmicrosoft.com
+    pub microsoft_365: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_type: Option<String>, // k= (rsa, ed25519)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub key_bits: Option<u32>,
+    pub revoked: bool, // Empty p= tag
+    pub testing: bool, // t=y
+    pub issues: Vec<String>,
+}
+
+/// DKIM selector discovery results.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DkimResults {
+    pub selectors: Vec<DkimSelector>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub m365_tenant_name: Option<String>, // From the selector CNAME target, e.g., contoso.onmicrosoft.com
+}
+
+/// MTA-STS policy mode (RFC 8461).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum MtaStsMode {
+    Enforce,
+    Testing,
+    None,
+}
+
+/// MTA-STS TXT record and the policy file served from `mta-sts.<domain>`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MtaStsResults {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub txt_record: Option<String>, // _mta-sts.<domain>
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub policy_id: Option<String>,
+    pub policy_url: String,
+    pub policy_found: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mode: Option<MtaStsMode>,
+    pub mx_patterns: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_age: Option<u64>,
+    pub unmatched_mx_hosts: Vec<String>, // Observed MX hosts the policy does not cover
+    pub issues: Vec<String>,
+}
+
+/// SMTP TLS reporting record (`_smtp._tls.<domain>`, RFC 8460).
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct TlsRptResults {
+    pub record: String,
+    pub reporting_uris: Vec<String>,
+}
+
+/// BIMI record (`default._bimi.<domain>`) and the assets it points to.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct BimiResults {
+    pub record: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logo_url: Option<String>, // l=
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub logo_is_svg: Option<bool>, // None if not fetched
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vmc_url: Option<String>, // a= (Verified Mark Certificate)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub vmc_is_certificate: Option<bool>, // None if not fetched
+    pub issues: Vec<String>,
+}
+
+/// DNSSEC validation status of the target zone.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum DnssecStatus {
+    Signed,   // DS at the parent and the zone validates
+    Unsigned, // No DS at the parent
+    Bogus,    // DS at the parent but validation fails
+    Indeterminate, // A lookup failed for another reason (timeout, SERVFAIL)
+}
+
+/// DNSSEC status and algorithms of the target zone.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DnssecResults {
+    pub status: DnssecStatus,
+    pub ds_algorithms: Vec<String>, // e.g., RSASHA256, ECDSAP256SHA256
+    pub dnskey_algorithms: Vec<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub validation_error: Option<String>,
+}
+
+/// A TLSA record (RFC 6698), in presentation form.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct TlsaRecord {
+    pub cert_usage: u8, // 3 = DANE-EE, 2 = DANE-TA
+    pub selector: u8,
+    pub matching_type: u8,
+    pub certificate_data: String, // Hex
+}
+
+/// TLSA records published at `_25._tcp.<mx-host>`.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub struct MxTlsaResult {
+    pub mx_host: String,
+    pub records: Vec<TlsaRecord>,
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct DnsResults {
     // MX Records
@@ -62,18 +491,34 @@ pub struct DnsResults {
     pub mx_records: Option<Vec<String>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub mx_records_found: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mx_hosts: Option<Vec<MxHost>>, // Sorted by priority, with provider classification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub eop_direct_delivery: Option<EopDirectDelivery>,
     // SPF Record
     #[serde(skip_serializing_if = "Option::is_none")]
     pub spf_record: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub spf_record_found: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub spf_analysis: Option<SpfAnalysis>,
     // DMARC Record
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dmarc_record: Option<String>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub dmarc_record_found: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub dmarc_policy: Option<String>, // e.g., none, quarantine, reject
+    pub dmarc_policy: Option<DmarcPolicy>,
+    // DKIM Selectors
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dkim: Option<DkimResults>,
+    // Email Transport Security / Branding
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mta_sts: Option<MtaStsResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_rpt: Option<TlsRptResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bimi: Option<BimiResults>,
     // Specific TXT Records for M365/Azure indicators
     #[serde(skip_serializing_if = "Option::is_none")]
     pub ms_txt_record: Option<String>, // ms=... record
@@ -91,6 +536,13 @@ pub struct DnsResults {
     pub enterpriseenrollment_txt_record: Option<String>, // enterpriseenrollment...
     #[serde(skip_serializing_if = "Option::is_none")]
     pub enterpriseenrollment_txt_found: Option<bool>,
+    // DNSSEC / DANE
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dnssec: Option<DnssecResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mx_tlsa: Option<Vec<MxTlsaResult>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub inbound_dane: Option<bool>, // Signed zone and TLSA for every MX host
     // Lync/Skype/Teams DNS Records
     #[serde(skip_serializing_if = "Option::is_none")]
     pub autodiscover_cname_or_a: Option<String>,
@@ -102,6 +554,23 @@ pub struct DnsResults {
     pub sipfederationtls_tcp_present: Option<bool>, // _sipfederationtls._tcp.<domain> SRV
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sip_tls_present: Option<bool>, // _sip._tls.<domain> SRV
+    // SRV Records (SIP federation, Autodiscover, XMPP, Kerberos, LDAP)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub srv_records: Option<Vec<SrvRecord>>,
+    // CNAME chains and dangling records
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cname_chains: Option<Vec<CnameChain>>, // Only host names that are CNAMEs
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub takeover_candidates: Option<Vec<TakeoverCandidate>>,
+    // Subdomain enumeration (opt-in)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub subdomains: Option<SubdomainResults>,
+    // Authoritative name servers / SOA
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub nameservers: Option<NameServerResults>,
+    // A/AAAA of every discovered host name
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub resolved_hosts: Option<Vec<ResolvedHost>>,
 }
 
 impl PartialEq for DnsResults {
@@ -109,11 +578,21 @@ impl PartialEq for DnsResults {
         // Compare fields that matter for equality
         self.mx_records == other.mx_records &&
         self.mx_records_found == other.mx_records_found &&
+        self.mx_hosts == other.mx_hosts &&
+        self.eop_direct_delivery == other.eop_direct_delivery &&
         self.spf_record == other.spf_record &&
         self.spf_record_found == other.spf_record_found &&
+        self.spf_analysis == other.spf_analysis &&
         self.dmarc_record == other.dmarc_record &&
         self.dmarc_record_found == other.dmarc_record_found &&
         self.dmarc_policy == other.dmarc_policy &&
+        self.dkim == other.dkim &&
+        self.mta_sts == other.mta_sts &&
+        self.tls_rpt == other.tls_rpt &&
+        self.bimi == other.bimi &&
+        self.dnssec == other.dnssec &&
+        self.mx_tlsa == other.mx_tlsa &&
+        self.inbound_dane == other.inbound_dane &&
         self.ms_txt_record == other.ms_txt_record &&
         self.ms_txt_found == other.ms_txt_found &&
         self.ms_adfs_auth_txt_record == other.ms_adfs_auth_txt_record &&
@@ -126,32 +605,347 @@ impl PartialEq for DnsResults {
         self.lyncdiscover_present == other.lyncdiscover_present &&
         self.sip_cname_or_a_present == other.sip_cname_or_a_present &&
         self.sipfederationtls_tcp_present == other.sipfederationtls_tcp_present &&
-        self.sip_tls_present == other.sip_tls_present
+        self.sip_tls_present == other.sip_tls_present &&
+        self.srv_records == other.srv_records &&
+        self.cname_chains == other.cname_chains &&
+        self.takeover_candidates == other.takeover_candidates &&
+        self.subdomains == other.subdomains &&
+        self.nameservers == other.nameservers &&
+        self.resolved_hosts == other.resolved_hosts
     }
 }
 
+/// SharePoint Online host probed for a tenant.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum SharePointSiteKind {
+    Root,     // <tenant>.sharepoint.com
+    OneDrive, // <tenant>-my.sharepoint.com
+    Admin,    // <tenant>-admin.sharepoint.com
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SharePointSite {
+    pub kind: SharePointSiteKind,
+    pub url: String,
+    pub exists: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>, // Status of the final response after redirects
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub redirect: Option<String>, // Final URL when redirected, e.g., to the sign-in page
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SharePointResults {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_name: Option<String>, // SharePoint tenant name whose root site exists
+    pub tenant_name_discovered: bool, // Taken from the onmicrosoft name rather than guessed from the domain
+    pub candidates_tried: Vec<String>,
+    pub sites: Vec<SharePointSite>,
+}
+
+/// Power Apps portal / Power Pages site found for a candidate name.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct PowerPagesSite {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub display_name: Option<String>, // Home page title
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub odata_feeds: Vec<String>, // Entity list feeds listed anonymously at /_odata
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub exposed_tables: Vec<String>, // Tables readable anonymously through the /_api/ Web API
+    pub anonymous_data_access: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct PowerPagesResults {
+    pub candidates_tried: Vec<String>, // Host names probed
+    pub sites: Vec<PowerPagesSite>,
+}
+
+/// Dynamics 365 / Dataverse environment found on a regional host.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DynamicsEnvironment {
+    pub url: String, // Instance URL, e.g., https://contoso.crm4.dynamics.com
+    pub geography: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tenant_id: Option<String>, // From the sign-in redirect
+    pub tenant_confirmed: bool, // Sign-in redirect names the target's tenant ID
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct DynamicsResults {
+    pub candidates_tried: Vec<String>, // Organisation names probed in every region
+    pub environments: Vec<DynamicsEnvironment>,
+}
+
+/// Protocol whose authentication schemes were probed.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum AuthProtocol {
+    Ews,
+    ActiveSync,
+    Autodiscover,
+    SmtpAuth,
+    Pop,
+    Imap,
+}
+
+/// Authentication schemes offered by an endpoint to an unauthenticated client.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AuthEndpoint {
+    pub protocol: AuthProtocol,
+    pub endpoint: String, // URL, or host:port for mail protocols
+    pub on_premises: bool,
+    pub reachable: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>, // HTTP status of the unauthenticated request
+    pub schemes: Vec<String>, // WWW-Authenticate schemes (Basic, NTLM, Negotiate, Bearer) or SASL mechanisms
+    pub basic_auth: bool, // Accepts a plain user name and password (HTTP Basic, SASL PLAIN/LOGIN, POP USER, IMAP LOGIN)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Fields decoded from the target info of an NTLM Type 2 (challenge) message.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct NtlmTargetInfo {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub netbios_domain: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub netbios_computer: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns_domain: Option<String>, // AD domain, e.g., corp.contoso.com
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns_forest: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dns_computer: Option<String>, // Server FQDN, e.g., EXCH01.corp.contoso.com
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_version: Option<String>, // major.minor.build, e.g., 10.0.17763
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub os_name: Option<String>, // Windows Server release matching the build, if known
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub timestamp: Option<String>, // Server clock (RFC 3339, UTC)
+}
+
+/// Internal Active Directory details disclosed by an endpoint's NTLM challenge.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct NtlmDisclosure {
+    pub url: String,
+    pub service: String, // Exchange, Skype/Lync, ADFS, RD Gateway
+    #[serde(flatten)]
+    pub target_info: NtlmTargetInfo,
+}
+
+/// Exchange Server build fingerprinted from a self-hosted OWA/ECP/EWS front end.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct ExchangeFingerprint {
+    pub host: String,
+    pub build: String, // e.g., 15.2.1544.4
+    pub source: String, // X-OWA-Version header or the resource path it was read from
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub version: Option<String>, // e.g., Exchange Server 2019
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub cumulative_update: Option<String>, // e.g., CU14
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub release_date: Option<String>, // Of the cumulative update (YYYY-MM-DD)
+    pub outdated: bool, // Not the latest cumulative update, or the version is out of support
+}
+
+/// Answer of an MDM (MS-MDE2) discovery service for the synthetic address.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct MdmDiscovery {
+    pub url: String, // Discovery.svc endpoint that answered
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub auth_policy: Option<String>, // OnPremise, Federated or Certificate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enrollment_service_url: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub authentication_service_url: Option<String>,
+}
+
+/// Device registration service (DRS) contract advertised for the domain.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct DeviceRegistrationDiscovery {
+    pub url: String, // Contract endpoint that answered
+    pub on_premises: bool, // Self-hosted DRS (AD FS) at enterpriseregistration.<domain>
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registration_endpoint: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub federated: Option<bool>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub passive_auth_endpoint: Option<String>,
+}
+
+/// Intune / MDM enrollment and Windows device registration endpoints.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct ProvisioningResults {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub enrollment_target: Option<String>, // Final CNAME target (or the name itself) of enterpriseenrollment.<domain>
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub registration_target: Option<String>, // Final CNAME target (or the name itself) of enterpriseregistration.<domain>
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mdm_discovery: Option<MdmDiscovery>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub device_registration: Option<DeviceRegistrationDiscovery>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ndes_endpoints: Vec<String>, // NDES/SCEP endpoints answering GetCACaps
+    pub intune_auto_enrollment: bool,
+    pub on_premises_mdm: bool, // Self-hosted MDM enrollment server or NDES
+    pub hybrid_join_infrastructure: bool, // enterpriseregistration.<domain> published, or a self-hosted DRS
+}
+
+/// Sign-on endpoint advertised in federation metadata.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SsoEndpoint {
+    pub protocol: String, // SAML2 or WS-Fed
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub binding: Option<String>, // SAML binding, e.g., HTTP-Redirect, HTTP-POST
+    pub location: String,
+}
+
+/// SAML2 / WS-Federation endpoints read from a federation metadata document.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct FederationMetadataResults {
+    pub url: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entity_id: Option<String>,
+    pub endpoints: Vec<SsoEndpoint>,
+}
+
+/// WS-Trust endpoint kind.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum WsTrustKind {
+    UsernameMixed,    // User name and password over TLS; a password-spray vector
+    WindowsTransport, // Integrated Windows authentication
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct WsTrustEndpoint {
+    pub url: String,
+    pub kind: WsTrustKind,
+    pub version: String, // 2005 or 13 (WS-Trust 1.3)
+    pub listed_in_mex: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub status: Option<u16>, // HTTP status of an unauthenticated GET
+    pub exposed: bool,
+}
+
+/// SAML, WS-Federation and WS-Trust endpoints of Entra ID and the federated identity provider.
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SamlResults {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub entra_metadata: Option<FederationMetadataResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idp_host: Option<String>, // Federated identity provider (e.g., AD FS) host
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idp_metadata: Option<FederationMetadataResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub idp_initiated_signon: Option<bool>, // AD FS IdpInitiatedSignOn page enabled
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub ws_trust_endpoints: Vec<WsTrustEndpoint>,
+    pub usernamemixed_exposed: bool,
+}
+
+/// Where the domain's mailboxes are served from.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum ExchangeTopology {
+    CloudOnly,  // Exchange Online only
+    Hybrid,     // Exchange Online plus on-premises Exchange
+    OnPremises, // On-premises Exchange only
+}
+
+/// Answer of the Autodiscover v2 JSON endpoint for one protocol.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct AutodiscoverV2Result {
+    pub protocol: String, // EWS, ActiveSync, REST
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub url: Option<String>, // Service URL returned for the mailbox
+    pub on_premises: bool, // The URL points outside Microsoft 365
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct AutodiscoverResults {
+    pub address: String, // Synthetic address queried
+    pub v2: Vec<AutodiscoverV2Result>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pox_url: Option<String>, // Legacy autodiscover.xml endpoint, when autodiscover.<domain> is self-hosted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pox_status: Option<u16>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pox_final_url: Option<String>, // After redirects
+    pub pox_on_premises: bool, // An Exchange server outside Microsoft 365 answered
+    pub m365_evidence: bool, // getuserrealm or MX shows an M365 tenant; needed to trust cloud v2 answers
+}
+
+/// Skype for Business / Teams deployment behind a domain's lyncdiscover endpoint.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq, Eq)]
+pub enum LyncDeployment {
+    SkypeOnline, // lyncdiscover.<domain> points at Skype for Business Online (webdir*.online.lync.com)
+    OnPremises,  // Self-hosted Lync / Skype for Business pool
+    TeamsOnly,   // Known to the online directory without Skype for Business DNS records
+}
+
+/// Links returned by the lyncdiscover (Lync/Skype for Business Autodiscover) JSON endpoint.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct LyncDiscoverResults {
+    pub url: String, // Discovery endpoint that answered
+    pub deployment: LyncDeployment,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_link: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub xframe_link: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub pool_fqdn: Option<String>, // Web services FQDN of an on-premises pool
+}
+
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
 pub struct M365Results {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub sharepoint_detected: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub teams_detected: Option<bool>, // Based on Lync/SIP DNS
+    pub sharepoint: Option<SharePointResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub teams_detected: Option<bool>, // Online deployment behind lyncdiscover
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub lync_discover: Option<LyncDiscoverResults>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub tenant_branding_accessible: Option<bool>, // Login page branding check
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub legacy_auth_ews_enabled: Option<bool>, // EWS endpoint responsive
+    pub legacy_auth_ews_enabled: Option<bool>, // A self-hosted EWS endpoint offers Basic auth
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub legacy_auth_activesync_enabled: Option<bool>, // A self-hosted ActiveSync endpoint offers Basic auth
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub auth_endpoints: Vec<AuthEndpoint>, // Per-endpoint authentication schemes
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub autodiscover: Option<AutodiscoverResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exchange_topology: Option<ExchangeTopology>, // From Autodiscover
     #[serde(skip_serializing_if = "Option::is_none")]
-    pub legacy_auth_activesync_enabled: Option<bool>, // ActiveSync endpoint responsive
-    // Add Power Apps, etc.
+    pub mail_routing: Option<MailRouting>, // From MX classification
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mail_gateway: Option<MailProvider>, // Primary third-party gateway, if any
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub power_pages: Option<PowerPagesResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub dynamics: Option<DynamicsResults>,
 }
 
 impl PartialEq for M365Results {
     fn eq(&self, other: &Self) -> bool {
         self.sharepoint_detected == other.sharepoint_detected &&
+        self.sharepoint == other.sharepoint &&
         self.teams_detected == other.teams_detected &&
+        self.lync_discover == other.lync_discover &&
         self.tenant_branding_accessible == other.tenant_branding_accessible &&
         self.legacy_auth_ews_enabled == other.legacy_auth_ews_enabled &&
-        self.legacy_auth_activesync_enabled == other.legacy_auth_activesync_enabled
+        self.legacy_auth_activesync_enabled == other.legacy_auth_activesync_enabled &&
+        self.auth_endpoints == other.auth_endpoints &&
+        self.autodiscover == other.autodiscover &&
+        self.exchange_topology == other.exchange_topology &&
+        self.mail_routing == other.mail_routing &&
+        self.mail_gateway == other.mail_gateway &&
+        self.power_pages == other.power_pages &&
+        self.dynamics == other.dynamics
     }
 }
 
@@ -163,6 +957,8 @@ pub struct AzureServiceResults {
     pub probable_storage_accounts: Vec<String>,
     #[serde(skip_serializing_if = "Vec::is_empty")]
     pub probable_cdn_endpoints: Vec<String>,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub azure_fronted_hosts: Vec<String>, // Discovered subdomains whose CNAME points into Azure
     // Add Key Vault, Functions, SWAs, ACR, Cog Services, B2C etc.
 }
 
@@ -170,7 +966,8 @@ impl PartialEq for AzureServiceResults {
     fn eq(&self, other: &Self) -> bool {
         self.probable_app_services == other.probable_app_services &&
         self.probable_storage_accounts == other.probable_storage_accounts &&
-        self.probable_cdn_endpoints == other.probable_cdn_endpoints
+        self.probable_cdn_endpoints == other.probable_cdn_endpoints &&
+        self.azure_fronted_hosts == other.azure_fronted_hosts
     }
 }
 
@@ -201,6 +998,43 @@ impl PartialEq for SecurityServiceResults {
     }
 }
 
+/// TLS certificate presented by an SMTP server after STARTTLS.
+#[derive(Debug, Serialize, Deserialize, Clone, PartialEq)]
+pub struct SmtpCertificate {
+    pub subject: String,
+    pub issuer: String,
+    pub subject_alt_names: Vec<String>,
+    pub not_before: String,
+    pub not_after: String,
+    pub expired: bool, // Outside its validity period
+    pub self_signed: bool, // Subject equals issuer
+}
+
+/// Result of probing a single SMTP host (banner, EHLO and STARTTLS).
+#[derive(Debug, Serialize, Deserialize, Clone, Default, PartialEq)]
+pub struct SmtpProbeResult {
+    pub host: String,
+    pub port: u16,
+    pub connected: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub banner: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub banner_hostname: Option<String>, // Host name announced in the banner or EHLO reply
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub software: Option<String>, // e.g., Microsoft Exchange
+    pub leaks_internal_hostname: bool,
+    pub capabilities: Vec<String>, // EHLO keywords
+    pub starttls_offered: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_version: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub tls_cipher: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub certificate: Option<SmtpCertificate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
 // --- Aggregated Results Structure ---
 
 #[derive(Debug, Serialize, Deserialize, Clone, Default)]
@@ -215,6 +1049,16 @@ pub struct ReconResults {
     pub azure_service_results: Option<AzureServiceResults>,
     pub aad_app_results: Option<AadAppResults>,
     pub security_service_results: Option<SecurityServiceResults>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub smtp_results: Option<Vec<SmtpProbeResult>>, // Only when SMTP probing is enabled
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub ntlm_results: Option<Vec<NtlmDisclosure>>, // Endpoints that answered an NTLM negotiate
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub exchange_results: Option<Vec<ExchangeFingerprint>>, // Self-hosted Exchange builds
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub provisioning: Option<ProvisioningResults>, // Intune/MDM enrollment and device registration
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub saml_results: Option<SamlResults>, // SAML/WS-Fed/WS-Trust endpoints
     // Add other result categories as needed
 }
 
@@ -1,6 +1,8 @@
 use crate::error::ReconError;
-use crate::models::ReconResults;
-use serde_json;
+use crate::models::{
+    AuthEndpoint, AuthProtocol, DiscoverySource, DnssecStatus, ExchangeTopology, FederationMetadataResults, HostingType, IpEnrichment, LyncDeployment, MailProvider, MailRouting, ReconResults, SharePointSiteKind, SpfNode,
+    SpfQualifier,
+};
 use std::io::{self, Write};
 
 /// Helper function to print a field with boolean value.
@@ -32,6 +34,43 @@ fn print_vec_field(writer: &mut impl Write, label: &str, values: &[String]) -> i
     }
 }
 
+/// Returns the SPF notation for a qualifier (e.g., `~` for SoftFail).
+fn spf_qualifier_symbol(qualifier: &SpfQualifier) -> &'static str {
+    match qualifier {
+        SpfQualifier::Pass => "+",
+        SpfQualifier::Fail => "-",
+        SpfQualifier::SoftFail => "~",
+        SpfQualifier::Neutral => "?",
+    }
+}
+
+/// Formats ASN and location of an address, e.g. "AS64500 Contoso Networks, Frankfurt, DE".
+fn enrichment_label(enrichment: &IpEnrichment) -> String {
+    let network = match (enrichment.asn, enrichment.as_organization.as_deref()) {
+        (Some(asn), Some(organization)) => Some(format!("AS{} {}", asn, organization)),
+        (Some(asn), None) => Some(format!("AS{}", asn)),
+        (None, organization) => organization.map(str::to_string),
+    };
+    let country = enrichment.country_code.clone().or_else(|| enrichment.country.clone());
+    [network, enrichment.city.clone(), country].into_iter().flatten().collect::<Vec<_>>().join(", ")
+}
+
+/// Helper function to print an SPF include/redirect tree, one record per line.
+fn print_spf_tree(writer: &mut impl Write, node: &SpfNode, depth: usize) -> io::Result<()> {
+    let indent = "  ".repeat(depth + 2);
+    let via = node.reached_via.as_deref().map_or(String::new(), |via| format!("{}:", via));
+    let all = node
+        .all_qualifier
+        .as_ref()
+        .map_or(String::new(), |q| format!(" [{}all]", spf_qualifier_symbol(q)));
+    let error = node.error.as_deref().map_or(String::new(), |e| format!(" ({})", e));
+    writeln!(writer, "{}- {}{}{}{}", indent, via, node.domain, all, error)?;
+    for child in &node.children {
+        print_spf_tree(writer, child, depth + 1)?;
+    }
+    Ok(())
+}
+
 /// Prints the reconnaissance results to standard output.
 ///
 /// Formats the output as JSON if `json_output` is true, otherwise prints
@@ -51,12 +90,286 @@ pub fn print_results(results: &ReconResults, json_output: bool) -> Result<(), Re
         if let Some(dns) = &results.dns_results {
             writeln!(handle, "\n[+] DNS Records:")?;
             print_bool_field(&mut handle, "MX Records Found", dns.mx_records_found)?;
+            if let Some(mx_hosts) = &dns.mx_hosts {
+                let mx_lines: Vec<String> = mx_hosts
+                    .iter()
+                    .map(|mx| {
+                        let gateway = if mx.secure_email_gateway { ", gateway" } else { "" };
+                        format!("{} {} ({:?}{})", mx.priority, mx.host, mx.provider, gateway)
+                    })
+                    .collect();
+                print_vec_field(&mut handle, "MX Hosts", &mx_lines)?;
+            }
+            if let Some(eop) = &dns.eop_direct_delivery {
+                print_string_field(&mut handle, "EOP Host", Some(eop.eop_host.as_str()))?;
+                print_bool_field(&mut handle, "EOP Host Resolves", Some(eop.resolves))?;
+                if eop.gateway_bypass_exposed {
+                    writeln!(handle, "  [!] Gateway Bypass: EOP accepts mail directly while MX points elsewhere")?;
+                }
+                print_bool_field(&mut handle, "EOP Accepts SMTP", eop.smtp_accepts_connections)?;
+            }
             print_bool_field(&mut handle, "SPF Record Found", dns.spf_record_found)?;
+            if let Some(spf) = &dns.spf_analysis {
+                writeln!(handle, "  SPF DNS Lookups: {} (limit 10)", spf.dns_lookup_count)?;
+                let all = spf.all_qualifier.as_ref().map(|q| format!("{}all", spf_qualifier_symbol(q)));
+                print_string_field(&mut handle, "SPF 'all' Qualifier", all.as_deref())?;
+                print_bool_field(&mut handle, "SPF Includes Exchange Online", spf.includes_exchange_online)?;
+                print_vec_field(&mut handle, "SPF IP Ranges", &spf.ip_ranges)?;
+                print_vec_field(&mut handle, "SPF Weaknesses", &spf.weaknesses)?;
+                writeln!(handle, "  SPF Tree:")?;
+                print_spf_tree(&mut handle, &spf.tree, 0)?;
+            }
             print_bool_field(&mut handle, "DMARC Record Found", dns.dmarc_record_found)?;
+            if let Some(dmarc) = &dns.dmarc_policy {
+                print_string_field(&mut handle, "DMARC Record Location", Some(dmarc.queried_name.as_str()))?;
+                let policy = dmarc.policy.as_ref().map(|p| format!("{:?}", p).to_lowercase());
+                print_string_field(&mut handle, "DMARC Policy (p)", policy.as_deref())?;
+                let subdomain_policy = dmarc.subdomain_policy.as_ref().map(|p| format!("{:?}", p).to_lowercase());
+                print_string_field(&mut handle, "DMARC Subdomain Policy (sp)", subdomain_policy.as_deref())?;
+                writeln!(handle, "  DMARC Percentage (pct): {}", dmarc.percentage)?;
+                writeln!(
+                    handle,
+                    "  DMARC Alignment (adkim/aspf): {:?}/{:?}",
+                    dmarc.dkim_alignment, dmarc.spf_alignment
+                )?;
+                let destinations: Vec<String> = dmarc
+                    .aggregate_reports
+                    .iter()
+                    .chain(dmarc.forensic_reports.iter())
+                    .map(|dest| match dest.authorized {
+                        Some(true) => format!("{} (external, authorized)", dest.uri),
+                        Some(false) => format!("{} (external, NOT authorized)", dest.uri),
+                        None => dest.uri.clone(),
+                    })
+                    .collect();
+                print_vec_field(&mut handle, "DMARC Report Destinations", &destinations)?;
+                print_vec_field(&mut handle, "DMARC Issues", &dmarc.issues)?;
+            }
+            if let Some(dkim) = &dns.dkim {
+                let selectors: Vec<String> = dkim
+                    .selectors
+                    .iter()
+                    .map(|selector| {
+                        let key = match (&selector.key_type, selector.key_bits) {
+                            _ if selector.revoked => "revoked".to_string(),
+                            (Some(key_type), Some(bits)) => format!("{} {}-bit", key_type, bits),
+                            (Some(key_type), None) => key_type.clone(),
+                            (None, _) => "no key".to_string(),
+                        };
+                        let m365 = if selector.microsoft_365 { ", Microsoft 365" } else { "" };
+                        let issues = if selector.issues.is_empty() {
+                            String::new()
+                        } else {
+                            format!(" - {}", selector.issues.join("; "))
+                        };
+                        format!("{} ({}{}){}", selector.selector, key, m365, issues)
+                    })
+                    .collect();
+                print_vec_field(&mut handle, "DKIM Selectors", &selectors)?;
+            }
+            if let Some(mta_sts) = &dns.mta_sts {
+                print_bool_field(&mut handle, "MTA-STS Policy Found", Some(mta_sts.policy_found))?;
+                let mode = mta_sts.mode.as_ref().map(|mode| format!("{:?}", mode).to_lowercase());
+                print_string_field(&mut handle, "MTA-STS Mode", mode.as_deref())?;
+                print_vec_field(&mut handle, "MTA-STS Issues", &mta_sts.issues)?;
+            }
+            print_bool_field(&mut handle, "TLS-RPT Record Found", Some(dns.tls_rpt.is_some()))?;
+            if let Some(bimi) = &dns.bimi {
+                print_string_field(&mut handle, "BIMI Logo", bimi.logo_url.as_deref())?;
+                print_string_field(&mut handle, "BIMI VMC", bimi.vmc_url.as_deref())?;
+                print_vec_field(&mut handle, "BIMI Issues", &bimi.issues)?;
+            }
+            if let Some(ns) = &dns.nameservers {
+                let providers: Vec<String> = ns.providers.iter().map(|provider| format!("{:?}", provider)).collect();
+                print_string_field(&mut handle, "DNS Provider", Some(providers.join(", ").as_str()))?;
+                if let Some(soa) = &ns.soa {
+                    writeln!(
+                        handle,
+                        "  SOA: {} {} (serial {})",
+                        soa.primary_ns, soa.responsible_mailbox, soa.serial
+                    )?;
+                }
+                let ns_lines: Vec<String> = ns
+                    .nameservers
+                    .iter()
+                    .map(|server| {
+                        let serial = server.soa_serial.map_or("no answer".to_string(), |serial| format!("serial {}", serial));
+                        format!("{} ({:?}, {})", server.host, server.provider, serial)
+                    })
+                    .collect();
+                print_vec_field(&mut handle, "Name Servers", &ns_lines)?;
+                print_bool_field(&mut handle, "Name Servers Consistent", Some(ns.consistent))?;
+                for issue in &ns.issues {
+                    writeln!(handle, "    - {}", issue)?;
+                }
+                for server in &ns.nameservers {
+                    match &server.zone_transfer {
+                        Some(axfr) if axfr.allowed => writeln!(
+                            handle,
+                            "  [!] Zone Transfer Allowed: {} ({} records)",
+                            server.host, axfr.record_count
+                        )?,
+                        Some(_) => writeln!(handle, "  Zone Transfer Refused: {}", server.host)?,
+                        None => {}
+                    }
+                }
+            }
+            if let Some(dnssec) = &dns.dnssec {
+                let status = match dnssec.status {
+                    DnssecStatus::Signed => "Signed",
+                    DnssecStatus::Unsigned => "Unsigned",
+                    DnssecStatus::Bogus => "Bogus",
+                    DnssecStatus::Indeterminate => "Indeterminate",
+                };
+                print_string_field(&mut handle, "DNSSEC Status", Some(status))?;
+                print_vec_field(&mut handle, "DNSSEC Algorithms (DNSKEY)", &dnssec.dnskey_algorithms)?;
+                print_string_field(&mut handle, "DNSSEC Validation Error", dnssec.validation_error.as_deref())?;
+            }
+            if let Some(mx_tlsa) = &dns.mx_tlsa {
+                let tlsa_lines: Vec<String> = mx_tlsa
+                    .iter()
+                    .filter(|mx| !mx.records.is_empty())
+                    .map(|mx| format!("_25._tcp.{} ({} TLSA records)", mx.mx_host, mx.records.len()))
+                    .collect();
+                print_vec_field(&mut handle, "MX TLSA Records", &tlsa_lines)?;
+            }
+            print_bool_field(&mut handle, "Inbound DANE", dns.inbound_dane)?;
             // Handle autodiscover which is now Option<String> not Option<bool>
             print_bool_field(&mut handle, "Autodiscover Present", Some(dns.autodiscover_cname_or_a.is_some()))?;
             print_bool_field(&mut handle, "LyncDiscover Present", dns.lyncdiscover_present)?;
             print_bool_field(&mut handle, "SIP CName/A Present", dns.sip_cname_or_a_present)?;
+            print_bool_field(&mut handle, "SIP Federation SRV Present", dns.sipfederationtls_tcp_present)?;
+            print_bool_field(&mut handle, "SIP TLS SRV Present", dns.sip_tls_present)?;
+            if let Some(srv_records) = &dns.srv_records {
+                let srv_lines: Vec<String> = srv_records
+                    .iter()
+                    .map(|srv| {
+                        let hosting = match srv.hosting {
+                            HostingType::MicrosoftHosted => "Microsoft-hosted",
+                            HostingType::OnPremises => "on-premises",
+                        };
+                        format!(
+                            "{} -> {}:{} (priority {}, weight {}, {})",
+                            srv.service, srv.target, srv.port, srv.priority, srv.weight, hosting
+                        )
+                    })
+                    .collect();
+                print_vec_field(&mut handle, "SRV Records", &srv_lines)?;
+            }
+            if let Some(chains) = &dns.cname_chains {
+                let chain_lines: Vec<String> = chains
+                    .iter()
+                    .map(|chain| {
+                        let status = if chain.nxdomain {
+                            " (NXDOMAIN)"
+                        } else if chain.loop_detected {
+                            " (loop)"
+                        } else {
+                            ""
+                        };
+                        format!("{}{}", chain.chain.join(" -> "), status)
+                    })
+                    .collect();
+                print_vec_field(&mut handle, "CNAME Chains", &chain_lines)?;
+            }
+            if let Some(enumeration) = &dns.subdomains {
+                writeln!(
+                    handle,
+                    "  Subdomain Discovery: {} found ({} words tried)",
+                    enumeration.subdomains.len(),
+                    enumeration.words_tried
+                )?;
+                if let Some(ct_names) = enumeration.ct_names_found {
+                    writeln!(handle, "    Certificate Transparency: {} names", ct_names)?;
+                }
+                if let Some(error) = &enumeration.ct_error {
+                    writeln!(handle, "    Certificate Transparency query failed: {}", error)?;
+                }
+                if enumeration.wildcard_detected {
+                    writeln!(
+                        handle,
+                        "    Wildcard zone ({}); {} matches filtered",
+                        enumeration.wildcard_targets.join(", "),
+                        enumeration.filtered_wildcard_matches
+                    )?;
+                }
+                let subdomain_lines: Vec<String> = enumeration
+                    .subdomains
+                    .iter()
+                    .map(|subdomain| {
+                        let target = subdomain
+                            .cname_target
+                            .clone()
+                            .unwrap_or_else(|| subdomain.addresses.join(", "));
+                        let hosting = match (&subdomain.azure_service, &subdomain.hosting) {
+                            (Some(service), _) => format!("Azure {}", service),
+                            (None, HostingType::MicrosoftHosted) => "Microsoft-hosted".to_string(),
+                            (None, HostingType::OnPremises) => "self-hosted".to_string(),
+                        };
+                        let source = match subdomain.source {
+                            DiscoverySource::Wordlist => "wordlist",
+                            DiscoverySource::CertificateTransparency => "CT",
+                        };
+                        format!("{} -> {} ({}, {})", subdomain.hostname, target, hosting, source)
+                    })
+                    .collect();
+                print_vec_field(&mut handle, "Subdomains", &subdomain_lines)?;
+            }
+            if let Some(hosts) = &dns.resolved_hosts {
+                let host_lines: Vec<String> = hosts
+                    .iter()
+                    .map(|host| {
+                        let addresses: Vec<&str> = host.ipv4.iter().chain(host.ipv6.iter()).map(String::as_str).collect();
+                        let mut tags: Vec<String> = Vec::new();
+                        for tag in &host.service_tag_matches {
+                            let label = match &tag.region {
+                                Some(region) if !tag.tag.contains('.') => format!("{} ({})", tag.tag, region),
+                                _ => tag.tag.clone(),
+                            };
+                            if !tags.contains(&label) {
+                                tags.push(label);
+                            }
+                        }
+                        let hosting = if tags.is_empty() { String::new() } else { format!(" [{}]", tags.join(", ")) };
+                        let mut locations: Vec<String> = Vec::new();
+                        for enrichment in &host.enrichment {
+                            let label = enrichment_label(enrichment);
+                            if !label.is_empty() && !locations.contains(&label) {
+                                locations.push(label);
+                            }
+                        }
+                        let location = if locations.is_empty() { String::new() } else { format!(" ({})", locations.join("; ")) };
+                        format!("{} -> {}{}{}", host.hostname, addresses.join(", "), hosting, location)
+                    })
+                    .collect();
+                print_vec_field(&mut handle, "Resolved Hosts", &host_lines)?;
+            }
+            for candidate in dns.takeover_candidates.as_deref().unwrap_or_default() {
+                writeln!(
+                    handle,
+                    "  [!] Subdomain Takeover ({}): {}",
+                    candidate.azure_service, candidate.hostname
+                )?;
+                writeln!(handle, "      Chain: {}", candidate.chain.join(" -> "))?;
+                writeln!(handle, "      {}", candidate.reason)?;
+            }
+            let region_scope = results
+                .azure_ad_config
+                .as_ref()
+                .and_then(|config| config.tenant_region_scope.as_deref())
+                .unwrap_or("unknown");
+            for host in dns.resolved_hosts.as_deref().unwrap_or_default() {
+                for enrichment in host.enrichment.iter().filter(|enrichment| enrichment.outside_tenant_region == Some(true)) {
+                    writeln!(
+                        handle,
+                        "  [!] Outside Tenant Region ({}): {} -> {} ({})",
+                        region_scope,
+                        host.hostname,
+                        enrichment.address,
+                        enrichment_label(enrichment)
+                    )?;
+                }
+            }
         }
 
         if let Some(tenant) = &results.tenant_info {
@@ -74,6 +387,9 @@ pub fn print_results(results: &ReconResults, json_output: bool) -> Result<(), Re
             print_string_field(&mut handle, "Federation Brand Name", federation.federation_brand_name.as_deref())?;
             print_string_field(&mut handle, "Namespace Type", federation.name_space_type.as_deref())?;
             print_string_field(&mut handle, "Authentication URL", federation.auth_url.as_deref())?;
+            if federation.mex_url.is_some() {
+                print_string_field(&mut handle, "MEX URL", federation.mex_url.as_deref())?;
+            }
             print_string_field(&mut handle, "Cloud Instance Name", federation.cloud_instance_name.as_deref())?;
         }
 
@@ -100,10 +416,286 @@ pub fn print_results(results: &ReconResults, json_output: bool) -> Result<(), Re
         if let Some(m365) = &results.m365_results {
             writeln!(handle, "\n[+] M365 Services:")?;
             print_bool_field(&mut handle, "SharePoint Detected", m365.sharepoint_detected)?;
-            print_bool_field(&mut handle, "Teams Detected (via DNS)", m365.teams_detected)?;
+            if let Some(sharepoint) = &m365.sharepoint {
+                let tenant_name = sharepoint.tenant_name.as_ref().map(|name| {
+                    let source = if sharepoint.tenant_name_discovered { "discovered" } else { "guessed from domain" };
+                    format!("{} ({})", name, source)
+                });
+                print_string_field(&mut handle, "SharePoint Tenant", tenant_name.as_deref())?;
+                if sharepoint.tenant_name.is_none() {
+                    print_vec_field(&mut handle, "SharePoint Names Tried", &sharepoint.candidates_tried)?;
+                }
+                let site_lines: Vec<String> = sharepoint
+                    .sites
+                    .iter()
+                    .map(|site| {
+                        let kind = match site.kind {
+                            SharePointSiteKind::Root => "Root",
+                            SharePointSiteKind::OneDrive => "OneDrive",
+                            SharePointSiteKind::Admin => "Admin",
+                        };
+                        let state = if site.exists { "exists" } else { "not found" };
+                        format!("{}: {} ({})", kind, site.url, state)
+                    })
+                    .collect();
+                print_vec_field(&mut handle, "SharePoint Sites", &site_lines)?;
+            }
+            print_bool_field(&mut handle, "Teams Detected (via lyncdiscover)", m365.teams_detected)?;
+            if let Some(lync) = &m365.lync_discover {
+                let deployment = match lync.deployment {
+                    LyncDeployment::SkypeOnline => "Skype for Business Online".to_string(),
+                    LyncDeployment::OnPremises => format!(
+                        "On-Premises Lync/Skype for Business (pool {})",
+                        lync.pool_fqdn.as_deref().unwrap_or("unknown")
+                    ),
+                    LyncDeployment::TeamsOnly => "Teams Only".to_string(),
+                };
+                print_string_field(&mut handle, "Skype/Teams Deployment", Some(deployment.as_str()))?;
+                print_string_field(&mut handle, "  Lyncdiscover User Link", lync.user_link.as_deref())?;
+                print_string_field(&mut handle, "  Lyncdiscover XFrame Link", lync.xframe_link.as_deref())?;
+            }
             print_bool_field(&mut handle, "Tenant Branding Accessible", m365.tenant_branding_accessible)?;
             print_bool_field(&mut handle, "Legacy Auth (EWS)", m365.legacy_auth_ews_enabled)?;
             print_bool_field(&mut handle, "Legacy Auth (ActiveSync)", m365.legacy_auth_activesync_enabled)?;
+            if !m365.auth_endpoints.is_empty() {
+                let endpoint_line = |endpoint: &AuthEndpoint| {
+                    let protocol = match endpoint.protocol {
+                        AuthProtocol::Ews => "EWS",
+                        AuthProtocol::ActiveSync => "ActiveSync",
+                        AuthProtocol::Autodiscover => "Autodiscover",
+                        AuthProtocol::SmtpAuth => "SMTP AUTH",
+                        AuthProtocol::Pop => "POP",
+                        AuthProtocol::Imap => "IMAP",
+                    };
+                    let schemes = if !endpoint.reachable {
+                        "unreachable".to_string()
+                    } else if endpoint.schemes.is_empty() {
+                        "no challenge".to_string()
+                    } else {
+                        endpoint.schemes.join(", ")
+                    };
+                    // Exchange Online answers the same for every caller, so only self-hosted Basic auth is a finding
+                    let warning = if endpoint.basic_auth && endpoint.on_premises { " [!] password auth" } else { "" };
+                    format!("{} ({}): {}{}", protocol, endpoint.endpoint, schemes, warning)
+                };
+                let (on_premises, cloud): (Vec<&AuthEndpoint>, Vec<&AuthEndpoint>) =
+                    m365.auth_endpoints.iter().partition(|endpoint| endpoint.on_premises);
+                if !on_premises.is_empty() {
+                    let lines: Vec<String> = on_premises.into_iter().map(endpoint_line).collect();
+                    print_vec_field(&mut handle, "Authentication Schemes (on-premises)", &lines)?;
+                }
+                if !cloud.is_empty() {
+                    let lines: Vec<String> = cloud.into_iter().map(endpoint_line).collect();
+                    print_vec_field(&mut handle, "Exchange Online Challenges (shared, informational)", &lines)?;
+                }
+            }
+            if let Some(autodiscover) = &m365.autodiscover {
+                let topology = m365.exchange_topology.as_ref().map(|topology| match topology {
+                    ExchangeTopology::CloudOnly => "Cloud Only (Exchange Online)",
+                    ExchangeTopology::Hybrid => "Hybrid (Exchange Online and on-premises)",
+                    ExchangeTopology::OnPremises => "On-Premises Exchange",
+                });
+                print_string_field(&mut handle, "Exchange Topology", topology)?;
+                let mut autodiscover_lines: Vec<String> = autodiscover
+                    .v2
+                    .iter()
+                    .map(|answer| match (&answer.url, &answer.error) {
+                        (Some(url), _) => {
+                            let location = if answer.on_premises { " (on-premises)" } else { "" };
+                            format!("{}: {}{}", answer.protocol, url, location)
+                        }
+                        (None, error) => format!("{}: {}", answer.protocol, error.as_deref().unwrap_or("no answer")),
+                    })
+                    .collect();
+                if let Some(pox_url) = &autodiscover.pox_url {
+                    let answer = match (autodiscover.pox_status, &autodiscover.pox_final_url) {
+                        (Some(status), Some(final_url)) if final_url != pox_url => format!("HTTP {} via {}", status, final_url),
+                        (Some(status), _) => format!("HTTP {}", status),
+                        (None, _) => "unreachable".to_string(),
+                    };
+                    let warning = if autodiscover.pox_on_premises { " [!] on-premises Exchange" } else { "" };
+                    autodiscover_lines.push(format!("POX: {} ({}){}", pox_url, answer, warning));
+                }
+                print_vec_field(&mut handle, &format!("Autodiscover ({})", autodiscover.address), &autodiscover_lines)?;
+            }
+            if let Some(power_pages) = &m365.power_pages {
+                let site_lines: Vec<String> = power_pages
+                    .sites
+                    .iter()
+                    .map(|site| {
+                        let name = site.display_name.as_deref().map(|name| format!(" \"{}\"", name)).unwrap_or_default();
+                        let mut exposed: Vec<String> = site.odata_feeds.iter().map(|feed| format!("/_odata/{}", feed)).collect();
+                        exposed.extend(site.exposed_tables.iter().map(|table| format!("/_api/{}", table)));
+                        let warning = if site.anonymous_data_access {
+                            format!(" [!] anonymous data access: {}", exposed.join(", "))
+                        } else {
+                            String::new()
+                        };
+                        format!("{}{}{}", site.url, name, warning)
+                    })
+                    .collect();
+                print_vec_field(&mut handle, "Power Pages Sites", &site_lines)?;
+            }
+            if let Some(dynamics) = &m365.dynamics {
+                let environment_lines: Vec<String> = dynamics
+                    .environments
+                    .iter()
+                    .map(|environment| {
+                        let tenant = match (&environment.tenant_id, environment.tenant_confirmed) {
+                            (Some(_), true) => ", tenant confirmed".to_string(),
+                            (Some(tenant_id), false) => format!(", other tenant {}", tenant_id),
+                            (None, _) => String::new(),
+                        };
+                        format!("{} ({}{})", environment.url, environment.geography, tenant)
+                    })
+                    .collect();
+                print_vec_field(&mut handle, "Dynamics 365 Environments", &environment_lines)?;
+            }
+            if let Some(routing) = &m365.mail_routing {
+                let routing = match routing {
+                    MailRouting::ExchangeOnlineDirect => "Direct to Exchange Online".to_string(),
+                    MailRouting::GatewayToExchangeOnline => format!(
+                        "Third-party gateway ({:?}) in front of Exchange Online",
+                        m365.mail_gateway.as_ref().unwrap_or(&MailProvider::OnPremises)
+                    ),
+                    MailRouting::ThirdPartyGateway => format!(
+                        "Third-party gateway ({:?})",
+                        m365.mail_gateway.as_ref().unwrap_or(&MailProvider::OnPremises)
+                    ),
+                    MailRouting::OtherProvider => "Non-Microsoft provider or on-premises".to_string(),
+                    MailRouting::NoMx => "No MX records".to_string(),
+                };
+                print_string_field(&mut handle, "Mail Routing", Some(routing.as_str()))?;
+            }
+        }
+
+        if let Some(probes) = &results.smtp_results {
+            writeln!(handle, "\n[+] SMTP Probe:")?;
+            for probe in probes {
+                writeln!(handle, "  {}:{}", probe.host, probe.port)?;
+                if let Some(error) = &probe.error {
+                    writeln!(handle, "    Error: {}", error)?;
+                }
+                print_string_field(&mut handle, "  Banner", probe.banner.as_deref())?;
+                print_string_field(&mut handle, "  Software", probe.software.as_deref())?;
+                if probe.leaks_internal_hostname {
+                    writeln!(
+                        handle,
+                        "    [!] Internal host name disclosed: {}",
+                        probe.banner_hostname.as_deref().unwrap_or("unknown")
+                    )?;
+                }
+                if probe.connected {
+                    print_bool_field(&mut handle, "  STARTTLS Offered", Some(probe.starttls_offered))?;
+                }
+                print_string_field(&mut handle, "  TLS Version", probe.tls_version.as_deref())?;
+                print_string_field(&mut handle, "  TLS Cipher", probe.tls_cipher.as_deref())?;
+                if let Some(cert) = &probe.certificate {
+                    print_string_field(&mut handle, "  Certificate Subject", Some(cert.subject.as_str()))?;
+                    print_string_field(&mut handle, "  Certificate Issuer", Some(cert.issuer.as_str()))?;
+                    print_vec_field(&mut handle, "  Certificate SANs", &cert.subject_alt_names)?;
+                    writeln!(handle, "    Valid: {} to {}", cert.not_before, cert.not_after)?;
+                    if cert.expired {
+                        writeln!(handle, "    [!] Certificate expired")?;
+                    }
+                    if cert.self_signed {
+                        writeln!(handle, "    [!] Certificate is self-signed")?;
+                    }
+                }
+            }
+        }
+
+        if let Some(disclosures) = results.ntlm_results.as_deref().filter(|disclosures| !disclosures.is_empty()) {
+            writeln!(handle, "\n[+] NTLM Challenge Disclosure:")?;
+            for disclosure in disclosures {
+                let info = &disclosure.target_info;
+                writeln!(handle, "  [!] {} ({})", disclosure.url, disclosure.service)?;
+                print_string_field(&mut handle, "  NetBIOS Domain", info.netbios_domain.as_deref())?;
+                print_string_field(&mut handle, "  DNS Domain", info.dns_domain.as_deref())?;
+                print_string_field(&mut handle, "  DNS Forest", info.dns_forest.as_deref())?;
+                print_string_field(&mut handle, "  Server", info.dns_computer.as_deref().or(info.netbios_computer.as_deref()))?;
+                let os = info.os_version.as_ref().map(|version| match &info.os_name {
+                    Some(name) => format!("{} ({})", version, name),
+                    None => version.clone(),
+                });
+                print_string_field(&mut handle, "  OS Version", os.as_deref())?;
+                print_string_field(&mut handle, "  Server Time", info.timestamp.as_deref())?;
+            }
+        }
+
+        if let Some(fingerprints) = results.exchange_results.as_deref().filter(|fingerprints| !fingerprints.is_empty()) {
+            writeln!(handle, "\n[+] On-Premises Exchange:")?;
+            for fingerprint in fingerprints {
+                writeln!(handle, "  {}", fingerprint.host)?;
+                let release = fingerprint.version.as_ref().map(|version| {
+                    match (&fingerprint.cumulative_update, &fingerprint.release_date) {
+                        (Some(update), Some(date)) => format!("{} {} ({})", version, update, date),
+                        _ => format!("{} (build older than known releases)", version),
+                    }
+                });
+                print_string_field(&mut handle, "  Version", release.as_deref())?;
+                writeln!(handle, "    Build: {} (from {})", fingerprint.build, fingerprint.source)?;
+                if fingerprint.outdated {
+                    writeln!(handle, "    [!] Outdated build")?;
+                }
+            }
+        }
+
+        if let Some(provisioning) = &results.provisioning {
+            writeln!(handle, "\n[+] Device Provisioning:")?;
+            print_string_field(&mut handle, "EnterpriseEnrollment Target", provisioning.enrollment_target.as_deref())?;
+            print_string_field(&mut handle, "EnterpriseRegistration Target", provisioning.registration_target.as_deref())?;
+            print_bool_field(&mut handle, "Intune Auto-Enrollment", Some(provisioning.intune_auto_enrollment))?;
+            print_bool_field(&mut handle, "On-Premises MDM/NDES", Some(provisioning.on_premises_mdm))?;
+            print_bool_field(&mut handle, "Hybrid Join Infrastructure", Some(provisioning.hybrid_join_infrastructure))?;
+            if let Some(discovery) = &provisioning.mdm_discovery {
+                print_string_field(&mut handle, "MDM Auth Policy", discovery.auth_policy.as_deref())?;
+                print_string_field(&mut handle, "MDM Enrollment Service", discovery.enrollment_service_url.as_deref())?;
+            }
+            if let Some(registration) = &provisioning.device_registration {
+                let source = if registration.on_premises { " (self-hosted DRS)" } else { "" };
+                let endpoint = registration.registration_endpoint.as_ref().map(|endpoint| format!("{}{}", endpoint, source));
+                print_string_field(&mut handle, "Device Registration Endpoint", endpoint.as_deref())?;
+                print_bool_field(&mut handle, "Device Registration Federated", registration.federated)?;
+            }
+            if !provisioning.ndes_endpoints.is_empty() {
+                print_vec_field(&mut handle, "NDES Endpoints", &provisioning.ndes_endpoints)?;
+            }
+        }
+
+        if let Some(saml) = &results.saml_results {
+            writeln!(handle, "\n[+] SAML / WS-Federation:")?;
+            let metadata_lines = |metadata: &FederationMetadataResults| -> Vec<String> {
+                metadata
+                    .endpoints
+                    .iter()
+                    .map(|endpoint| match &endpoint.binding {
+                        Some(binding) => format!("{} {}: {}", endpoint.protocol, binding, endpoint.location),
+                        None => format!("{}: {}", endpoint.protocol, endpoint.location),
+                    })
+                    .collect()
+            };
+            if let Some(metadata) = &saml.entra_metadata {
+                print_vec_field(&mut handle, "Entra Sign-On Endpoints", &metadata_lines(metadata))?;
+            }
+            if let Some(host) = &saml.idp_host {
+                print_string_field(&mut handle, "Identity Provider", Some(host.as_str()))?;
+                if let Some(metadata) = &saml.idp_metadata {
+                    print_string_field(&mut handle, "  Entity ID", metadata.entity_id.as_deref())?;
+                    print_vec_field(&mut handle, "  Sign-On Endpoints", &metadata_lines(metadata))?;
+                }
+                print_bool_field(&mut handle, "  IdP-Initiated Sign-On", saml.idp_initiated_signon)?;
+                let exposed: Vec<String> = saml
+                    .ws_trust_endpoints
+                    .iter()
+                    .filter(|endpoint| endpoint.exposed)
+                    .map(|endpoint| endpoint.url.clone())
+                    .collect();
+                print_vec_field(&mut handle, "  Exposed WS-Trust Endpoints", &exposed)?;
+                if saml.usernamemixed_exposed {
+                    writeln!(handle, "    [!] WS-Trust usernamemixed exposed: accepts user names and passwords directly (password spraying)")?;
+                }
+            }
         }
 
         if let Some(azure) = &results.azure_service_results {
@@ -111,6 +703,9 @@ pub fn print_results(results: &ReconResults, json_output: bool) -> Result<(), Re
             print_vec_field(&mut handle, "Probable App Services", &azure.probable_app_services)?;
             print_vec_field(&mut handle, "Probable Storage Accounts", &azure.probable_storage_accounts)?;
             print_vec_field(&mut handle, "Probable CDN Endpoints", &azure.probable_cdn_endpoints)?;
+            if !azure.azure_fronted_hosts.is_empty() {
+                print_vec_field(&mut handle, "Azure-Fronted Subdomains", &azure.azure_fronted_hosts)?;
+            }
         }
 
         writeln!(handle, "\n--- End of Report ---")?;
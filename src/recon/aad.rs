@@ -1,6 +1,7 @@
 use crate::config::CloudConfig;
 use crate::error::ReconError;
 use crate::models::{AadConnectStatus, AzureAdConfig};
+use crate::recon::common::is_guid;
 use reqwest::Client;
 use serde::Deserialize;
 use tracing::{debug, info, warn};
@@ -17,22 +18,28 @@ struct OpenIdConfigResponse {
     // We can ignore other fields
 }
 
-/// Fetches the Azure AD OpenID configuration.
+/// Fetches the Azure AD OpenID configuration of the domain's tenant.
+///
+/// Issued per domain, so the issuer and endpoints carry the tenant ID (see `tenant_id_from_config`).
 pub async fn get_azure_ad_config(
     client: Client, // Pass cloned client
-    _domain: String, // Keep domain for potential future use, mark unused for now
+    domain: String, // Pass owned domain
     config: CloudConfig, // Pass cloned config
 ) -> Result<AzureAdConfig, ReconError> {
-    // Construct the full OpenID configuration URL
-    let base_url = Url::parse(&config.login_endpoint)?;
-    let config_url = base_url.join(&config.openid_config_endpoint)?;
+    // Construct the full OpenID configuration URL, e.g. https://login.microsoftonline.com/contoso.com/.well-known/openid-configuration
+    let config_url = Url::parse(&format!(
+        "{}/{}{}",
+        config.login_endpoint.trim_end_matches('/'),
+        domain,
+        config.openid_config_endpoint
+    ))?;
 
-    debug!(target = _domain, url = config_url.as_str(), "Querying OpenID Config");
+    debug!(target = domain, url = config_url.as_str(), "Querying OpenID Config");
 
     let response = client.get(config_url.clone()).send().await?;
 
     if !response.status().is_success() {
-        warn!(target = _domain, status = %response.status(), url = config_url.as_str(), "OpenID Config request failed");
+        warn!(target = domain, status = %response.status(), url = config_url.as_str(), "OpenID Config request failed");
         return Err(ReconError::UnexpectedApiResponse {
             service: "OpenID Configuration".to_string(),
             status: response.status(),
@@ -41,7 +48,7 @@ pub async fn get_azure_ad_config(
     }
 
     let config_data: OpenIdConfigResponse = response.json().await?;
-    debug!(target = _domain, "OpenID Config response parsed successfully");
+    debug!(target = domain, "OpenID Config response parsed successfully");
 
     // Map the deserialized fields to our AzureAdConfig model
     Ok(AzureAdConfig {
@@ -53,6 +60,19 @@ pub async fn get_azure_ad_config(
     })
 }
 
+/// Tenant ID from the OpenID configuration: the first path segment of the issuer
+/// (`https://sts.windows.net/<guid>/`) or, failing that, of the token endpoint.
+pub fn tenant_id_from_config(config: &AzureAdConfig) -> Option<String> {
+    [&config.issuer, &config.token_endpoint]
+        .into_iter()
+        .flatten()
+        .filter_map(|endpoint| Url::parse(endpoint).ok())
+        .find_map(|url| {
+            let segment = url.path_segments()?.next()?.to_string();
+            is_guid(&segment).then(|| segment.to_lowercase())
+        })
+}
+
 /// Checks the Azure AD Connect status by probing the Seamless SSO endpoint.
 ///
 /// Infers Hybrid status if the endpoint is reachable, CloudOnly otherwise.
@@ -100,4 +120,31 @@ pub async fn check_aad_connect_status(
             }
         }
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tenant_id_from_config() {
+        let config = AzureAdConfig {
+            issuer: Some("https://sts.windows.net/72F988BF-86F1-41AF-91AB-2D7CD011DB47/".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(tenant_id_from_config(&config).as_deref(), Some("72f988bf-86f1-41af-91ab-2d7cd011db47"));
+
+        let config = AzureAdConfig {
+            issuer: Some("https://sts.windows.net/{tenantid}/".to_string()),
+            token_endpoint: Some("https://login.microsoftonline.com/72f988bf-86f1-41af-91ab-2d7cd011db47/oauth2/token".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(tenant_id_from_config(&config).as_deref(), Some("72f988bf-86f1-41af-91ab-2d7cd011db47"));
+
+        let config = AzureAdConfig {
+            token_endpoint: Some("https://login.microsoftonline.com/common/oauth2/token".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(tenant_id_from_config(&config), None);
+    }
+}
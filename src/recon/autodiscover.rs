@@ -0,0 +1,197 @@
+use crate::config::CloudConfig;
+use crate::error::ReconError;
+use crate::models::{AutodiscoverResults, AutodiscoverV2Result, DnsResults, ExchangeTopology, HostingType};
+use crate::recon::common::PROBE_USER;
+use crate::recon::dns::classify_hosting;
+use futures::future::join_all;
+use reqwest::header::{CONTENT_TYPE, WWW_AUTHENTICATE};
+use reqwest::{Client, StatusCode, Url};
+use serde::Deserialize;
+use tracing::{debug, info};
+
+/// Protocols requested from the Autodiscover v2 endpoint.
+const AUTODISCOVER_V2_PROTOCOLS: &[&str] = &["EWS", "ActiveSync", "REST"];
+
+/// Autodiscover v2 (JSON) response, e.g. `{"Protocol":"EWS","Url":"https://outlook.office365.com/EWS/Exchange.asmx"}`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct AutodiscoverV2Response {
+    url: Option<String>,
+    error_code: Option<String>,
+    error_message: Option<String>,
+}
+
+/// Asks Autodiscover where the domain's mailboxes are served from.
+///
+/// The Exchange Online Autodiscover v2 endpoint returns its own URLs for cloud mailboxes and
+/// the on-premises URLs for hybrid domains. The legacy POX endpoint at `autodiscover.<domain>`
+/// is also queried when it is not hosted by Microsoft.
+///
+/// Exchange Online also answers with its own URLs for domains it does not host, so cloud
+/// answers only count when `m365_evidence` (realm discovery or mail routing) shows a tenant.
+pub async fn check_autodiscover_endpoints(
+    client: Client,
+    domain: String,
+    config: CloudConfig,
+    dns_results: Option<DnsResults>,
+    m365_evidence: bool,
+) -> Result<AutodiscoverResults, ReconError> {
+    let address = format!("{}@{}", PROBE_USER, domain);
+    let base_url = format!("https://{}", config.ews_endpoint_host);
+    let v2 = join_all(
+        AUTODISCOVER_V2_PROTOCOLS
+            .iter()
+            .map(|protocol| query_autodiscover_v2(&client, &base_url, &address, protocol)),
+    )
+    .await;
+    let mut results = AutodiscoverResults {
+        address,
+        v2,
+        m365_evidence,
+        ..Default::default()
+    };
+
+    let self_hosted = dns_results
+        .as_ref()
+        .and_then(|dns| dns.autodiscover_cname_or_a.as_deref())
+        .is_some_and(|target| classify_hosting(target) == HostingType::OnPremises);
+    if self_hosted {
+        let pox_url = format!("https://autodiscover.{}/autodiscover/autodiscover.xml", domain);
+        query_autodiscover_pox(&client, &pox_url, &mut results).await;
+        results.pox_url = Some(pox_url);
+    }
+
+    info!(
+        domain = domain.as_str(),
+        topology = ?exchange_topology(&results),
+        "Finished Autodiscover checks"
+    );
+    Ok(results)
+}
+
+/// Requests the service URL for `protocol` from an Autodiscover v2 endpoint.
+pub async fn query_autodiscover_v2(client: &Client, base_url: &str, address: &str, protocol: &str) -> AutodiscoverV2Result {
+    let url = format!("{}/autodiscover/autodiscover.json/v1.0/{}", base_url.trim_end_matches('/'), address);
+    debug!(url = url.as_str(), protocol, "Querying Autodiscover v2");
+    let mut result = AutodiscoverV2Result {
+        protocol: protocol.to_string(),
+        url: None,
+        on_premises: false,
+        error: None,
+    };
+
+    let response = match client.get(&url).query(&[("Protocol", protocol)]).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            result.error = Some(e.to_string());
+            return result;
+        }
+    };
+    let status = response.status();
+    match response.json::<AutodiscoverV2Response>().await {
+        Ok(AutodiscoverV2Response { url: Some(url), .. }) => {
+            result.on_premises = Url::parse(&url)
+                .ok()
+                .and_then(|url| url.host_str().map(classify_hosting))
+                .is_some_and(|hosting| hosting == HostingType::OnPremises);
+            result.url = Some(url);
+        }
+        Ok(response) => {
+            result.error = Some(match (response.error_code, response.error_message) {
+                (Some(code), Some(message)) => format!("{}: {}", code, message),
+                (code, message) => code.or(message).unwrap_or_else(|| format!("No URL returned (HTTP {})", status)),
+            });
+        }
+        Err(e) => result.error = Some(format!("Unexpected response (HTTP {}): {}", status, e)),
+    }
+    result
+}
+
+/// Posts a legacy (POX) Autodiscover request and records whether an Exchange server answered.
+async fn query_autodiscover_pox(client: &Client, url: &str, results: &mut AutodiscoverResults) {
+    let body = format!(
+        concat!(
+            r#"<?xml version="1.0" encoding="utf-8"?>"#,
+            r#"<Autodiscover xmlns="http://schemas.microsoft.com/exchange/autodiscover/outlook/requestschema/2006">"#,
+            "<Request><EMailAddress>{}</EMailAddress>",
+            "<AcceptableResponseSchema>http://schemas.microsoft.com/exchange/autodiscover/outlook/responseschema/2006a</AcceptableResponseSchema>",
+            "</Request></Autodiscover>"
+        ),
+        results.address
+    );
+    debug!(url, "Querying Autodiscover (POX)");
+    match client.post(url).header(CONTENT_TYPE, "text/xml").body(body).send().await {
+        Ok(response) => {
+            let status = response.status();
+            let final_url = response.url().clone();
+            let challenged = response.headers().contains_key(WWW_AUTHENTICATE);
+            let answered = match status {
+                StatusCode::UNAUTHORIZED => challenged,
+                StatusCode::OK => response.text().await.unwrap_or_default().contains("<Autodiscover"),
+                _ => false,
+            };
+            results.pox_on_premises =
+                answered && final_url.host_str().is_some_and(|host| classify_hosting(host) == HostingType::OnPremises);
+            results.pox_status = Some(status.as_u16());
+            results.pox_final_url = Some(final_url.to_string());
+        }
+        Err(e) => debug!(url, error = %e, "Autodiscover (POX) request failed"),
+    }
+}
+
+/// Derives the Exchange topology from the Autodiscover answers.
+///
+/// Cloud answers are ignored without other evidence of an M365 tenant.
+pub fn exchange_topology(results: &AutodiscoverResults) -> Option<ExchangeTopology> {
+    let cloud = results.m365_evidence && results.v2.iter().any(|answer| answer.url.is_some() && !answer.on_premises);
+    let on_premises = results.v2.iter().any(|answer| answer.on_premises) || results.pox_on_premises;
+    match (cloud, on_premises) {
+        (true, true) => Some(ExchangeTopology::Hybrid),
+        (true, false) => Some(ExchangeTopology::CloudOnly),
+        (false, true) => Some(ExchangeTopology::OnPremises),
+        (false, false) => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(url: Option<&str>, on_premises: bool) -> AutodiscoverV2Result {
+        AutodiscoverV2Result {
+            protocol: "EWS".to_string(),
+            url: url.map(str::to_string),
+            on_premises,
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_exchange_topology() {
+        let mut results = AutodiscoverResults {
+            v2: vec![answer(Some("https://outlook.office365.com/EWS/Exchange.asmx"), false)],
+            m365_evidence: true,
+            ..Default::default()
+        };
+        assert_eq!(exchange_topology(&results), Some(ExchangeTopology::CloudOnly));
+
+        results.pox_on_premises = true;
+        assert_eq!(exchange_topology(&results), Some(ExchangeTopology::Hybrid));
+
+        results.v2 = vec![answer(Some("https://mail.contoso.com/EWS/Exchange.asmx"), true), answer(None, false)];
+        assert_eq!(exchange_topology(&results), Some(ExchangeTopology::OnPremises));
+
+        assert_eq!(exchange_topology(&AutodiscoverResults::default()), None);
+    }
+
+    #[test]
+    fn test_exchange_topology_needs_m365_evidence_for_cloud_answers() {
+        let mut results = AutodiscoverResults {
+            v2: vec![answer(Some("https://outlook.office365.com/EWS/Exchange.asmx"), false)],
+            ..Default::default()
+        };
+        assert_eq!(exchange_topology(&results), None);
+        results.pox_on_premises = true;
+        assert_eq!(exchange_topology(&results), Some(ExchangeTopology::OnPremises));
+    }
+}
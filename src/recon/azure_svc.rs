@@ -1,6 +1,6 @@
 use crate::config::CloudConfig;
 use crate::error::ReconError;
-use crate::models::AzureServiceResults;
+use crate::models::{AzureServiceResults, DnsResults};
 use reqwest::Client;
 use tracing::{debug, info, warn};
 
@@ -9,6 +9,7 @@ pub async fn run_azure_service_checks(
     client: Client, // Pass cloned client
     domain: String, // Pass owned domain
     cloud_config: CloudConfig, // Pass cloned config
+    dns_results: Option<DnsResults>, // Discovered subdomains, if enumeration ran
 ) -> Result<AzureServiceResults, ReconError> {
     info!(target = domain, "Starting Azure service checks");
 
@@ -48,12 +49,31 @@ pub async fn run_azure_service_checks(
         probable_app_services: app_service_url.map_or_else(Vec::new, |url| vec![url]),
         probable_storage_accounts: storage_url.map_or_else(Vec::new, |url| vec![url]),
         probable_cdn_endpoints: cdn_url.map_or_else(Vec::new, |url| vec![url]),
+        azure_fronted_hosts: azure_fronted_hosts(dns_results.as_ref()),
     };
 
     info!(target = domain.as_str(), "Finished Azure service checks");
     Ok(results)
 }
 
+/// Lists the discovered subdomains whose CNAME points into an Azure service.
+fn azure_fronted_hosts(dns_results: Option<&DnsResults>) -> Vec<String> {
+    dns_results
+        .and_then(|dns| dns.subdomains.as_ref())
+        .map(|discovery| {
+            discovery
+                .subdomains
+                .iter()
+                .filter_map(|subdomain| {
+                    let service = subdomain.azure_service.as_deref()?;
+                    let target = subdomain.cname_target.as_deref().unwrap_or_default();
+                    Some(format!("{} -> {} ({})", subdomain.hostname, target, service))
+                })
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
 /// Checks for the presence of Azure App Services.
 ///
 /// Constructs the expected App Service URL (domain.azurewebsites.net) and probes it.
@@ -1,6 +1,6 @@
 use crate::config::AppConfig;
 use crate::error::ReconError;
-use reqwest::{Client, header};
+use reqwest::{header, redirect, Client, ClientBuilder};
 use std::time::Duration;
 
 /// Creates a new shared reqwest HTTP client instance.
@@ -8,6 +8,17 @@ use std::time::Duration;
 /// Configures the client with a timeout and a default user agent
 /// based on the application configuration.
 pub fn new_client(config: &AppConfig) -> Result<Client, ReconError> {
+    build_client(client_builder(config)?)
+}
+
+/// Creates a client like `new_client` that does not follow redirects.
+///
+/// For fetches that must not follow 3xx responses, such as MTA-STS policies (RFC 8461 §3.3).
+pub fn new_no_redirect_client(config: &AppConfig) -> Result<Client, ReconError> {
+    build_client(client_builder(config)?.redirect(redirect::Policy::none()))
+}
+
+fn client_builder(config: &AppConfig) -> Result<ClientBuilder, ReconError> {
     // Default to 30 seconds if not specified in config
     let timeout = Duration::from_secs(config.request_timeout_seconds.unwrap_or(30));
     let user_agent = &config.default_user_agent;
@@ -17,13 +28,15 @@ pub fn new_client(config: &AppConfig) -> Result<Client, ReconError> {
         .map_err(|e| ReconError::Config(config::ConfigError::Foreign(Box::new(e))))? // Convert header error to ConfigError
     );
 
-    let client = Client::builder()
+    Ok(Client::builder()
         .timeout(timeout)
-        .default_headers(headers)
+        .default_headers(headers))
         // TODO: Configure TLS settings if necessary (e.g., accept invalid certs - use with caution!)
         // .danger_accept_invalid_certs(true)
-        .build()
-        .map_err(|e| ReconError::Config(config::ConfigError::Foreign(Box::new(e))))?; // Convert reqwest client error to ConfigError
+}
 
-    Ok(client)
-}
\ No newline at end of file
+fn build_client(builder: ClientBuilder) -> Result<Client, ReconError> {
+    builder
+        .build()
+        .map_err(|e| ReconError::Config(config::ConfigError::Foreign(Box::new(e)))) // Convert reqwest client error to ConfigError
+}
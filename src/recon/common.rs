@@ -0,0 +1,124 @@
+use std::collections::hash_map::RandomState;
+use std::hash::{BuildHasher, Hasher};
+use std::time::Duration;
+use tokio::sync::Mutex;
+use tokio::time::{interval, Interval, MissedTickBehavior};
+
+/// Upper bound on DNS lookups in flight; the QPS limit is what actually paces them.
+pub const MAX_CONCURRENT_LOOKUPS: usize = 32;
+
+/// First labels of host names commonly used for self-hosted Exchange front ends (OWA, EWS, ActiveSync).
+pub const EXCHANGE_HOST_LABELS: &[&str] = &["mail", "owa", "webmail", "exchange", "outlook", "remote", "email", "autodiscover"];
+
+/// First labels of host names commonly used for self-hosted Lync / Skype for Business.
+pub const SKYPE_HOST_LABELS: &[&str] = &["lyncdiscover", "lyncweb", "sip", "meet", "dialin"];
+
+/// First labels of host names commonly used for AD FS.
+pub const ADFS_HOST_LABELS: &[&str] = &["adfs", "sts", "fs", "sso"];
+
+/// First labels of host names commonly used for Remote Desktop Web Access and RD Gateway.
+pub const RD_GATEWAY_HOST_LABELS: &[&str] = &["rdweb", "rds", "rdgateway", "gateway"];
+
+/// First labels of host names commonly used to publish NDES (SCEP) for certificate enrollment.
+pub const NDES_HOST_LABELS: &[&str] = &["ndes", "scep", "pki", "certsrv", "enterpriseenrollment"];
+
+/// Local part of the synthetic address sent to per-user discovery services (Autodiscover,
+/// MDM discovery); the mailbox does not need to exist.
+pub const PROBE_USER: &str = "recon";
+
+/// Paces DNS queries to a maximum rate shared by all lookups.
+pub struct QueryLimiter(Mutex<Interval>);
+
+impl QueryLimiter {
+    pub fn new(queries_per_second: u32) -> Self {
+        let mut ticker = interval(Duration::from_secs_f64(1.0 / f64::from(queries_per_second.max(1))));
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        Self(Mutex::new(ticker))
+    }
+
+    /// Waits until the next query may be sent.
+    pub async fn wait(&self) {
+        self.0.lock().await.tick().await;
+    }
+}
+
+/// Normalises host names (no trailing dot, lowercase) and drops empty and repeated ones,
+/// keeping the first occurrence.
+pub fn unique_hostnames(hostnames: impl IntoIterator<Item = String>) -> Vec<String> {
+    let mut unique: Vec<String> = Vec::new();
+    for hostname in hostnames {
+        let hostname = hostname.trim_end_matches('.').to_lowercase();
+        if !hostname.is_empty() && !unique.contains(&hostname) {
+            unique.push(hostname);
+        }
+    }
+    unique
+}
+
+/// Returns a value that is not predictable across calls or runs.
+///
+/// Draws on the per-instance random keys of `RandomState`; good enough for DNS query IDs
+/// and throwaway labels, not for anything security-sensitive.
+pub fn random_u64() -> u64 {
+    let mut hasher = RandomState::new().build_hasher();
+    hasher.write_u64(0);
+    hasher.finish()
+}
+
+/// Candidate tenant names for services addressed by tenant (SharePoint, Power Pages, Dynamics 365),
+/// most likely first: the onmicrosoft label, then names guessed from the registrable domain
+/// (e.g., `contoso-corp.co.uk` -> `contosocorp`, `contoso-corp`), since tenant names often drop
+/// the punctuation of the domain.
+pub fn tenant_label_candidates(tenant_name: Option<&str>, domain: &str) -> Vec<String> {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    let registrable = psl::domain_str(&domain).unwrap_or(&domain);
+    let label = registrable.split('.').next().unwrap_or(registrable);
+    let tenant_label = tenant_name.and_then(|name| name.split('.').next()).map(str::to_lowercase);
+
+    let mut candidates: Vec<String> = Vec::new();
+    for candidate in tenant_label.into_iter().chain([label.replace('-', ""), label.to_string()]) {
+        if !candidate.is_empty() && !candidates.contains(&candidate) {
+            candidates.push(candidate);
+        }
+    }
+    candidates
+}
+
+/// Whether `value` is a GUID in its usual 8-4-4-4-12 hex form (e.g., a tenant ID).
+pub fn is_guid(value: &str) -> bool {
+    let groups: Vec<&str> = value.split('-').collect();
+    groups.len() == 5
+        && groups.iter().zip([8, 4, 4, 4, 12]).all(|(group, len)| group.len() == len && group.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_unique_hostnames() {
+        let hostnames = ["Mail.Contoso.com.", "", "mail.contoso.com", "www.contoso.com"].map(str::to_string);
+        assert_eq!(unique_hostnames(hostnames), vec!["mail.contoso.com", "www.contoso.com"]);
+    }
+
+    #[test]
+    fn test_tenant_label_candidates() {
+        assert_eq!(
+            tenant_label_candidates(Some("ContosoLtd.onmicrosoft.com"), "contoso-corp.co.uk"),
+            vec!["contosoltd", "contosocorp", "contoso-corp"]
+        );
+        assert_eq!(
+            tenant_label_candidates(None, "mail.contoso-corp.co.uk."),
+            vec!["contosocorp", "contoso-corp"]
+        );
+        assert_eq!(tenant_label_candidates(Some("contoso.onmicrosoft.com"), "contoso.com"), vec!["contoso"]);
+    }
+
+    #[test]
+    fn test_is_guid() {
+        assert!(is_guid("72f988bf-86f1-41af-91ab-2d7cd011db47"));
+        assert!(is_guid("72F988BF-86F1-41AF-91AB-2D7CD011DB47"));
+        assert!(!is_guid("common"));
+        assert!(!is_guid("72f988bf-86f1-41af-91ab-2d7cd011db4"));
+    }
+}
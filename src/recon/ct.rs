@@ -0,0 +1,87 @@
+use crate::error::ReconError;
+use reqwest::Client;
+use serde::Deserialize;
+use tracing::{debug, info};
+
+/// A certificate entry in a crt.sh-compatible JSON response.
+#[derive(Debug, Deserialize)]
+struct CtEntry {
+    #[serde(default)]
+    common_name: Option<String>,
+    #[serde(default)]
+    name_value: Option<String>, // Newline-separated SAN entries
+}
+
+/// Queries a crt.sh-compatible Certificate Transparency search API for names under `domain`.
+///
+/// Returns the distinct host names found in certificate subjects and SANs, with
+/// wildcard prefixes stripped and names outside the domain dropped.
+pub async fn query_ct_logs(client: Client, base_url: String, domain: String) -> Result<Vec<String>, ReconError> {
+    let url = format!("{}/", base_url.trim_end_matches('/'));
+    debug!(domain = domain.as_str(), url = url.as_str(), "Querying Certificate Transparency logs");
+
+    let response = client
+        .get(&url)
+        .query(&[("q", format!("%.{}", domain).as_str()), ("output", "json")])
+        .send()
+        .await?;
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(ReconError::UnexpectedApiResponse {
+            service: "Certificate Transparency".to_string(),
+            status,
+            body,
+        });
+    }
+
+    let entries: Vec<CtEntry> = serde_json::from_str(&response.text().await?)?;
+    let names = extract_names(&entries, &domain);
+    info!(domain = domain.as_str(), certificates = entries.len(), names = names.len(), "Finished CT log query");
+    Ok(names)
+}
+
+/// Collects, normalizes and deduplicates the host names under `domain` from CT entries.
+fn extract_names(entries: &[CtEntry], domain: &str) -> Vec<String> {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    let suffix = format!(".{}", domain);
+    let mut names: Vec<String> = entries
+        .iter()
+        .flat_map(|entry| {
+            entry
+                .name_value
+                .iter()
+                .flat_map(|value| value.lines())
+                .chain(entry.common_name.as_deref())
+        })
+        .map(|name| {
+            let name = name.trim().trim_end_matches('.').to_lowercase();
+            name.strip_prefix("*.").map(str::to_string).unwrap_or(name)
+        })
+        .filter(|name| (*name == domain || name.ends_with(&suffix)) && !name.contains(['*', ' ', '@']))
+        .collect();
+    names.sort();
+    names.dedup();
+    names
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_names() {
+        let entries: Vec<CtEntry> = serde_json::from_str(
+            r#"[
+                {"common_name": "*.contoso.com", "name_value": "*.contoso.com\ncontoso.com"},
+                {"common_name": "adfs.contoso.com", "name_value": "ADFS.contoso.com\nsts.contoso.com"},
+                {"common_name": "contoso.com", "name_value": "admin@contoso.com\nwww.fabrikam.com\nnotcontoso.com"}
+            ]"#,
+        )
+        .unwrap();
+        assert_eq!(
+            extract_names(&entries, "contoso.com"),
+            vec!["adfs.contoso.com", "contoso.com", "sts.contoso.com"]
+        );
+    }
+}
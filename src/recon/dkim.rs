@@ -0,0 +1,298 @@
+use crate::error::ReconError;
+use crate::models::{DkimResults, DkimSelector};
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures::future::join_all;
+use tracing::{debug, info};
+use trust_dns_resolver::proto::rr::RecordType;
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Selectors Microsoft 365 publishes as CNAMEs to the tenant's onmicrosoft domain.
+const M365_SELECTORS: &[&str] = &["selector1", "selector2"];
+
+/// Suffixes of the initial (onmicrosoft) tenant domain across clouds.
+const ONMICROSOFT_SUFFIXES: &[&str] = &[".onmicrosoft.com", ".onmicrosoft.us", ".partner.onmschina.cn"];
+
+/// Suffix of the newer M365 DKIM CNAME format (e.g., contoso.n-v1.dkim.mail.microsoft).
+const M365_DKIM_SUFFIX: &str = ".dkim.mail.microsoft";
+
+/// Probes the Microsoft 365 DKIM selectors plus the given third-party selectors.
+///
+/// The M365 selector CNAME target also reveals the tenant's onmicrosoft name, completed with
+/// the selected cloud's `onmicrosoft_suffix` when the target only carries the tenant label.
+pub async fn discover_dkim_selectors(
+    resolver: TokioAsyncResolver,
+    domain: String,
+    extra_selectors: Vec<String>,
+    onmicrosoft_suffix: String,
+) -> Result<DkimResults, ReconError> {
+    let mut selectors: Vec<String> = M365_SELECTORS.iter().map(|s| s.to_string()).collect();
+    for selector in extra_selectors {
+        if !selectors.contains(&selector) {
+            selectors.push(selector);
+        }
+    }
+    debug!(domain = domain.as_str(), count = selectors.len(), "Probing DKIM selectors");
+
+    let probes = selectors
+        .iter()
+        .map(|selector| probe_selector(&resolver, &domain, selector));
+    let found: Vec<DkimSelector> = join_all(probes).await.into_iter().flatten().collect();
+
+    let m365_tenant_name = found
+        .iter()
+        .filter_map(|selector| selector.cname_target.as_deref())
+        .find_map(|target| tenant_name_from_cname(target, &onmicrosoft_suffix));
+
+    info!(domain = domain.as_str(), count = found.len(), "Finished DKIM selector discovery");
+    Ok(DkimResults {
+        selectors: found,
+        m365_tenant_name,
+    })
+}
+
+/// Looks up a single selector, returning `None` if neither a CNAME nor a key is published.
+async fn probe_selector(resolver: &TokioAsyncResolver, domain: &str, selector: &str) -> Option<DkimSelector> {
+    let name = format!("{}._domainkey.{}", selector, domain);
+
+    let cname_target = match resolver.lookup(name.as_str(), RecordType::CNAME).await {
+        Ok(response) => response
+            .iter()
+            .find_map(|rdata| rdata.as_cname())
+            .map(|cname| cname.to_string().trim_end_matches('.').to_lowercase()),
+        Err(_) => None,
+    };
+
+    // TXT lookups follow the CNAME, so this also returns keys hosted by the provider
+    let record = match resolver.txt_lookup(name.as_str()).await {
+        Ok(response) => response
+            .iter()
+            .map(|txt| txt.iter().map(|bytes| String::from_utf8_lossy(bytes)).collect::<String>())
+            .find(|txt| txt.contains("p=")),
+        Err(_) => None,
+    };
+
+    if cname_target.is_none() && record.is_none() {
+        return None;
+    }
+    debug!(domain = name.as_str(), "Found DKIM selector");
+
+    let microsoft_365 = cname_target.as_deref().is_some_and(is_m365_dkim_target);
+    let mut result = DkimSelector {
+        selector: selector.to_string(),
+        name,
+        cname_target,
+        microsoft_365,
+        record: record.clone(),
+        key_type: None,
+        key_bits: None,
+        revoked: false,
+        testing: false,
+        issues: Vec::new(),
+    };
+    match record {
+        Some(record) => analyze_key_record(&record, &mut result),
+        None => result
+            .issues
+            .push("CNAME published but no key found at the target (DKIM signing may be disabled)".to_string()),
+    }
+    Some(result)
+}
+
+/// Whether a selector CNAME target is an M365 DKIM key host, in either format.
+fn is_m365_dkim_target(target: &str) -> bool {
+    target.split_once("._domainkey.").is_some_and(|(_, tenant_part)| {
+        ONMICROSOFT_SUFFIXES.iter().any(|suffix| tenant_part.ends_with(suffix)) || tenant_part.ends_with(M365_DKIM_SUFFIX)
+    })
+}
+
+/// Extracts the tenant's onmicrosoft name from an M365 selector CNAME target.
+///
+/// e.g., `selector1-contoso-com._domainkey.contoso.onmicrosoft.com` -> `contoso.onmicrosoft.com`.
+/// The newer format only carries the tenant label, completed with `onmicrosoft_suffix`
+/// (e.g., `contoso.n-v1.dkim.mail.microsoft` -> `contoso.onmicrosoft.com`); without a suffix
+/// for the cloud, no name is returned.
+fn tenant_name_from_cname(target: &str, onmicrosoft_suffix: &str) -> Option<String> {
+    let (_, tenant_part) = target.split_once("._domainkey.")?;
+    if ONMICROSOFT_SUFFIXES.iter().any(|suffix| tenant_part.ends_with(suffix)) {
+        return Some(tenant_part.to_string());
+    }
+    if onmicrosoft_suffix.is_empty() {
+        return None;
+    }
+    tenant_part
+        .strip_suffix(M365_DKIM_SUFFIX)
+        .and_then(|rest| rest.split('.').next())
+        .map(|tenant| format!("{}{}", tenant, onmicrosoft_suffix))
+}
+
+/// Parses the tags of a DKIM key record and fills in key type, length and issues.
+fn analyze_key_record(record: &str, selector: &mut DkimSelector) {
+    let mut key_type = "rsa".to_string();
+    let mut public_key = None;
+    for tag in record.split(';').map(str::trim) {
+        let Some((name, value)) = tag.split_once('=') else {
+            continue;
+        };
+        // Long keys are often split over several strings; drop the whitespace in between
+        let value: String = value.chars().filter(|c| !c.is_whitespace()).collect();
+        match name.trim() {
+            "k" => key_type = value.to_lowercase(),
+            "p" => public_key = Some(value),
+            "t" => selector.testing = value.split(':').any(|flag| flag == "y"),
+            _ => {}
+        }
+    }
+    selector.key_type = Some(key_type.clone());
+
+    if selector.testing {
+        selector
+            .issues
+            .push("Testing mode (t=y); verifiers treat failures as unsigned mail".to_string());
+    }
+
+    let public_key = public_key.unwrap_or_default();
+    if public_key.is_empty() {
+        selector.revoked = true;
+        selector.issues.push("Key revoked (empty p= tag)".to_string());
+        return;
+    }
+
+    let Ok(der) = STANDARD.decode(public_key.as_bytes()) else {
+        selector.issues.push("Public key is not valid base64".to_string());
+        return;
+    };
+    selector.key_bits = match key_type.as_str() {
+        "ed25519" => Some(der.len() as u32 * 8),
+        _ => rsa_modulus_bits(&der),
+    };
+    match selector.key_bits {
+        None => selector.issues.push("Public key could not be parsed".to_string()),
+        Some(bits) if key_type == "rsa" && bits < 1024 => {
+            selector.issues.push(format!("{}-bit RSA key is below the 1024-bit minimum", bits))
+        }
+        Some(bits) if key_type == "rsa" && bits < 2048 => {
+            selector.issues.push(format!("{}-bit RSA key; 2048 bits is recommended", bits))
+        }
+        Some(_) => {}
+    }
+}
+
+/// Reads one DER element, returning its tag, contents and the remaining input.
+fn read_der(data: &[u8]) -> Option<(u8, &[u8], &[u8])> {
+    let (&tag, rest) = data.split_first()?;
+    let (&first_len, rest) = rest.split_first()?;
+    let (len, rest) = if first_len & 0x80 == 0 {
+        (first_len as usize, rest)
+    } else {
+        let count = (first_len & 0x7f) as usize;
+        if count == 0 || count > 4 || rest.len() < count {
+            return None;
+        }
+        let len = rest[..count].iter().fold(0usize, |acc, byte| (acc << 8) | *byte as usize);
+        (len, &rest[count..])
+    };
+    if rest.len() < len {
+        return None;
+    }
+    Some((tag, &rest[..len], &rest[len..]))
+}
+
+/// Returns the RSA modulus length of a SubjectPublicKeyInfo or PKCS#1 RSAPublicKey.
+fn rsa_modulus_bits(der: &[u8]) -> Option<u32> {
+    let (tag, sequence, _) = read_der(der)?;
+    if tag != 0x30 {
+        return None;
+    }
+    let (first_tag, _, after_first) = read_der(sequence)?;
+    let rsa_public_key = match first_tag {
+        // SubjectPublicKeyInfo: AlgorithmIdentifier followed by a BIT STRING
+        0x30 => {
+            let (bit_string_tag, bits, _) = read_der(after_first)?;
+            if bit_string_tag != 0x03 {
+                return None;
+            }
+            let (tag, inner, _) = read_der(bits.get(1..)?)?; // Skip the unused-bits byte
+            if tag != 0x30 {
+                return None;
+            }
+            inner
+        }
+        // PKCS#1 RSAPublicKey: the modulus comes first
+        0x02 => sequence,
+        _ => return None,
+    };
+
+    let (tag, modulus, _) = read_der(rsa_public_key)?;
+    if tag != 0x02 {
+        return None;
+    }
+    let start = modulus.iter().position(|byte| *byte != 0)?;
+    let modulus = &modulus[start..];
+    Some((modulus.len() as u32 - 1) * 8 + (8 - modulus[0].leading_zeros()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const RSA_1024_SPKI: &str = "MIGfMA0GCSqGSIb3DQEBAQUAA4GNADCBiQKBgQDVQ85Ocr8R2utRoqxHnfsGinPp9N/IW5xzWhXcJtX2S5rHAcMo/rZ3gEC04L2N0Du1ThH/jiOzgqG+6ctyXEg1AdLGnyH5Owm2Bfj+Zi2SxBpV5DnRhFTJgrG3fTgZt3rUaTk+6DirPPDls9iXRkswUtpdK8sW/KezdPHSlNv/LwIDAQAB";
+    const RSA_2048_PKCS1: &str = "MIIBCgKCAQEAk4vLuA8nhHeDwggPgnGJiwr/PMSSJazd0KrQHUorRMRvQRkWxW0SllVXPfZ1XgDCbPdOR6S8Rbu/iF4rA9FAn3xe6cbLtEkipOqey8zB/NW34QL5rztsIrvO+jYQSji1JDS8HPMt4/ZWgo4IBgTwAZS4vkeLDvSRUHH6qKk9aps2jz8nSk90iD2azrglEUDh81gQq3WswAQuQxrx9YsyvA1qoz457hocduu9+nhqNM7azL6IFjdc37UJ1IG76d45u9Y7ca9NtE0IEQwuM9dfbmCQ0us7wEGWzrWW6melW9WQl1GJRZQJ0e26qNMUIocKk4Cx0u7CMotvz17twTgPGQIDAQAB";
+
+    fn empty_selector() -> DkimSelector {
+        DkimSelector {
+            selector: "selector1".into(),
+            name: "selector1._domainkey.contoso.com".into(),
+            cname_target: None,
+            microsoft_365: false,
+            record: None,
+            key_type: None,
+            key_bits: None,
+            revoked: false,
+            testing: false,
+            issues: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_rsa_modulus_bits() {
+        assert_eq!(rsa_modulus_bits(&STANDARD.decode(RSA_1024_SPKI).unwrap()), Some(1024));
+        assert_eq!(rsa_modulus_bits(&STANDARD.decode(RSA_2048_PKCS1).unwrap()), Some(2048));
+        assert_eq!(rsa_modulus_bits(b"not der"), None);
+    }
+
+    #[test]
+    fn test_analyze_key_record() {
+        let mut selector = empty_selector();
+        analyze_key_record(&format!("v=DKIM1; k=rsa; t=y; p={}", RSA_1024_SPKI), &mut selector);
+        assert_eq!(selector.key_bits, Some(1024));
+        assert!(selector.testing);
+        assert_eq!(selector.issues.len(), 2);
+
+        let mut revoked = empty_selector();
+        analyze_key_record("v=DKIM1; p=", &mut revoked);
+        assert!(revoked.revoked);
+        assert_eq!(revoked.key_bits, None);
+    }
+
+    #[test]
+    fn test_tenant_name_from_cname() {
+        assert_eq!(
+            tenant_name_from_cname("selector1-contoso-com._domainkey.contoso.onmicrosoft.com", ".onmicrosoft.us").as_deref(),
+            Some("contoso.onmicrosoft.com")
+        );
+        let new_format = "selector2-contoso-com._domainkey.contoso.n-v1.dkim.mail.microsoft";
+        assert_eq!(tenant_name_from_cname(new_format, ".onmicrosoft.com").as_deref(), Some("contoso.onmicrosoft.com"));
+        assert_eq!(tenant_name_from_cname(new_format, ".onmicrosoft.us").as_deref(), Some("contoso.onmicrosoft.us"));
+        assert_eq!(tenant_name_from_cname(new_format, ""), None);
+        assert_eq!(tenant_name_from_cname("s1.domainkey.u123.wl.sendgrid.net", ".onmicrosoft.com"), None);
+    }
+
+    #[test]
+    fn test_is_m365_dkim_target() {
+        assert!(is_m365_dkim_target("selector1-contoso-com._domainkey.contoso.onmicrosoft.com"));
+        assert!(is_m365_dkim_target("selector2-contoso-com._domainkey.contoso.n-v1.dkim.mail.microsoft"));
+        assert!(!is_m365_dkim_target("s1.domainkey.u123.wl.sendgrid.net"));
+        assert!(!is_m365_dkim_target("google._domainkey.contoso.com"));
+    }
+}
@@ -0,0 +1,289 @@
+use crate::error::ReconError;
+use crate::models::{DmarcAlignment, DmarcDisposition, DmarcPolicy, DmarcReportDestination};
+use tracing::{debug, info};
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Looks up the DMARC policy that applies to `domain`.
+///
+/// Queries `_dmarc.<domain>` first and falls back to the organizational domain
+/// (derived from the public suffix list) as described in RFC 7489, section 6.6.3.
+/// Returns `None` if neither publishes a DMARC record.
+pub async fn get_dmarc_policy(
+    resolver: TokioAsyncResolver,
+    domain: String,
+) -> Result<Option<DmarcPolicy>, ReconError> {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    let org_domain = organizational_domain(&domain);
+
+    let mut candidates = vec![(domain.clone(), false)];
+    if org_domain != domain {
+        candidates.push((org_domain.clone(), true));
+    }
+
+    for (policy_domain, from_org) in candidates {
+        let queried_name = format!("_dmarc.{}", policy_domain);
+        debug!(domain = queried_name.as_str(), "Querying DMARC record");
+        let records = fetch_dmarc_records(&resolver, &queried_name).await;
+        let Some(record) = records.first() else {
+            continue;
+        };
+
+        let mut policy = parse_dmarc_record(record, &policy_domain, from_org);
+        policy.queried_name = queried_name;
+        if records.len() > 1 {
+            policy
+                .issues
+                .push(format!("{} DMARC records published; receivers ignore all of them", records.len()));
+        }
+
+        for destination in policy.aggregate_reports.iter_mut().chain(policy.forensic_reports.iter_mut()) {
+            if let (true, Some(dest_domain)) = (destination.external, destination.domain.as_deref()) {
+                let authorized = check_external_authorization(&resolver, &policy_domain, dest_domain).await;
+                if !authorized {
+                    policy.issues.push(format!(
+                        "External report destination {} has not authorized reports for {}",
+                        dest_domain, policy_domain
+                    ));
+                }
+                destination.authorized = Some(authorized);
+            }
+        }
+
+        info!(domain = domain.as_str(), "Found DMARC policy at {}", policy.queried_name);
+        return Ok(Some(policy));
+    }
+
+    debug!(domain = domain.as_str(), "No DMARC record found");
+    Ok(None)
+}
+
+/// Returns the organizational domain (registrable domain) for a host name.
+fn organizational_domain(domain: &str) -> String {
+    psl::domain_str(domain).unwrap_or(domain).to_lowercase()
+}
+
+/// Returns all `v=DMARC1` TXT records published at `name`.
+async fn fetch_dmarc_records(resolver: &TokioAsyncResolver, name: &str) -> Vec<String> {
+    match resolver.txt_lookup(name).await {
+        Ok(response) => response
+            .iter()
+            .map(|txt| txt.iter().map(|bytes| String::from_utf8_lossy(bytes)).collect::<String>())
+            .filter(|txt| is_dmarc_record(txt))
+            .collect(),
+        Err(e) => {
+            debug!(domain = name, error = %e, "DMARC TXT lookup failed");
+            Vec::new()
+        }
+    }
+}
+
+/// Checks whether an external report destination accepts reports for `policy_domain`
+/// (RFC 7489, section 7.1).
+async fn check_external_authorization(resolver: &TokioAsyncResolver, policy_domain: &str, dest_domain: &str) -> bool {
+    let name = format!("{}._report._dmarc.{}", policy_domain, dest_domain);
+    debug!(domain = name.as_str(), "Checking external DMARC report authorization");
+    !fetch_dmarc_records(resolver, &name).await.is_empty()
+}
+
+/// Checks whether a TXT record is a DMARC version 1 record.
+fn is_dmarc_record(txt: &str) -> bool {
+    txt.split(';')
+        .next()
+        .map(|tag| tag.replace(' ', "").eq_ignore_ascii_case("v=DMARC1"))
+        .unwrap_or(false)
+}
+
+/// Parses a DMARC record into a typed policy, recording any weaknesses found.
+///
+/// `from_org` marks a record found at the organizational domain, in which case the queried
+/// domain is a subdomain and its disposition is `sp=`, falling back to `p=`.
+fn parse_dmarc_record(record: &str, policy_domain: &str, from_org: bool) -> DmarcPolicy {
+    let mut policy = DmarcPolicy {
+        record: record.to_string(),
+        queried_name: format!("_dmarc.{}", policy_domain),
+        from_organizational_domain: from_org,
+        policy: None,
+        subdomain_policy: None,
+        percentage: 100,
+        aggregate_reports: Vec::new(),
+        forensic_reports: Vec::new(),
+        dkim_alignment: DmarcAlignment::Relaxed,
+        spf_alignment: DmarcAlignment::Relaxed,
+        failure_options: "0".to_string(),
+        report_interval: 86400,
+        issues: Vec::new(),
+    };
+    let org_domain = organizational_domain(policy_domain);
+
+    for tag in record.split(';').map(str::trim).filter(|tag| !tag.is_empty()) {
+        let Some((name, value)) = tag.split_once('=') else {
+            policy.issues.push(format!("Malformed tag '{}'", tag));
+            continue;
+        };
+        let value = value.trim();
+        match name.trim().to_lowercase().as_str() {
+            "v" => {}
+            "p" => policy.policy = parse_disposition(value, "p", &mut policy.issues),
+            "sp" => policy.subdomain_policy = parse_disposition(value, "sp", &mut policy.issues),
+            "pct" => match value.parse::<u8>() {
+                Ok(pct) if pct <= 100 => policy.percentage = pct,
+                _ => policy.issues.push(format!("Invalid pct value '{}'", value)),
+            },
+            "rua" => policy.aggregate_reports = parse_destinations(value, &org_domain, &mut policy.issues),
+            "ruf" => policy.forensic_reports = parse_destinations(value, &org_domain, &mut policy.issues),
+            "adkim" => policy.dkim_alignment = parse_alignment(value, "adkim", &mut policy.issues),
+            "aspf" => policy.spf_alignment = parse_alignment(value, "aspf", &mut policy.issues),
+            "fo" => policy.failure_options = value.to_string(),
+            "ri" => match value.parse::<u32>() {
+                Ok(ri) => policy.report_interval = ri,
+                Err(_) => policy.issues.push(format!("Invalid ri value '{}'", value)),
+            },
+            other => debug!(tag = other, "Ignoring unknown DMARC tag"),
+        }
+    }
+
+    match (&policy.policy, &policy.subdomain_policy) {
+        (None, _) => policy.issues.push("Missing or invalid required p= tag".to_string()),
+        (Some(_), Some(DmarcDisposition::None)) if from_org => policy
+            .issues
+            .push("sp=none only monitors the queried subdomain; spoofed mail is still delivered".to_string()),
+        (Some(DmarcDisposition::None), None) if from_org => policy
+            .issues
+            .push("p=none (no sp=) only monitors the queried subdomain; spoofed mail is still delivered".to_string()),
+        (Some(_), _) if from_org => {}
+        (Some(DmarcDisposition::None), _) => policy
+            .issues
+            .push("p=none only monitors; spoofed mail is still delivered".to_string()),
+        (Some(_), Some(DmarcDisposition::None)) => policy
+            .issues
+            .push("sp=none leaves subdomains unprotected".to_string()),
+        _ => {}
+    }
+    if policy.percentage < 100 {
+        policy.issues.push(format!(
+            "pct={} applies the policy to only {}% of failing mail",
+            policy.percentage, policy.percentage
+        ));
+    }
+    if policy.aggregate_reports.is_empty() {
+        policy
+            .issues
+            .push("No rua= destination; aggregate reports are not collected".to_string());
+    }
+
+    policy
+}
+
+/// Parses a `p=`/`sp=` value.
+fn parse_disposition(value: &str, tag: &str, issues: &mut Vec<String>) -> Option<DmarcDisposition> {
+    match value.to_lowercase().as_str() {
+        "none" => Some(DmarcDisposition::None),
+        "quarantine" => Some(DmarcDisposition::Quarantine),
+        "reject" => Some(DmarcDisposition::Reject),
+        _ => {
+            issues.push(format!("Invalid {} value '{}'", tag, value));
+            None
+        }
+    }
+}
+
+/// Parses an `adkim=`/`aspf=` value, defaulting to relaxed.
+fn parse_alignment(value: &str, tag: &str, issues: &mut Vec<String>) -> DmarcAlignment {
+    match value.to_lowercase().as_str() {
+        "s" => DmarcAlignment::Strict,
+        "r" => DmarcAlignment::Relaxed,
+        _ => {
+            issues.push(format!("Invalid {} value '{}'", tag, value));
+            DmarcAlignment::Relaxed
+        }
+    }
+}
+
+/// Parses a comma-separated `rua=`/`ruf=` URI list such as `mailto:dmarc@example.com!10m`.
+fn parse_destinations(value: &str, org_domain: &str, issues: &mut Vec<String>) -> Vec<DmarcReportDestination> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|uri| !uri.is_empty())
+        .map(|uri| {
+            // Strip the optional size limit suffix (e.g., "!10m")
+            let address = uri.split('!').next().unwrap_or(uri);
+            let domain = match address.get(..7) {
+                Some(scheme) if scheme.eq_ignore_ascii_case("mailto:") => address
+                    .rsplit_once('@')
+                    .map(|(_, domain)| domain.trim_end_matches('.').to_lowercase()),
+                _ => None,
+            };
+            if domain.is_none() {
+                issues.push(format!("Report destination '{}' is not a valid mailto: URI", uri));
+            }
+            let external = domain
+                .as_deref()
+                .is_some_and(|domain| organizational_domain(domain) != org_domain);
+            DmarcReportDestination {
+                uri: uri.to_string(),
+                domain,
+                external,
+                authorized: None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_dmarc_record_all_tags() {
+        let record = "v=DMARC1; p=reject; sp=quarantine; pct=50; rua=mailto:dmarc@contoso.com,mailto:x@reports.example.net!10m; \
+                      ruf=mailto:forensic@contoso.co.uk; adkim=s; aspf=r; fo=1; ri=3600";
+        let policy = parse_dmarc_record(record, "mail.contoso.com", false);
+        assert_eq!(policy.policy, Some(DmarcDisposition::Reject));
+        assert_eq!(policy.subdomain_policy, Some(DmarcDisposition::Quarantine));
+        assert_eq!(policy.percentage, 50);
+        assert_eq!(policy.dkim_alignment, DmarcAlignment::Strict);
+        assert_eq!(policy.spf_alignment, DmarcAlignment::Relaxed);
+        assert_eq!(policy.failure_options, "1");
+        assert_eq!(policy.report_interval, 3600);
+        assert_eq!(policy.aggregate_reports.len(), 2);
+        assert!(!policy.aggregate_reports[0].external);
+        assert_eq!(policy.aggregate_reports[1].domain.as_deref(), Some("reports.example.net"));
+        assert!(policy.aggregate_reports[1].external);
+        assert!(policy.forensic_reports[0].external);
+        assert!(policy.issues.iter().any(|issue| issue.starts_with("pct=50")));
+    }
+
+    #[test]
+    fn test_parse_dmarc_record_defaults_and_issues() {
+        let policy = parse_dmarc_record("v=DMARC1; p=none", "contoso.com", false);
+        assert_eq!(policy.policy, Some(DmarcDisposition::None));
+        assert_eq!(policy.percentage, 100);
+        assert_eq!(policy.dkim_alignment, DmarcAlignment::Relaxed);
+        assert_eq!(policy.report_interval, 86400);
+        assert_eq!(policy.issues.len(), 2); // p=none and missing rua
+    }
+
+    #[test]
+    fn test_parse_dmarc_record_from_organizational_domain() {
+        let record = "v=DMARC1; p=none; sp=reject; rua=mailto:dmarc@contoso.com";
+        let subdomain = parse_dmarc_record(record, "contoso.com", true);
+        assert!(subdomain.from_organizational_domain);
+        assert!(subdomain.issues.is_empty());
+        let apex = parse_dmarc_record(record, "contoso.com", false);
+        assert!(apex.issues.iter().any(|issue| issue.starts_with("p=none")));
+
+        let inherited = parse_dmarc_record("v=DMARC1; p=none; rua=mailto:dmarc@contoso.com", "contoso.com", true);
+        assert_eq!(inherited.issues.len(), 1);
+        let weak_sp = parse_dmarc_record("v=DMARC1; p=reject; sp=none; rua=mailto:dmarc@contoso.com", "contoso.com", true);
+        assert!(weak_sp.issues[0].starts_with("sp=none"));
+    }
+
+    #[test]
+    fn test_organizational_domain() {
+        assert_eq!(organizational_domain("mail.contoso.co.uk"), "contoso.co.uk");
+        assert_eq!(organizational_domain("contoso.com"), "contoso.com");
+        assert!(is_dmarc_record("v=DMARC1; p=reject"));
+        assert!(!is_dmarc_record("v=spf1 -all"));
+    }
+}
@@ -1,12 +1,70 @@
+use crate::config::CloudConfig;
 use crate::error::ReconError;
-use crate::models::DnsResults;
+use crate::models::{DnsResults, HostingType, MxHost, SrvRecord};
+use crate::recon::common::QueryLimiter;
+use crate::recon::dkim::discover_dkim_selectors;
+use crate::recon::dmarc::get_dmarc_policy;
+use crate::recon::dnssec::{check_dnssec, check_mx_tlsa, inbound_dane};
+use crate::recon::email_security::{check_bimi, check_mta_sts, check_tls_rpt};
+use crate::recon::mx::{check_eop_direct_delivery, classify_mx_host};
+use crate::recon::nameservers::check_nameservers;
+use reqwest::Client;
+use crate::recon::service_tags::resolve_hosts;
+use crate::recon::spf::analyze_spf;
+use crate::recon::subdomains::discover_subdomains;
+use crate::recon::takeover::{chain_target, check_dangling_records, resolve_cname_chain};
+use crate::recon::ReconOptions;
+use futures::future::join_all;
+use std::sync::Arc;
 use tracing::{debug, info, warn};
 use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
-use trust_dns_resolver::proto::rr::RecordType;
+use trust_dns_resolver::error::ResolveErrorKind;
 use trust_dns_resolver::TokioAsyncResolver;
 
+/// SRV services queried for every target domain.
+const SRV_SERVICES: &[&str] = &[
+    "_sipfederationtls._tcp", // Teams/Skype federation
+    "_sip._tls",              // Skype/Lync client sign-in
+    "_autodiscover._tcp",     // Exchange Autodiscover
+    "_xmpp-server._tcp",      // XMPP federation (legacy Lync)
+    "_kerberos._tcp",         // Active Directory domain controllers
+    "_ldap._tcp",             // Active Directory domain controllers
+];
+
+/// Host names under the target domain whose CNAME chains are checked for dangling records.
+const WELL_KNOWN_HOST_PREFIXES: &[&str] = &[
+    "www",
+    "autodiscover",
+    "lyncdiscover",
+    "sip",
+    "enterpriseregistration",
+    "enterpriseenrollment",
+    "mta-sts",
+    "msoid",
+];
+
+/// Host name suffixes owned by Microsoft across the commercial and sovereign clouds.
+const MICROSOFT_HOST_SUFFIXES: &[&str] = &[
+    ".lync.com",
+    ".outlook.com",
+    ".office365.com",
+    ".office.com",
+    ".microsoft.com",
+    ".microsoftonline.com",
+    ".skypeforbusiness.us",
+    ".office365.us",
+    ".lync.cn",
+    ".partner.outlook.cn",
+    ".partner.microsoftonline.cn",
+];
+
 /// Performs all DNS-related reconnaissance checks concurrently.
-pub async fn run_dns_checks(domain: &str) -> Result<DnsResults, ReconError> {
+pub async fn run_dns_checks(
+    client: &Client,
+    domain: &str,
+    cloud_config: &CloudConfig,
+    options: &ReconOptions,
+) -> Result<DnsResults, ReconError> {
     info!(target = domain, "Starting DNS checks");
     // Create a resolver instance. Cache results for efficiency within this run.
     // Using Google's public DNS servers as a default, could be made configurable.
@@ -16,33 +74,125 @@ pub async fn run_dns_checks(domain: &str) -> Result<DnsResults, ReconError> {
     );
 
     let domain = domain.to_string(); // Clone domain for use in tasks
+    // Per-host lookups (subdomains, CNAME chains, address resolution) share one query rate
+    let limiter = Arc::new(QueryLimiter::new(options.subdomain_qps));
 
     // Spawn tasks for each DNS check
     let mx_handle = tokio::spawn(get_mx_records(resolver.clone(), domain.clone()));
     let txt_handle = tokio::spawn(get_txt_records(resolver.clone(), domain.clone()));
-    let autodiscover_handle = tokio::spawn(check_autodiscover(resolver.clone(), domain.clone()));
-    let lync_handle = tokio::spawn(check_record_presence(resolver.clone(), format!("lyncdiscover.{}", domain)));
-    let sip_handle = tokio::spawn(check_record_presence(resolver.clone(), format!("sip.{}", domain)));
+    let autodiscover_handle = tokio::spawn(check_autodiscover(resolver.clone(), limiter.clone(), domain.clone()));
+    let lync_handle = tokio::spawn(check_record_presence(resolver.clone(), limiter.clone(), format!("lyncdiscover.{}", domain)));
+    let sip_handle = tokio::spawn(check_record_presence(resolver.clone(), limiter.clone(), format!("sip.{}", domain)));
+    let srv_handle = tokio::spawn(get_srv_records(resolver.clone(), domain.clone()));
+    let dmarc_handle = tokio::spawn(get_dmarc_policy(resolver.clone(), domain.clone()));
+    let dkim_handle = tokio::spawn(discover_dkim_selectors(
+        resolver.clone(),
+        domain.clone(),
+        options.dkim_selectors.clone(),
+        cloud_config.onmicrosoft_suffix.clone(),
+    ));
+    let tls_rpt_handle = tokio::spawn(check_tls_rpt(resolver.clone(), domain.clone()));
+    let bimi_handle = tokio::spawn(check_bimi(resolver.clone(), client.clone(), domain.clone()));
+    let dnssec_handle = tokio::spawn(check_dnssec(resolver.clone(), domain.clone()));
+    let nameserver_handle = tokio::spawn(check_nameservers(
+        resolver.clone(),
+        domain.clone(),
+        options.axfr_enabled,
+        options.probe_timeout,
+    ));
+    let subdomain_handle = (!options.subdomain_words.is_empty() || options.ct_log_url.is_some()).then(|| {
+        tokio::spawn(discover_subdomains(
+            client.clone(),
+            resolver.clone(),
+            domain.clone(),
+            options.subdomain_words.clone(),
+            options.ct_log_url.clone(),
+            limiter.clone(),
+        ))
+    });
+    let spf_handle = tokio::spawn(analyze_spf(
+        resolver.clone(),
+        domain.clone(),
+        cloud_config.exchange_spf_include.clone(),
+    ));
 
     // Await results, handling potential errors
-    let mx_records = mx_handle.await.unwrap_or_else(|e| {
+    let mx_hosts = mx_handle.await.unwrap_or_else(|e| {
         warn!(domain = domain.as_str(), error = %e, "MX lookup task failed");
         Err(ReconError::check_failed("MX Lookup", e.to_string()))
     })?;
+    let mx_records: Vec<String> = mx_hosts.iter().map(|mx| mx.host.clone()).collect();
+    // MTA-STS needs the observed MX hosts to validate the policy's mx patterns
+    let mta_sts_handle = tokio::spawn(check_mta_sts(
+        resolver.clone(),
+        options.no_redirect_client.clone(),
+        domain.clone(),
+        mx_records.clone(),
+    ));
+    let tlsa_handle = tokio::spawn(check_mx_tlsa(resolver.clone(), mx_records.clone()));
+    let eop_handle = tokio::spawn(check_eop_direct_delivery(
+        resolver.clone(),
+        domain.clone(),
+        cloud_config.eop_host_suffix.clone(),
+        mx_hosts.clone(),
+    ));
     
     let txt_records = txt_handle.await.unwrap_or_else(|e| {
         warn!(domain = domain.as_str(), error = %e, "TXT lookup task failed");
         Err(ReconError::check_failed("TXT Lookup", e.to_string()))
     })?;
     
-    // Extract SPF and DMARC records
+    // Extract SPF record
     let spf_record = txt_records.iter()
         .find(|txt| txt.to_lowercase().starts_with("v=spf1"))
         .map(|s| s.to_string());
-    
-    let dmarc_record = txt_records.iter()
-        .find(|txt| txt.to_lowercase().starts_with("v=dmarc1"))
-        .map(|s| s.to_string());
+
+    // DMARC lives at _dmarc.<domain> (or the organizational domain), not in the apex TXT records
+    let dmarc_policy = dmarc_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "DMARC lookup task failed");
+        Err(ReconError::check_failed("DMARC Lookup", e.to_string()))
+    })?;
+
+    let dkim = dkim_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "DKIM selector task failed");
+        Err(ReconError::check_failed("DKIM Selector Discovery", e.to_string()))
+    })?;
+
+    let mta_sts = mta_sts_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "MTA-STS check task failed");
+        Err(ReconError::check_failed("MTA-STS Check", e.to_string()))
+    })?;
+
+    let tls_rpt = tls_rpt_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "TLS-RPT check task failed");
+        Err(ReconError::check_failed("TLS-RPT Check", e.to_string()))
+    })?;
+
+    let bimi = bimi_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "BIMI check task failed");
+        Err(ReconError::check_failed("BIMI Check", e.to_string()))
+    })?;
+
+    let dnssec = dnssec_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "DNSSEC check task failed");
+        Err(ReconError::check_failed("DNSSEC Check", e.to_string()))
+    })?;
+
+    let nameservers = nameserver_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "Name server check task failed");
+        Err(ReconError::check_failed("Name Server Check", e.to_string()))
+    })?;
+
+    let mx_tlsa = tlsa_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "TLSA lookup task failed");
+        Err(ReconError::check_failed("TLSA Lookup", e.to_string()))
+    })?;
+    let eop_direct_delivery = eop_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "EOP direct delivery task failed");
+        Err(ReconError::check_failed("EOP Direct Delivery Check", e.to_string()))
+    })?;
+
+    let inbound_dane = inbound_dane(&dnssec.status, &mx_tlsa);
     
     let autodiscover_cname_or_a = autodiscover_handle.await.unwrap_or_else(|e| {
         warn!(domain = domain.as_str(), error = %e, "Autodiscover check task failed");
@@ -59,15 +209,63 @@ pub async fn run_dns_checks(domain: &str) -> Result<DnsResults, ReconError> {
         Err(ReconError::check_failed("SIP Check", e.to_string()))
     })?;
 
+    let (srv_records, failed_srv_services) = srv_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "SRV lookup task failed");
+        Err(ReconError::check_failed("SRV Lookup", e.to_string()))
+    })?;
+    let srv_present = |service: &str| srv_presence(&srv_records, &failed_srv_services, service);
+
+    let spf_analysis = spf_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "SPF evaluation task failed");
+        Err(ReconError::check_failed("SPF Evaluation", e.to_string()))
+    })?;
+
+    let subdomains = match subdomain_handle {
+        Some(handle) => Some(handle.await.unwrap_or_else(|e| {
+            warn!(domain = domain.as_str(), error = %e, "Subdomain discovery task failed");
+            Err(ReconError::check_failed("Subdomain Discovery", e.to_string()))
+        })?),
+        None => None,
+    };
+
+    // Follow the CNAME chain of every host name seen so far and look for dangling Azure records
+    let mut hostnames: Vec<String> = vec![domain.clone()];
+    hostnames.extend(WELL_KNOWN_HOST_PREFIXES.iter().map(|prefix| format!("{}.{}", prefix, domain)));
+    hostnames.extend(mx_records.iter().cloned());
+    hostnames.extend(srv_records.iter().map(|srv| srv.target.clone()));
+    if let Some(subdomains) = &subdomains {
+        hostnames.extend(subdomains.subdomains.iter().map(|subdomain| subdomain.hostname.clone()));
+    }
+    let (cname_chains, takeover_candidates) = check_dangling_records(resolver.clone(), client.clone(), hostnames.clone(), limiter.clone()).await?;
+    // Resolve every host name (and CNAME target) to IPv4/IPv6 and attribute the addresses to service tags
+    hostnames.extend(cname_chains.iter().filter_map(|chain| chain.chain.last().cloned()));
+    let resolved_hosts = resolve_hosts(
+        resolver.clone(),
+        hostnames,
+        limiter.clone(),
+        options.service_tags.clone(),
+        options.geoip.clone(),
+    ).await?;
+
     info!(target = domain.as_str(), "Finished DNS checks");
     Ok(DnsResults {
         mx_records: Some(mx_records.clone()),
         mx_records_found: Some(!mx_records.is_empty()),
+        mx_hosts: Some(mx_hosts),
+        eop_direct_delivery,
         spf_record: spf_record.clone(),
         spf_record_found: Some(spf_record.is_some()),
-        dmarc_record: dmarc_record.clone(),
-        dmarc_record_found: Some(dmarc_record.is_some()),
-        dmarc_policy: extract_dmarc_policy(dmarc_record.as_deref()),
+        spf_analysis,
+        dmarc_record: dmarc_policy.as_ref().map(|policy| policy.record.clone()),
+        dmarc_record_found: Some(dmarc_policy.is_some()),
+        dmarc_policy,
+        dkim: Some(dkim),
+        mta_sts,
+        tls_rpt,
+        bimi,
+        dnssec: Some(dnssec),
+        mx_tlsa: Some(mx_tlsa),
+        inbound_dane: Some(inbound_dane),
         ms_txt_record: None, // TODO: Extract MS TXT record if needed
         ms_txt_found: None,
         ms_adfs_auth_txt_record: None, // TODO: Extract ADFS auth TXT record if needed
@@ -79,95 +277,163 @@ pub async fn run_dns_checks(domain: &str) -> Result<DnsResults, ReconError> {
         autodiscover_cname_or_a,
         lyncdiscover_present: Some(lyncdiscover_present),
         sip_cname_or_a_present: Some(sip_cname_or_a_present),
-        sipfederationtls_tcp_present: None, // TODO: Add check if needed
-        sip_tls_present: None, // TODO: Add check if needed
+        sipfederationtls_tcp_present: srv_present("_sipfederationtls._tcp"),
+        sip_tls_present: srv_present("_sip._tls"),
+        srv_records: Some(srv_records),
+        cname_chains: Some(cname_chains),
+        takeover_candidates: Some(takeover_candidates),
+        subdomains,
+        nameservers,
+        resolved_hosts: Some(resolved_hosts),
     })
 }
 
-/// Resolves MX records for the given domain.
-async fn get_mx_records(resolver: TokioAsyncResolver, domain: String) -> Result<Vec<String>, ReconError> {
+/// Resolves and classifies MX records for the given domain, sorted by priority.
+///
+/// A domain without MX records yields an empty list rather than an error.
+async fn get_mx_records(resolver: TokioAsyncResolver, domain: String) -> Result<Vec<MxHost>, ReconError> {
     debug!(domain = domain.as_str(), "Querying MX records");
-    let response = resolver.mx_lookup(domain.as_str()).await?;
-    let records: Vec<String> = response
+    let response = match resolver.mx_lookup(domain.as_str()).await {
+        Ok(response) => response,
+        Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+            debug!(domain = domain.as_str(), "No MX records");
+            return Ok(Vec::new());
+        }
+        Err(e) => return Err(e.into()),
+    };
+    let mut records: Vec<MxHost> = response
         .iter()
-        .map(|mx| mx.exchange().to_string())
+        .map(|mx| classify_mx_host(&mx.exchange().to_string(), mx.preference()))
         .collect();
+    records.sort_by_key(|mx| mx.priority);
     debug!(domain = domain.as_str(), count = records.len(), "Found MX records");
     Ok(records)
 }
 
+/// Resolves the well-known SRV services for the given domain.
+///
+/// Services without records (NXDOMAIN/NODATA) are skipped rather than treated as errors; services
+/// whose lookup failed otherwise (timeout, SERVFAIL) are returned alongside the records.
+async fn get_srv_records(resolver: TokioAsyncResolver, domain: String) -> Result<(Vec<SrvRecord>, Vec<String>), ReconError> {
+    let lookups = SRV_SERVICES.iter().map(|service| {
+        let resolver = resolver.clone();
+        let name = format!("{}.{}", service, domain);
+        async move {
+            debug!(domain = name.as_str(), "Querying SRV records");
+            match resolver.srv_lookup(name.as_str()).await {
+                Ok(response) => Ok(response
+                    .iter()
+                    .map(|srv| {
+                        let target = srv.target().to_string().trim_end_matches('.').to_lowercase();
+                        SrvRecord {
+                            service: service.to_string(),
+                            hosting: classify_hosting(&target),
+                            target,
+                            port: srv.port(),
+                            priority: srv.priority(),
+                            weight: srv.weight(),
+                        }
+                    })
+                    .collect::<Vec<_>>()),
+                Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+                    debug!(domain = name.as_str(), "No SRV records");
+                    Ok(Vec::new())
+                }
+                Err(e) => {
+                    warn!(domain = name.as_str(), error = %e, "SRV lookup failed");
+                    Err(service.to_string())
+                }
+            }
+        }
+    });
+
+    let mut records: Vec<SrvRecord> = Vec::new();
+    let mut failed_services: Vec<String> = Vec::new();
+    for result in join_all(lookups).await {
+        match result {
+            Ok(service_records) => records.extend(service_records),
+            Err(service) => failed_services.push(service),
+        }
+    }
+    debug!(domain = domain.as_str(), count = records.len(), failed = failed_services.len(), "Found SRV records");
+    Ok((records, failed_services))
+}
+
+/// Whether `service` has SRV records; `None` when its lookup failed.
+fn srv_presence(records: &[SrvRecord], failed_services: &[String], service: &str) -> Option<bool> {
+    if failed_services.iter().any(|failed| failed == service) {
+        return None;
+    }
+    Some(records.iter().any(|srv| srv.service == service))
+}
+
+/// Classifies a host name as Microsoft-hosted or on-premises by its DNS suffix.
+pub fn classify_hosting(host: &str) -> HostingType {
+    let host = host.trim_end_matches('.').to_lowercase();
+    if MICROSOFT_HOST_SUFFIXES.iter().any(|suffix| host.ends_with(suffix)) {
+        HostingType::MicrosoftHosted
+    } else {
+        HostingType::OnPremises
+    }
+}
+
 /// Resolves TXT records for the given domain.
-async fn get_txt_records(resolver: TokioAsyncResolver, domain: String) -> Result<Vec<String>, ReconError> {
+pub async fn get_txt_records(resolver: TokioAsyncResolver, domain: String) -> Result<Vec<String>, ReconError> {
     debug!(domain = domain.as_str(), "Querying TXT records");
     let response = resolver.txt_lookup(domain.as_str()).await?;
     let records: Vec<String> = response
         .iter()
-        .flat_map(|txt| txt.iter().map(|bytes| String::from_utf8_lossy(bytes).to_string()))
+        // Long records are split into several character-strings; join them back together
+        .map(|txt| txt.iter().map(|bytes| String::from_utf8_lossy(bytes)).collect::<String>())
         .collect();
     debug!(domain = domain.as_str(), count = records.len(), "Found TXT records");
     Ok(records)
 }
 
-/// Checks for Autodiscover CNAME or A record.
-async fn check_autodiscover(resolver: TokioAsyncResolver, domain: String) -> Result<Option<String>, ReconError> {
+/// Checks for an Autodiscover CNAME or A record.
+///
+/// Returns the final target of the CNAME chain, or the first address if there is no CNAME.
+async fn check_autodiscover(resolver: TokioAsyncResolver, limiter: Arc<QueryLimiter>, domain: String) -> Result<Option<String>, ReconError> {
     let autodiscover_domain = format!("autodiscover.{}", domain);
     debug!(domain = autodiscover_domain.as_str(), "Checking autodiscover");
-    
-    // First try CNAME lookup
-    match resolver.lookup(autodiscover_domain.as_str(), RecordType::CNAME).await {
-        Ok(cname_response) => {
-            if let Some(cname) = cname_response.iter().next() {
-                if let Some(name) = cname.as_cname() {
-                    return Ok(Some(name.to_string()));
-                }
-            }
-        }
-        Err(e) => debug!(error = %e, "CNAME lookup failed, will try A record"),
-    }
-    
-    // If CNAME fails, try A record
-    match resolver.lookup_ip(autodiscover_domain.as_str()).await {
-        Ok(a_response) => {
-            if let Some(ip) = a_response.iter().next() {
-                return Ok(Some(ip.to_string()));
-            }
-        }
-        Err(e) => debug!(error = %e, "A record lookup failed"),
-    }
-    
-    Ok(None) // No autodiscover record found
+    let chain = resolve_cname_chain(&resolver, &limiter, &autodiscover_domain).await;
+    Ok(chain_target(&chain))
 }
 
-/// Generic check if a DNS record exists (A or CNAME).
-async fn check_record_presence(resolver: TokioAsyncResolver, domain: String) -> Result<bool, ReconError> {
+/// Generic check if a DNS record exists (A/AAAA, or a CNAME chain, even a dangling one).
+async fn check_record_presence(resolver: TokioAsyncResolver, limiter: Arc<QueryLimiter>, domain: String) -> Result<bool, ReconError> {
     debug!(domain = domain.as_str(), "Checking record presence");
-    
-    // Try A/AAAA lookup
-    let ip_result = resolver.lookup_ip(domain.as_str()).await;
-    if let Ok(response) = ip_result {
-        if response.iter().next().is_some() {
-            return Ok(true);
-        }
+    let chain = resolve_cname_chain(&resolver, &limiter, &domain).await;
+    Ok(chain.chain.len() > 1 || !chain.addresses.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srv_presence() {
+        let records = vec![SrvRecord {
+            service: "_sip._tls".to_string(),
+            target: "sipdir.online.lync.com".to_string(),
+            hosting: HostingType::MicrosoftHosted,
+            port: 443,
+            priority: 100,
+            weight: 1,
+        }];
+        let failed = vec!["_sipfederationtls._tcp".to_string()];
+        assert_eq!(srv_presence(&records, &failed, "_sip._tls"), Some(true));
+        assert_eq!(srv_presence(&records, &failed, "_autodiscover._tcp"), Some(false));
+        assert_eq!(srv_presence(&records, &failed, "_sipfederationtls._tcp"), None);
     }
-    
-    // Try CNAME lookup
-    let cname_result = resolver.lookup(domain.as_str(), RecordType::CNAME).await;
-    if let Ok(response) = cname_result {
-        if response.iter().next().is_some() {
-            return Ok(true);
-        }
+
+    #[test]
+    fn test_classify_hosting() {
+        assert_eq!(classify_hosting("sipfed.online.lync.com."), HostingType::MicrosoftHosted);
+        assert_eq!(classify_hosting("sipdir.online.gov.skypeforbusiness.us"), HostingType::MicrosoftHosted);
+        assert_eq!(classify_hosting("autodiscover.outlook.com"), HostingType::MicrosoftHosted);
+        assert_eq!(classify_hosting("sipedge.contoso.com"), HostingType::OnPremises);
+        // Suffix matching must not accept look-alike zones
+        assert_eq!(classify_hosting("evil-lync.com"), HostingType::OnPremises);
     }
-    
-    Ok(false)
 }
-
-/// Extract the DMARC policy from a DMARC record.
-fn extract_dmarc_policy(dmarc_record: Option<&str>) -> Option<String> {
-    dmarc_record.and_then(|record| {
-        record
-            .split(';')
-            .map(str::trim)
-            .find(|part| part.to_lowercase().starts_with("p="))
-            .map(|policy_part| policy_part[2..].to_string())
-    })
-}
\ No newline at end of file
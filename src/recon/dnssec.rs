@@ -0,0 +1,216 @@
+use crate::error::ReconError;
+use crate::models::{DnssecResults, DnssecStatus, MxTlsaResult, TlsaRecord};
+use futures::future::join_all;
+use tracing::{debug, info};
+use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+use trust_dns_resolver::proto::error::ProtoErrorKind;
+use trust_dns_resolver::proto::op::ResponseCode;
+use trust_dns_resolver::proto::rr::dnssec::rdata::DNSSECRData;
+use trust_dns_resolver::proto::rr::{RData, RecordType};
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Errors the validating resolver reports when an RRSIG, DNSKEY or NSEC proof fails to verify.
+/// Other messages are generic lookup failures and leave the status indeterminate.
+const VALIDATION_FAILURE_MESSAGES: &[&str] = &[
+    "validation failed",
+    "Could not validate all DNSKEYs",
+    "self-signed dnskey is invalid",
+    "could not validate negative response with NSEC",
+    "could not validate negative response missing SOA",
+];
+
+/// Determines the DNSSEC status of the target zone and the algorithms in use.
+///
+/// A zone without a DS record at its parent is unsigned. A zone with a DS record is
+/// signed if a validating lookup of its SOA succeeds, and bogus if validation fails.
+/// Lookups that fail for other reasons (timeouts, SERVFAIL) leave the status indeterminate.
+pub async fn check_dnssec(resolver: TokioAsyncResolver, domain: String) -> Result<DnssecResults, ReconError> {
+    debug!(domain = domain.as_str(), "Checking DNSSEC status");
+    let ds_lookup = lookup_algorithms(&resolver, &domain, RecordType::DS).await;
+    let dnskey_lookup = lookup_algorithms(&resolver, &domain, RecordType::DNSKEY).await;
+
+    let (status, validation_error) = match zone_status(&ds_lookup, &dnskey_lookup) {
+        Some(decided) => decided,
+        None => {
+            let mut options = ResolverOpts::default();
+            options.validate = true;
+            let validating_resolver = TokioAsyncResolver::tokio(ResolverConfig::google(), options);
+            let validation = validating_resolver.lookup(domain.as_str(), RecordType::SOA).await.map(|_| ());
+            validation_status(&validation)
+        }
+    };
+
+    info!(domain = domain.as_str(), status = ?status, "Finished DNSSEC check");
+    Ok(DnssecResults {
+        status,
+        ds_algorithms: ds_lookup.unwrap_or_default(),
+        dnskey_algorithms: dnskey_lookup.unwrap_or_default(),
+        validation_error,
+    })
+}
+
+/// Status decided from the DS and DNSKEY lookups alone, or `None` when a validating lookup
+/// has to decide between signed and bogus.
+fn zone_status(
+    ds_lookup: &Result<Vec<String>, String>,
+    dnskey_lookup: &Result<Vec<String>, String>,
+) -> Option<(DnssecStatus, Option<String>)> {
+    match (ds_lookup, dnskey_lookup) {
+        (Err(e), _) => Some((DnssecStatus::Indeterminate, Some(format!("DS lookup failed: {}", e)))),
+        (Ok(ds), _) if ds.is_empty() => Some((DnssecStatus::Unsigned, None)),
+        (Ok(_), Err(e)) => Some((DnssecStatus::Indeterminate, Some(format!("DNSKEY lookup failed: {}", e)))),
+        (Ok(_), Ok(dnskeys)) if dnskeys.is_empty() => Some((
+            DnssecStatus::Bogus,
+            Some("DS published at the parent but the zone serves no DNSKEY".to_string()),
+        )),
+        _ => None,
+    }
+}
+
+/// Status from the validating SOA lookup: only a failed signature check makes the zone bogus.
+fn validation_status(validation: &Result<(), ResolveError>) -> (DnssecStatus, Option<String>) {
+    let Err(e) = validation else {
+        return (DnssecStatus::Signed, None);
+    };
+    let validation_failed = match e.kind() {
+        ResolveErrorKind::Proto(proto) => match proto.kind() {
+            ProtoErrorKind::RrsigsNotPresent { .. } => true,
+            ProtoErrorKind::Message(message) => VALIDATION_FAILURE_MESSAGES.contains(message),
+            _ => false,
+        },
+        _ => false,
+    };
+    let status = if validation_failed { DnssecStatus::Bogus } else { DnssecStatus::Indeterminate };
+    (status, Some(e.to_string()))
+}
+
+/// Inbound DANE needs a signed zone and a TLSA record for every MX host.
+pub fn inbound_dane(status: &DnssecStatus, mx_tlsa: &[MxTlsaResult]) -> bool {
+    *status == DnssecStatus::Signed && !mx_tlsa.is_empty() && mx_tlsa.iter().all(|mx| !mx.records.is_empty())
+}
+
+/// Looks up the DANE TLSA records at `_25._tcp.<mx>` for each MX host.
+pub async fn check_mx_tlsa(resolver: TokioAsyncResolver, mx_hosts: Vec<String>) -> Result<Vec<MxTlsaResult>, ReconError> {
+    let lookups = mx_hosts.into_iter().map(|mx_host| {
+        let resolver = resolver.clone();
+        async move {
+            let mx_host = mx_host.trim_end_matches('.').to_lowercase();
+            let name = format!("_25._tcp.{}", mx_host);
+            debug!(domain = name.as_str(), "Querying TLSA records");
+            let records = match resolver.lookup(name.as_str(), RecordType::TLSA).await {
+                Ok(response) => response
+                    .iter()
+                    .filter_map(|rdata| match rdata {
+                        RData::TLSA(tlsa) => Some(TlsaRecord {
+                            cert_usage: u8::from(tlsa.cert_usage()),
+                            selector: u8::from(tlsa.selector()),
+                            matching_type: u8::from(tlsa.matching()),
+                            certificate_data: tlsa.cert_data().iter().map(|byte| format!("{:02x}", byte)).collect(),
+                        }),
+                        _ => None,
+                    })
+                    .collect(),
+                Err(e) => {
+                    debug!(domain = name.as_str(), error = %e, "No TLSA records");
+                    Vec::new()
+                }
+            };
+            MxTlsaResult { mx_host, records }
+        }
+    });
+
+    Ok(join_all(lookups).await)
+}
+
+/// Returns the distinct algorithms of the DS or DNSKEY records published for `domain`.
+///
+/// An answer without such records is empty; other failures (timeouts, SERVFAIL) are errors.
+async fn lookup_algorithms(
+    resolver: &TokioAsyncResolver,
+    domain: &str,
+    record_type: RecordType,
+) -> Result<Vec<String>, String> {
+    let mut algorithms: Vec<String> = Vec::new();
+    match resolver.lookup(domain, record_type).await {
+        Ok(response) => {
+            for rdata in response.iter() {
+                let algorithm = match rdata {
+                    RData::DNSSEC(DNSSECRData::DS(ds)) => ds.algorithm().to_string(),
+                    RData::DNSSEC(DNSSECRData::DNSKEY(key)) => key.algorithm().to_string(),
+                    _ => continue,
+                };
+                if !algorithms.contains(&algorithm) {
+                    algorithms.push(algorithm);
+                }
+            }
+        }
+        Err(e) => match e.kind() {
+            ResolveErrorKind::NoRecordsFound { response_code, .. }
+                if matches!(*response_code, ResponseCode::NoError | ResponseCode::NXDomain) => {}
+            _ => {
+                debug!(domain = domain, record_type = %record_type, error = %e, "DNSSEC record lookup failed");
+                return Err(e.to_string());
+            }
+        },
+    }
+    Ok(algorithms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use trust_dns_resolver::proto::error::ProtoError;
+    use trust_dns_resolver::proto::rr::Name;
+
+    #[test]
+    fn test_zone_status() {
+        let algorithms = || Ok(vec!["RSASHA256".to_string()]);
+        assert_eq!(zone_status(&Ok(Vec::new()), &Ok(Vec::new())), Some((DnssecStatus::Unsigned, None)));
+        let (status, error) = zone_status(&Err("request timed out".to_string()), &algorithms()).unwrap();
+        assert_eq!(status, DnssecStatus::Indeterminate);
+        assert_eq!(error.as_deref(), Some("DS lookup failed: request timed out"));
+        assert_eq!(zone_status(&algorithms(), &Err("SERVFAIL".to_string())).unwrap().0, DnssecStatus::Indeterminate);
+        assert_eq!(zone_status(&algorithms(), &Ok(Vec::new())).unwrap().0, DnssecStatus::Bogus);
+        assert_eq!(zone_status(&algorithms(), &algorithms()), None);
+    }
+
+    #[test]
+    fn test_validation_status() {
+        assert_eq!(validation_status(&Ok(())), (DnssecStatus::Signed, None));
+        let missing_rrsigs = ProtoError::from(ProtoErrorKind::RrsigsNotPresent {
+            name: Name::from_ascii("contoso.com.").unwrap(),
+            record_type: RecordType::SOA,
+        });
+        assert_eq!(validation_status(&Err(missing_rrsigs.into())).0, DnssecStatus::Bogus);
+        let (status, error) = validation_status(&Err(ResolveErrorKind::Timeout.into()));
+        assert_eq!(status, DnssecStatus::Indeterminate);
+        assert_eq!(error.as_deref(), Some("request timed out"));
+        assert_eq!(validation_status(&Err(ResolveErrorKind::NoConnections.into())).0, DnssecStatus::Indeterminate);
+        let bad_signature = ProtoError::from(ProtoErrorKind::Message("validation failed"));
+        assert_eq!(validation_status(&Err(bad_signature.into())).0, DnssecStatus::Bogus);
+        let generic = ProtoError::from(ProtoErrorKind::Msg("unexpected response".to_string()));
+        assert_eq!(validation_status(&Err(generic.into())).0, DnssecStatus::Indeterminate);
+        let depth = ProtoError::from(ProtoErrorKind::Message("exceeded max validation depth"));
+        assert_eq!(validation_status(&Err(depth.into())).0, DnssecStatus::Indeterminate);
+    }
+
+    #[test]
+    fn test_inbound_dane() {
+        let record = TlsaRecord {
+            cert_usage: 3,
+            selector: 1,
+            matching_type: 1,
+            certificate_data: "ab".to_string(),
+        };
+        let mx = |records: Vec<TlsaRecord>| MxTlsaResult {
+            mx_host: "mx1.contoso.com".to_string(),
+            records,
+        };
+        let covered = vec![mx(vec![record.clone()]), mx(vec![record])];
+        assert!(inbound_dane(&DnssecStatus::Signed, &covered));
+        assert!(!inbound_dane(&DnssecStatus::Indeterminate, &covered));
+        assert!(!inbound_dane(&DnssecStatus::Signed, &[]));
+        assert!(!inbound_dane(&DnssecStatus::Signed, &[covered[0].clone(), mx(Vec::new())]));
+    }
+}
@@ -0,0 +1,133 @@
+use crate::config::{CloudConfig, DynamicsRegion};
+use crate::error::ReconError;
+use crate::models::{DynamicsEnvironment, DynamicsResults};
+use crate::recon::common::{is_guid, tenant_label_candidates};
+use futures::future::join_all;
+use reqwest::{Client, Url};
+use tracing::{debug, info};
+
+/// Looks for Dynamics 365 / Dataverse environments named after the tenant or domain in every
+/// regional host of the cloud.
+///
+/// Environments send anonymous visitors to their tenant's sign-in page, whose path carries the
+/// tenant ID; a match with the target's tenant ID confirms the environment belongs to it.
+pub async fn check_dynamics(
+    client: Client,
+    domain: String,
+    tenant_name: Option<String>,
+    tenant_id: Option<String>,
+    config: CloudConfig,
+) -> Result<DynamicsResults, ReconError> {
+    let candidates = tenant_label_candidates(tenant_name.as_deref(), &domain);
+    let login_host = config.login_microsoftonline_host.as_str();
+    debug!(
+        domain = domain.as_str(),
+        candidates = candidates.len(),
+        regions = config.dynamics_regions.len(),
+        "Checking Dynamics 365 environments"
+    );
+
+    let client = &client;
+    let probes = candidates.iter().flat_map(|name| {
+        config.dynamics_regions.iter().map(move |region| {
+            let url = format!("https://{}{}", name, region.host_suffix);
+            probe_dynamics_environment(client, url, region, login_host)
+        })
+    });
+    let mut environments: Vec<DynamicsEnvironment> = join_all(probes).await.into_iter().flatten().collect();
+    confirm_tenant(&mut environments, tenant_id.as_deref());
+
+    info!(domain = domain.as_str(), found = environments.len(), "Finished Dynamics 365 checks");
+    Ok(DynamicsResults {
+        candidates_tried: candidates,
+        environments,
+    })
+}
+
+/// Marks the environments whose sign-in redirect names the target's tenant ID.
+fn confirm_tenant(environments: &mut [DynamicsEnvironment], tenant_id: Option<&str>) {
+    for environment in environments {
+        environment.tenant_confirmed = match (environment.tenant_id.as_deref(), tenant_id) {
+            (Some(found), Some(expected)) => found.eq_ignore_ascii_case(expected),
+            _ => false,
+        };
+    }
+}
+
+/// Requests an environment URL and reports it when it redirects to the sign-in host.
+pub async fn probe_dynamics_environment(
+    client: &Client,
+    url: String,
+    region: &DynamicsRegion,
+    login_host: &str,
+) -> Option<DynamicsEnvironment> {
+    let response = match client.get(&url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            // Unknown organisation names do not resolve
+            debug!(url = url.as_str(), error = %e, "Dynamics 365 request failed");
+            return None;
+        }
+    };
+    let tenant_id = sign_in_tenant(response.url(), login_host)?;
+    debug!(url = url.as_str(), tenant_id = tenant_id.as_deref(), "Dynamics 365 environment found");
+    Some(DynamicsEnvironment {
+        url,
+        geography: region.geography.clone(),
+        tenant_id,
+        tenant_confirmed: false,
+    })
+}
+
+/// `Some` when `final_url` is on the sign-in host, with the tenant ID from its first path segment
+/// (e.g., `/72f988bf-86f1-41af-91ab-2d7cd011db47/oauth2/authorize`) when it is a GUID.
+fn sign_in_tenant(final_url: &Url, login_host: &str) -> Option<Option<String>> {
+    if !final_url.host_str().is_some_and(|host| host.eq_ignore_ascii_case(login_host)) {
+        return None;
+    }
+    let segment = final_url.path_segments().and_then(|mut segments| segments.next()).unwrap_or_default();
+    Some(is_guid(segment).then(|| segment.to_lowercase()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_sign_in_tenant() {
+        let login_host = "login.microsoftonline.com";
+        let redirect = Url::parse(
+            "https://login.microsoftonline.com/72F988BF-86F1-41AF-91AB-2D7CD011DB47/oauth2/authorize?client_id=00000007-0000-0000-c000-000000000000",
+        )
+        .unwrap();
+        assert_eq!(
+            sign_in_tenant(&redirect, login_host),
+            Some(Some("72f988bf-86f1-41af-91ab-2d7cd011db47".to_string()))
+        );
+        let common = Url::parse("https://login.microsoftonline.com/common/oauth2/authorize").unwrap();
+        assert_eq!(sign_in_tenant(&common, login_host), Some(None));
+        let elsewhere = Url::parse("https://www.microsoft.com/dynamics-365").unwrap();
+        assert_eq!(sign_in_tenant(&elsewhere, login_host), None);
+    }
+
+    #[test]
+    fn test_confirm_tenant() {
+        let environment = |tenant_id: Option<&str>| DynamicsEnvironment {
+            url: "https://contoso.crm4.dynamics.com".to_string(),
+            geography: "EMEA".to_string(),
+            tenant_id: tenant_id.map(str::to_string),
+            tenant_confirmed: false,
+        };
+        let mut environments = vec![
+            environment(Some("72f988bf-86f1-41af-91ab-2d7cd011db47")),
+            environment(Some("f8cdef31-a31e-4b4a-93e4-5f571e91255a")),
+            environment(None),
+        ];
+        confirm_tenant(&mut environments, Some("72F988BF-86F1-41AF-91AB-2D7CD011DB47"));
+        let confirmed: Vec<bool> = environments.iter().map(|environment| environment.tenant_confirmed).collect();
+        assert_eq!(confirmed, vec![true, false, false]);
+
+        confirm_tenant(&mut environments, None);
+        assert!(environments.iter().all(|environment| !environment.tenant_confirmed));
+    }
+}
@@ -0,0 +1,295 @@
+use crate::error::ReconError;
+use crate::models::{BimiResults, MtaStsMode, MtaStsResults, TlsRptResults};
+use crate::recon::dns::get_txt_records;
+use reqwest::{header, Client};
+use tracing::{debug, info};
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Policy fields parsed from an `mta-sts.txt` file.
+#[derive(Debug, Default, PartialEq)]
+struct MtaStsPolicyFile {
+    version: Option<String>,
+    mode: Option<MtaStsMode>,
+    mx_patterns: Vec<String>,
+    max_age: Option<u64>,
+}
+
+/// Checks the MTA-STS TXT record and policy file, comparing the policy's `mx`
+/// patterns with the MX hosts observed in DNS.
+///
+/// Returns `None` if the domain publishes neither the TXT record nor a policy file.
+/// `client` must not follow redirects (see `fetch_mta_sts_policy`).
+pub async fn check_mta_sts(
+    resolver: TokioAsyncResolver,
+    client: Client,
+    domain: String,
+    mx_hosts: Vec<String>,
+) -> Result<Option<MtaStsResults>, ReconError> {
+    let txt_record = get_txt_records(resolver, format!("_mta-sts.{}", domain))
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .find(|txt| txt.to_lowercase().starts_with("v=stsv1"));
+
+    let policy_url = format!("https://mta-sts.{}/.well-known/mta-sts.txt", domain);
+    let (policy_body, content_type) = match fetch_mta_sts_policy(&client, &policy_url).await {
+        Some((body, content_type)) => (Some(body), content_type),
+        None => (None, None),
+    };
+
+    if txt_record.is_none() && policy_body.is_none() {
+        debug!(domain = domain.as_str(), "No MTA-STS deployment found");
+        return Ok(None);
+    }
+
+    let policy = policy_body.as_deref().map(parse_mta_sts_policy).unwrap_or_default();
+    let policy_id = txt_record.as_deref().and_then(|txt| tag_value(txt, "id"));
+    let mut issues = Vec::new();
+
+    match (&txt_record, &policy_body) {
+        (Some(_), None) => issues.push("TXT record published but the policy file could not be retrieved".to_string()),
+        (None, Some(_)) => issues.push("Policy file served but no _mta-sts TXT record; senders will not fetch it".to_string()),
+        _ => {}
+    }
+    if policy_body.is_some() {
+        if policy.version.as_deref() != Some("STSv1") {
+            issues.push("Policy file is missing 'version: STSv1'".to_string());
+        }
+        if !content_type.as_deref().is_some_and(|ct| ct.starts_with("text/plain")) {
+            issues.push("Policy file is not served as text/plain".to_string());
+        }
+        match policy.mode {
+            Some(MtaStsMode::Testing) => issues.push("mode: testing only reports failures".to_string()),
+            Some(MtaStsMode::None) => issues.push("mode: none disables MTA-STS".to_string()),
+            Some(MtaStsMode::Enforce) => {}
+            None => issues.push("Policy file has no valid mode".to_string()),
+        }
+    }
+
+    let unmatched_mx_hosts: Vec<String> = if policy_body.is_some() {
+        mx_hosts
+            .iter()
+            .map(|host| host.trim_end_matches('.').to_lowercase())
+            .filter(|host| !policy.mx_patterns.iter().any(|pattern| mx_pattern_matches(pattern, host)))
+            .collect()
+    } else {
+        Vec::new()
+    };
+    for host in &unmatched_mx_hosts {
+        issues.push(format!("MX host {} does not match any policy mx pattern", host));
+    }
+
+    info!(domain = domain.as_str(), "Finished MTA-STS check");
+    Ok(Some(MtaStsResults {
+        txt_record,
+        policy_id,
+        policy_url,
+        policy_found: policy_body.is_some(),
+        mode: policy.mode,
+        mx_patterns: policy.mx_patterns,
+        max_age: policy.max_age,
+        unmatched_mx_hosts,
+        issues,
+    }))
+}
+
+/// Fetches an MTA-STS policy file, returning its body and content type.
+///
+/// Senders must not follow redirects for the policy (RFC 8461 §3.3), so a 3xx answer, like any
+/// other non-success status, means the policy cannot be retrieved. This also keeps catch-all
+/// `mta-sts.` hosts that redirect elsewhere from looking like they serve a policy.
+pub async fn fetch_mta_sts_policy(client: &Client, url: &str) -> Option<(String, Option<String>)> {
+    debug!(url = url, "Fetching MTA-STS policy");
+    let response = match client.get(url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            debug!(url = url, error = %e, "MTA-STS policy request failed");
+            return None;
+        }
+    };
+    if !response.status().is_success() {
+        debug!(url = url, status = %response.status(), "MTA-STS policy not served");
+        return None;
+    }
+    let content_type = response
+        .headers()
+        .get(header::CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string);
+    let body = response.text().await.ok()?;
+    Some((body, content_type))
+}
+
+/// Checks for an SMTP TLS reporting record at `_smtp._tls.<domain>`.
+pub async fn check_tls_rpt(resolver: TokioAsyncResolver, domain: String) -> Result<Option<TlsRptResults>, ReconError> {
+    let record = get_txt_records(resolver, format!("_smtp._tls.{}", domain))
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .find(|txt| txt.to_lowercase().starts_with("v=tlsrptv1"));
+
+    Ok(record.map(|record| {
+        let reporting_uris = tag_value(&record, "rua")
+            .map(|rua| rua.split(',').map(|uri| uri.trim().to_string()).collect())
+            .unwrap_or_default();
+        TlsRptResults { record, reporting_uris }
+    }))
+}
+
+/// Checks the BIMI record at `default._bimi.<domain>` and fetches the logo and VMC it declares.
+pub async fn check_bimi(
+    resolver: TokioAsyncResolver,
+    client: Client,
+    domain: String,
+) -> Result<Option<BimiResults>, ReconError> {
+    let Some(record) = get_txt_records(resolver, format!("default._bimi.{}", domain))
+        .await
+        .unwrap_or_default()
+        .into_iter()
+        .find(|txt| txt.to_lowercase().starts_with("v=bimi1"))
+    else {
+        return Ok(None);
+    };
+
+    let logo_url = tag_value(&record, "l").filter(|url| !url.is_empty());
+    let vmc_url = tag_value(&record, "a").filter(|url| !url.is_empty());
+    let mut issues = Vec::new();
+
+    let logo_is_svg = match &logo_url {
+        Some(url) => fetch_asset(&client, url, &mut issues).await.map(|(content_type, body)| {
+            content_type.is_some_and(|ct| ct.contains("svg")) || body.contains("<svg")
+        }),
+        None => {
+            issues.push("No logo (l=) declared".to_string());
+            None
+        }
+    };
+    if logo_is_svg == Some(false) {
+        issues.push("Logo URL does not serve an SVG image".to_string());
+    }
+
+    let vmc_is_certificate = match &vmc_url {
+        Some(url) => fetch_asset(&client, url, &mut issues)
+            .await
+            .map(|(_, body)| body.contains("-----BEGIN CERTIFICATE-----")),
+        None => {
+            issues.push("No Verified Mark Certificate (a=); most mailbox providers will not show the logo".to_string());
+            None
+        }
+    };
+    if vmc_is_certificate == Some(false) {
+        issues.push("VMC URL does not serve a PEM certificate".to_string());
+    }
+
+    info!(domain = domain.as_str(), "Finished BIMI check");
+    Ok(Some(BimiResults {
+        record,
+        logo_url,
+        logo_is_svg,
+        vmc_url,
+        vmc_is_certificate,
+        issues,
+    }))
+}
+
+/// Fetches a BIMI asset, returning its content type and body, or `None` (with an issue) on failure.
+async fn fetch_asset(client: &Client, url: &str, issues: &mut Vec<String>) -> Option<(Option<String>, String)> {
+    if !url.starts_with("https://") {
+        issues.push(format!("{} is not an HTTPS URL", url));
+    }
+    match client.get(url).send().await {
+        Ok(response) if response.status().is_success() => {
+            let content_type = response
+                .headers()
+                .get(header::CONTENT_TYPE)
+                .and_then(|value| value.to_str().ok())
+                .map(str::to_lowercase);
+            response.text().await.ok().map(|body| (content_type, body))
+        }
+        Ok(response) => {
+            issues.push(format!("{} returned status {}", url, response.status()));
+            None
+        }
+        Err(e) => {
+            debug!(url = url, error = %e, "BIMI asset request failed");
+            issues.push(format!("{} could not be retrieved", url));
+            None
+        }
+    }
+}
+
+/// Returns the value of a `tag=value` entry in a semicolon-separated TXT record.
+fn tag_value(record: &str, tag: &str) -> Option<String> {
+    record.split(';').find_map(|part| {
+        let (name, value) = part.split_once('=')?;
+        name.trim().eq_ignore_ascii_case(tag).then(|| value.trim().to_string())
+    })
+}
+
+/// Parses the `key: value` lines of an MTA-STS policy file.
+fn parse_mta_sts_policy(body: &str) -> MtaStsPolicyFile {
+    let mut policy = MtaStsPolicyFile::default();
+    for line in body.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key.trim().to_lowercase().as_str() {
+            "version" => policy.version = Some(value.to_string()),
+            "mode" => {
+                policy.mode = match value.to_lowercase().as_str() {
+                    "enforce" => Some(MtaStsMode::Enforce),
+                    "testing" => Some(MtaStsMode::Testing),
+                    "none" => Some(MtaStsMode::None),
+                    _ => None,
+                }
+            }
+            "mx" => policy.mx_patterns.push(value.to_lowercase()),
+            "max_age" => policy.max_age = value.parse().ok(),
+            _ => {}
+        }
+    }
+    policy
+}
+
+/// Matches an MX host against a policy `mx` pattern, where `*.` covers exactly one label.
+fn mx_pattern_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.trim_end_matches('.');
+    match pattern.strip_prefix("*.") {
+        Some(suffix) => host
+            .split_once('.')
+            .is_some_and(|(_, rest)| rest.eq_ignore_ascii_case(suffix)),
+        None => host.eq_ignore_ascii_case(pattern),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_mta_sts_policy() {
+        let body = "version: STSv1\r\nmode: enforce\r\nmx: *.mail.protection.outlook.com\r\nmx: backup.contoso.com\r\nmax_age: 604800\r\n";
+        let policy = parse_mta_sts_policy(body);
+        assert_eq!(policy.version.as_deref(), Some("STSv1"));
+        assert_eq!(policy.mode, Some(MtaStsMode::Enforce));
+        assert_eq!(policy.mx_patterns, vec!["*.mail.protection.outlook.com", "backup.contoso.com"]);
+        assert_eq!(policy.max_age, Some(604800));
+    }
+
+    #[test]
+    fn test_mx_pattern_matches() {
+        assert!(mx_pattern_matches("*.mail.protection.outlook.com", "contoso-com.mail.protection.outlook.com"));
+        assert!(!mx_pattern_matches("*.mail.protection.outlook.com", "a.b.mail.protection.outlook.com"));
+        assert!(mx_pattern_matches("mx1.contoso.com", "MX1.contoso.com"));
+        assert!(!mx_pattern_matches("mx1.contoso.com", "mx2.contoso.com"));
+    }
+
+    #[test]
+    fn test_tag_value() {
+        let record = "v=BIMI1; l=https://contoso.com/logo.svg; a=";
+        assert_eq!(tag_value(record, "l").as_deref(), Some("https://contoso.com/logo.svg"));
+        assert_eq!(tag_value(record, "a").as_deref(), Some(""));
+        assert_eq!(tag_value(record, "x"), None);
+    }
+}
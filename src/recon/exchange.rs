@@ -0,0 +1,286 @@
+use crate::error::ReconError;
+use crate::models::{AuthEndpoint, AutodiscoverResults, DnsResults, ExchangeFingerprint, MailProvider};
+use crate::recon::common::EXCHANGE_HOST_LABELS;
+use crate::recon::legacy_auth::self_hosted_hosts;
+use futures::future::join_all;
+use reqwest::{Client, Url};
+use tracing::{debug, info, warn};
+
+/// Response header carrying the full Exchange build on OWA, ECP and EWS responses.
+const OWA_VERSION_HEADER: &str = "X-OWA-Version";
+
+/// Front-end paths requested from each host, with the resource path prefixes their pages embed
+/// the build in, e.g., `/owa/auth/15.2.1544/themes/resources/favicon.ico`.
+const FINGERPRINT_PATHS: &[(&str, &[&str])] = &[
+    ("/owa/", &["/owa/auth/", "/owa/"]),
+    ("/ecp/", &["/ecp/"]),
+    ("/EWS/Exchange.asmx", &[]),
+];
+
+/// Exchange Server releases by build: (major, minor, build, version, update, release date).
+/// Sorted by build within each major.minor; security updates keep the build of their cumulative update.
+const EXCHANGE_BUILDS: &[(u32, u32, u32, &str, &str, &str)] = &[
+    (14, 0, 639, "Exchange Server 2010", "RTM", "2009-11-09"),
+    (14, 1, 218, "Exchange Server 2010", "SP1", "2010-08-23"),
+    (14, 2, 247, "Exchange Server 2010", "SP2", "2011-12-04"),
+    (14, 3, 123, "Exchange Server 2010", "SP3", "2013-02-12"),
+    (15, 0, 516, "Exchange Server 2013", "RTM", "2012-12-03"),
+    (15, 0, 620, "Exchange Server 2013", "CU1", "2013-04-02"),
+    (15, 0, 712, "Exchange Server 2013", "CU2", "2013-07-09"),
+    (15, 0, 775, "Exchange Server 2013", "CU3", "2013-11-25"),
+    (15, 0, 847, "Exchange Server 2013", "SP1", "2014-02-25"),
+    (15, 0, 913, "Exchange Server 2013", "CU5", "2014-05-27"),
+    (15, 0, 995, "Exchange Server 2013", "CU6", "2014-08-26"),
+    (15, 0, 1044, "Exchange Server 2013", "CU7", "2014-12-09"),
+    (15, 0, 1076, "Exchange Server 2013", "CU8", "2015-03-17"),
+    (15, 0, 1104, "Exchange Server 2013", "CU9", "2015-06-17"),
+    (15, 0, 1130, "Exchange Server 2013", "CU10", "2015-09-15"),
+    (15, 0, 1156, "Exchange Server 2013", "CU11", "2015-12-10"),
+    (15, 0, 1178, "Exchange Server 2013", "CU12", "2016-03-15"),
+    (15, 0, 1210, "Exchange Server 2013", "CU13", "2016-06-21"),
+    (15, 0, 1236, "Exchange Server 2013", "CU14", "2016-09-20"),
+    (15, 0, 1263, "Exchange Server 2013", "CU15", "2016-12-13"),
+    (15, 0, 1293, "Exchange Server 2013", "CU16", "2017-03-21"),
+    (15, 0, 1320, "Exchange Server 2013", "CU17", "2017-06-27"),
+    (15, 0, 1347, "Exchange Server 2013", "CU18", "2017-09-19"),
+    (15, 0, 1365, "Exchange Server 2013", "CU19", "2017-12-19"),
+    (15, 0, 1367, "Exchange Server 2013", "CU20", "2018-03-20"),
+    (15, 0, 1395, "Exchange Server 2013", "CU21", "2018-06-19"),
+    (15, 0, 1473, "Exchange Server 2013", "CU22", "2019-02-12"),
+    (15, 0, 1497, "Exchange Server 2013", "CU23", "2019-06-18"),
+    (15, 1, 225, "Exchange Server 2016", "RTM", "2015-10-01"),
+    (15, 1, 396, "Exchange Server 2016", "CU1", "2016-03-15"),
+    (15, 1, 466, "Exchange Server 2016", "CU2", "2016-06-21"),
+    (15, 1, 544, "Exchange Server 2016", "CU3", "2016-09-20"),
+    (15, 1, 669, "Exchange Server 2016", "CU4", "2016-12-13"),
+    (15, 1, 845, "Exchange Server 2016", "CU5", "2017-03-21"),
+    (15, 1, 1034, "Exchange Server 2016", "CU6", "2017-06-27"),
+    (15, 1, 1261, "Exchange Server 2016", "CU7", "2017-09-19"),
+    (15, 1, 1415, "Exchange Server 2016", "CU8", "2017-12-19"),
+    (15, 1, 1466, "Exchange Server 2016", "CU9", "2018-03-20"),
+    (15, 1, 1531, "Exchange Server 2016", "CU10", "2018-06-19"),
+    (15, 1, 1591, "Exchange Server 2016", "CU11", "2018-10-16"),
+    (15, 1, 1713, "Exchange Server 2016", "CU12", "2019-02-12"),
+    (15, 1, 1779, "Exchange Server 2016", "CU13", "2019-06-18"),
+    (15, 1, 1847, "Exchange Server 2016", "CU14", "2019-09-17"),
+    (15, 1, 1913, "Exchange Server 2016", "CU15", "2019-12-17"),
+    (15, 1, 1979, "Exchange Server 2016", "CU16", "2020-03-17"),
+    (15, 1, 2044, "Exchange Server 2016", "CU17", "2020-06-16"),
+    (15, 1, 2106, "Exchange Server 2016", "CU18", "2020-09-15"),
+    (15, 1, 2176, "Exchange Server 2016", "CU19", "2020-12-15"),
+    (15, 1, 2242, "Exchange Server 2016", "CU20", "2021-03-16"),
+    (15, 1, 2308, "Exchange Server 2016", "CU21", "2021-06-29"),
+    (15, 1, 2375, "Exchange Server 2016", "CU22", "2021-09-28"),
+    (15, 1, 2507, "Exchange Server 2016", "CU23", "2022-04-20"),
+    (15, 2, 221, "Exchange Server 2019", "RTM", "2018-10-22"),
+    (15, 2, 330, "Exchange Server 2019", "CU1", "2019-02-12"),
+    (15, 2, 397, "Exchange Server 2019", "CU2", "2019-06-18"),
+    (15, 2, 464, "Exchange Server 2019", "CU3", "2019-09-17"),
+    (15, 2, 529, "Exchange Server 2019", "CU4", "2019-12-17"),
+    (15, 2, 595, "Exchange Server 2019", "CU5", "2020-03-17"),
+    (15, 2, 659, "Exchange Server 2019", "CU6", "2020-06-16"),
+    (15, 2, 721, "Exchange Server 2019", "CU7", "2020-09-15"),
+    (15, 2, 792, "Exchange Server 2019", "CU8", "2020-12-15"),
+    (15, 2, 858, "Exchange Server 2019", "CU9", "2021-03-16"),
+    (15, 2, 922, "Exchange Server 2019", "CU10", "2021-06-29"),
+    (15, 2, 986, "Exchange Server 2019", "CU11", "2021-09-28"),
+    (15, 2, 1118, "Exchange Server 2019", "CU12", "2022-04-20"),
+    (15, 2, 1258, "Exchange Server 2019", "CU13", "2023-05-03"),
+    (15, 2, 1544, "Exchange Server 2019", "CU14", "2024-02-13"),
+    (15, 2, 1748, "Exchange Server 2019", "CU15", "2025-02-10"),
+    (15, 2, 2562, "Exchange Server Subscription Edition", "RTM", "2025-07-01"),
+];
+
+/// Versions past their end of support (Exchange 2016 and 2019 ended on 2025-10-14).
+const UNSUPPORTED_VERSIONS: &[&str] = &[
+    "Exchange Server 2010",
+    "Exchange Server 2013",
+    "Exchange Server 2016",
+    "Exchange Server 2019",
+];
+
+/// Fingerprints the Exchange build of self-hosted Exchange front ends.
+///
+/// Candidates are on-premises MX hosts, hosts returned by Autodiscover or probed during the
+/// authentication scheme checks, and resolved Exchange-style names under the domain.
+pub async fn check_exchange_versions(
+    client: Client,
+    domain: String,
+    dns_results: Option<DnsResults>,
+    autodiscover: Option<AutodiscoverResults>,
+    auth_endpoints: Vec<AuthEndpoint>,
+) -> Result<Vec<ExchangeFingerprint>, ReconError> {
+    let mut hosts: Vec<String> = Vec::new();
+    let mut add = |host: &str| {
+        let host = host.trim_end_matches('.').to_lowercase();
+        if !host.is_empty() && !hosts.contains(&host) {
+            hosts.push(host);
+        }
+    };
+    let url_host = |url: &str| Url::parse(url).ok().and_then(|url| url.host_str().map(str::to_string));
+
+    if let Some(autodiscover) = &autodiscover {
+        for answer in autodiscover.v2.iter().filter(|answer| answer.on_premises) {
+            answer.url.as_deref().and_then(url_host).inspect(|host| add(host));
+        }
+        if autodiscover.pox_on_premises {
+            autodiscover.pox_final_url.as_deref().and_then(url_host).inspect(|host| add(host));
+        }
+    }
+    for endpoint in auth_endpoints.iter().filter(|endpoint| endpoint.on_premises && endpoint.reachable) {
+        url_host(&endpoint.endpoint).inspect(|host| add(host));
+    }
+    let mx_hosts = dns_results.as_ref().and_then(|dns| dns.mx_hosts.as_deref()).unwrap_or_default();
+    for mx in mx_hosts.iter().filter(|mx| mx.provider == MailProvider::OnPremises && !mx.secure_email_gateway) {
+        add(&mx.host);
+    }
+    for host in self_hosted_hosts(&domain, dns_results.as_ref(), EXCHANGE_HOST_LABELS) {
+        add(&host);
+    }
+    debug!(domain = domain.as_str(), hosts = hosts.len(), "Fingerprinting Exchange hosts");
+
+    let probes = hosts.into_iter().map(|host| {
+        let client = client.clone();
+        async move {
+            let (build, source) = fingerprint_exchange_host(&client, &format!("https://{}", host)).await?;
+            Some(identify_exchange_build(host, build, source))
+        }
+    });
+    let fingerprints: Vec<ExchangeFingerprint> = join_all(probes).await.into_iter().flatten().collect();
+    for fingerprint in fingerprints.iter().filter(|fingerprint| fingerprint.outdated) {
+        warn!(
+            host = fingerprint.host.as_str(),
+            build = fingerprint.build.as_str(),
+            version = fingerprint.version.as_deref().unwrap_or("unknown"),
+            "Outdated Exchange Server build"
+        );
+    }
+    info!(domain = domain.as_str(), found = fingerprints.len(), "Finished Exchange version checks");
+    Ok(fingerprints)
+}
+
+/// Requests the OWA, ECP and EWS front ends under `base_url` and returns the Exchange build
+/// with where it was read from. A full build from the `X-OWA-Version` header is preferred over
+/// the (possibly truncated) build embedded in resource paths.
+pub async fn fingerprint_exchange_host(client: &Client, base_url: &str) -> Option<(String, String)> {
+    let base_url = base_url.trim_end_matches('/');
+    let mut from_path = None;
+    for (path, prefixes) in FINGERPRINT_PATHS {
+        let url = format!("{}{}", base_url, path);
+        let response = match client.get(&url).send().await {
+            Ok(response) => response,
+            Err(e) => {
+                debug!(url = url.as_str(), error = %e, "Exchange front end request failed");
+                continue;
+            }
+        };
+        let header = response
+            .headers()
+            .get(OWA_VERSION_HEADER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(parse_exchange_build);
+        if let Some(build) = header {
+            return Some((build, format!("{} header ({})", OWA_VERSION_HEADER, path)));
+        }
+        if from_path.is_none() && !prefixes.is_empty() {
+            let body = response.text().await.unwrap_or_default();
+            from_path = build_from_resource_paths(&body, prefixes).map(|(build, prefix)| (build, format!("{} resource path", prefix)));
+        }
+    }
+    from_path
+}
+
+/// Validates an Exchange build string (major.minor.build[.revision]) and returns it trimmed.
+fn parse_exchange_build(value: &str) -> Option<String> {
+    let value = value.trim();
+    let parts: Vec<u32> = value.split('.').map(|part| part.parse().ok()).collect::<Option<_>>()?;
+    ((3..=4).contains(&parts.len()) && (8..=15).contains(&parts[0])).then(|| value.to_string())
+}
+
+/// Finds the first build embedded after one of `prefixes` in a page, e.g., `/owa/auth/15.1.2507/`.
+fn build_from_resource_paths<'a>(body: &str, prefixes: &[&'a str]) -> Option<(String, &'a str)> {
+    for prefix in prefixes {
+        for (index, _) in body.match_indices(prefix) {
+            let rest = &body[index + prefix.len()..];
+            let end = rest.find(|c: char| !c.is_ascii_digit() && c != '.').unwrap_or(rest.len());
+            if let Some(build) = parse_exchange_build(rest[..end].trim_end_matches('.')) {
+                return Some((build, prefix));
+            }
+        }
+    }
+    None
+}
+
+/// Maps a build to its Exchange release using the bundled build table.
+fn identify_exchange_build(host: String, build: String, source: String) -> ExchangeFingerprint {
+    let parts: Vec<u32> = build.split('.').filter_map(|part| part.parse().ok()).collect();
+    let line: Vec<_> = EXCHANGE_BUILDS
+        .iter()
+        .filter(|(major, minor, ..)| parts.len() >= 3 && *major == parts[0] && *minor == parts[1])
+        .collect();
+    // Latest release at or below the observed build; older builds than the table still name the version
+    let release = line.iter().rev().find(|(_, _, number, ..)| *number <= parts[2]);
+    let version = release.or(line.first()).map(|(_, _, _, version, ..)| *version);
+    let latest = version.and_then(|version| line.iter().rev().find(|(_, _, _, candidate, ..)| *candidate == version));
+
+    let outdated = match (version, release) {
+        (Some(version), _) if UNSUPPORTED_VERSIONS.contains(&version) => true,
+        (Some(_), Some(release)) => latest.is_some_and(|latest| latest.2 > release.2),
+        (Some(_), None) => true,
+        (None, _) => false,
+    };
+    ExchangeFingerprint {
+        host,
+        build,
+        source,
+        version: version.map(str::to_string),
+        cumulative_update: release.map(|(_, _, _, _, update, _)| update.to_string()),
+        release_date: release.map(|(.., date)| date.to_string()),
+        outdated,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_build_from_resource_paths() {
+        let owa = r#"<link rel="shortcut icon" href="/owa/auth/15.1.2507/themes/resources/favicon.ico" type="image/x-icon">"#;
+        assert_eq!(
+            build_from_resource_paths(owa, &["/owa/auth/", "/owa/"]),
+            Some(("15.1.2507".to_string(), "/owa/auth/"))
+        );
+        let owa_2010 = r#"<img src="/owa/14.3.123.4/themes/base/lgntopl.gif">"#;
+        assert_eq!(
+            build_from_resource_paths(owa_2010, &["/owa/auth/", "/owa/"]),
+            Some(("14.3.123.4".to_string(), "/owa/"))
+        );
+        assert_eq!(build_from_resource_paths(r#"<a href="/owa/auth/logon.aspx">"#, &["/owa/auth/", "/owa/"]), None);
+        assert_eq!(parse_exchange_build("15.2.1544.4"), Some("15.2.1544.4".to_string()));
+        assert_eq!(parse_exchange_build("1.0"), None);
+    }
+
+    #[test]
+    fn test_identify_exchange_build() {
+        let identify = |build: &str| identify_exchange_build("mail.contoso.com".to_string(), build.to_string(), String::new());
+
+        let cu14 = identify("15.2.1544.4");
+        assert_eq!(cu14.version.as_deref(), Some("Exchange Server 2019"));
+        assert_eq!(cu14.cumulative_update.as_deref(), Some("CU14"));
+        assert_eq!(cu14.release_date.as_deref(), Some("2024-02-13"));
+        assert!(cu14.outdated);
+
+        // Security updates keep the cumulative update's build number
+        let cu23_su = identify("15.1.2507.39");
+        assert_eq!(cu23_su.cumulative_update.as_deref(), Some("CU23"));
+
+        let subscription = identify("15.2.2562.17");
+        assert_eq!(subscription.version.as_deref(), Some("Exchange Server Subscription Edition"));
+        assert!(!subscription.outdated);
+
+        let unknown = identify("16.0.1.1");
+        assert_eq!(unknown.version, None);
+        assert!(!unknown.outdated);
+    }
+}
@@ -0,0 +1,315 @@
+use crate::error::ReconError;
+use crate::models::{IpEnrichment, ResolvedHost};
+use maxminddb::Reader;
+use serde_json::Value;
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+use tracing::{debug, warn};
+
+/// Microsoft's own network; addresses announced by it are not the target's servers.
+const MICROSOFT_ASN: u32 = 8075;
+
+/// Offline ASN/geolocation databases in MaxMind DB format (MaxMind GeoLite2/GeoIP2 or IPinfo).
+///
+/// Several databases can be combined, e.g. a City and an ASN database; the first
+/// database to provide a field wins.
+#[derive(Default)]
+pub struct GeoIpDatabases {
+    readers: Vec<Reader<Vec<u8>>>,
+}
+
+impl fmt::Debug for GeoIpDatabases {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let types: Vec<&str> = self.readers.iter().map(|reader| reader.metadata.database_type.as_str()).collect();
+        f.debug_struct("GeoIpDatabases").field("databases", &types).finish()
+    }
+}
+
+impl GeoIpDatabases {
+    /// Adds a database from the contents of an `.mmdb` file.
+    pub fn add_database(&mut self, bytes: Vec<u8>) -> Result<(), ReconError> {
+        let reader = Reader::from_source(bytes).map_err(|e| ReconError::Other(format!("Invalid MMDB database: {}", e)))?;
+        debug!(database_type = reader.metadata.database_type.as_str(), "Loaded MMDB database");
+        self.readers.push(reader);
+        Ok(())
+    }
+
+    /// Looks up `ip` in every database, or returns `None` if none of them knows it.
+    pub fn lookup(&self, ip: IpAddr) -> Option<IpEnrichment> {
+        let mut enrichment: Option<IpEnrichment> = None;
+        for reader in &self.readers {
+            let Ok(record) = reader.lookup::<Value>(ip) else {
+                continue;
+            };
+            let found = enrichment_from_record(ip, &record);
+            enrichment = Some(match enrichment {
+                Some(existing) => merge(existing, found),
+                None => found,
+            });
+        }
+        enrichment
+    }
+}
+
+/// Loads the `.mmdb` files given by the user.
+pub fn load_geoip_databases<P: AsRef<Path>>(paths: &[P]) -> Result<GeoIpDatabases, ReconError> {
+    let mut databases = GeoIpDatabases::default();
+    for path in paths {
+        let path = path.as_ref();
+        let bytes = std::fs::read(path)
+            .map_err(|e| ReconError::cli_error(format!("Cannot read MMDB database {}: {}", path.display(), e)))?;
+        databases
+            .add_database(bytes)
+            .map_err(|e| ReconError::cli_error(format!("{}: {}", path.display(), e)))?;
+    }
+    Ok(databases)
+}
+
+/// Checks whether an address lies outside the tenant's region scope (from the OpenID configuration).
+///
+/// Returns `None` when the scope is worldwide/unknown or the location is not known.
+pub fn outside_tenant_region(enrichment: &IpEnrichment, tenant_region_scope: &str) -> Option<bool> {
+    match tenant_region_scope.to_uppercase().as_str() {
+        "USGOV" | "USG" | "DOD" => enrichment.country_code.as_deref().map(|country| country != "US"),
+        scope @ ("NA" | "EU" | "AS" | "OC" | "SA" | "AF") => {
+            enrichment.continent_code.as_deref().map(|continent| continent != scope)
+        }
+        _ => None,
+    }
+}
+
+/// Flags self-hosted addresses (e.g., ADFS, on-premises Exchange) located outside the tenant's region.
+///
+/// Addresses attributed to a Microsoft service tag or announced by Microsoft's ASN are skipped.
+/// Returns the number of addresses flagged.
+pub fn flag_outside_tenant_region(hosts: &mut [ResolvedHost], tenant_region_scope: &str) -> usize {
+    let mut flagged = 0;
+    for host in hosts.iter_mut() {
+        let microsoft_hosted = !host.service_tag_matches.is_empty();
+        for enrichment in host.enrichment.iter_mut() {
+            if microsoft_hosted || enrichment.asn == Some(MICROSOFT_ASN) {
+                continue;
+            }
+            enrichment.outside_tenant_region = outside_tenant_region(enrichment, tenant_region_scope);
+            if enrichment.outside_tenant_region == Some(true) {
+                warn!(
+                    hostname = host.hostname.as_str(),
+                    address = enrichment.address.as_str(),
+                    country = enrichment.country_code.as_deref().unwrap_or("unknown"),
+                    tenant_region_scope,
+                    "Self-hosted server outside the tenant's region"
+                );
+                flagged += 1;
+            }
+        }
+    }
+    flagged
+}
+
+/// Extracts the fields we report from a MaxMind- or IPinfo-style record.
+fn enrichment_from_record(ip: IpAddr, record: &Value) -> IpEnrichment {
+    let text = |value: Option<&Value>| value.and_then(Value::as_str).map(str::to_string);
+    // MaxMind nests names per language; IPinfo uses flat strings
+    let name_or_string = |value: Option<&Value>| {
+        value.and_then(|value| match value {
+            Value::String(name) => Some(name.clone()),
+            Value::Object(_) => text(value.pointer("/names/en")),
+            _ => None,
+        })
+    };
+
+    let asn = record
+        .get("autonomous_system_number")
+        .and_then(Value::as_u64)
+        .or_else(|| {
+            record
+                .get("asn")
+                .and_then(Value::as_str)
+                .and_then(|asn| asn.trim_start_matches("AS").parse().ok())
+        })
+        .and_then(|asn| u32::try_from(asn).ok());
+    let as_organization = text(record.get("autonomous_system_organization"))
+        .or_else(|| text(record.get("as_name")))
+        .or_else(|| text(record.get("name")));
+    let country_code = text(record.pointer("/country/iso_code"))
+        .or_else(|| text(record.pointer("/registered_country/iso_code")))
+        .or_else(|| text(record.get("country")));
+    let country = text(record.pointer("/country/names/en")).or_else(|| text(record.get("country_name")));
+    let continent_code = text(record.pointer("/continent/code")).or_else(|| text(record.get("continent")));
+
+    IpEnrichment {
+        address: ip.to_string(),
+        asn,
+        as_organization,
+        country_code,
+        country,
+        city: name_or_string(record.get("city")),
+        continent_code,
+        outside_tenant_region: None,
+    }
+}
+
+/// Fills the fields missing from `first` with those from `second`.
+fn merge(first: IpEnrichment, second: IpEnrichment) -> IpEnrichment {
+    IpEnrichment {
+        address: first.address,
+        asn: first.asn.or(second.asn),
+        as_organization: first.as_organization.or(second.as_organization),
+        country_code: first.country_code.or(second.country_code),
+        country: first.country.or(second.country),
+        city: first.city.or(second.city),
+        continent_code: first.continent_code.or(second.continent_code),
+        outside_tenant_region: first.outside_tenant_region.or(second.outside_tenant_region),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Encodes a string (shorter than 285 bytes) in MMDB data format.
+    fn mmdb_string(value: &str) -> Vec<u8> {
+        let mut bytes = if value.len() < 29 {
+            vec![0x40 | value.len() as u8]
+        } else {
+            vec![0x40 | 29, (value.len() - 29) as u8]
+        };
+        bytes.extend_from_slice(value.as_bytes());
+        bytes
+    }
+
+    /// Encodes a map with fewer than 29 entries in MMDB data format.
+    fn mmdb_map(entries: Vec<(&str, Vec<u8>)>) -> Vec<u8> {
+        let mut bytes = vec![0xE0 | entries.len() as u8];
+        for (key, value) in entries {
+            bytes.extend(mmdb_string(key));
+            bytes.extend(value);
+        }
+        bytes
+    }
+
+    fn mmdb_uint(type_bits: u8, value: u32) -> Vec<u8> {
+        let digits: Vec<u8> = value.to_be_bytes().into_iter().skip_while(|byte| *byte == 0).collect();
+        let mut bytes = vec![(type_bits << 5) | digits.len() as u8];
+        bytes.extend(digits);
+        bytes
+    }
+
+    /// Builds an IPv4 database where 192.0.2.0/24 maps to a single GeoIP2/ASN-style record.
+    fn build_test_database() -> Vec<u8> {
+        let data = mmdb_map(vec![
+            ("autonomous_system_number", mmdb_uint(6, 64500)),
+            ("autonomous_system_organization", mmdb_string("Contoso Networks")),
+            ("city", mmdb_map(vec![("names", mmdb_map(vec![("en", mmdb_string("Frankfurt"))]))])),
+            ("continent", mmdb_map(vec![("code", mmdb_string("EU"))])),
+            (
+                "country",
+                mmdb_map(vec![
+                    ("iso_code", mmdb_string("DE")),
+                    ("names", mmdb_map(vec![("en", mmdb_string("Germany"))])),
+                ]),
+            ),
+        ]);
+
+        // One search tree node per bit of the /24 prefix, 24-bit records
+        let prefix: u32 = u32::from(std::net::Ipv4Addr::new(192, 0, 2, 0));
+        let node_count: u32 = 24;
+        let data_pointer = node_count + 16;
+        let mut bytes = Vec::new();
+        for depth in 0..node_count {
+            let bit = (prefix >> (31 - depth)) & 1;
+            let next = if depth + 1 == node_count { data_pointer } else { depth + 1 };
+            let (left, right) = if bit == 0 { (next, node_count) } else { (node_count, next) };
+            bytes.extend_from_slice(&left.to_be_bytes()[1..]);
+            bytes.extend_from_slice(&right.to_be_bytes()[1..]);
+        }
+        bytes.extend_from_slice(&[0u8; 16]);
+        bytes.extend(data);
+
+        bytes.extend_from_slice(b"\xAB\xCD\xEFMaxMind.com");
+        bytes.extend(mmdb_map(vec![
+            ("binary_format_major_version", mmdb_uint(5, 2)),
+            ("binary_format_minor_version", mmdb_uint(5, 0)),
+            ("build_epoch", vec![0x01, 0x02, 0x01]), // uint64 = 1
+            ("database_type", mmdb_string("Test-City-ASN")),
+            ("description", mmdb_map(vec![])),
+            ("ip_version", mmdb_uint(5, 4)),
+            ("languages", vec![0x00, 0x04]), // empty array
+            ("node_count", mmdb_uint(6, node_count)),
+            ("record_size", mmdb_uint(5, 24)),
+        ]));
+        bytes
+    }
+
+    #[test]
+    fn test_lookup_from_mmdb() {
+        let mut databases = GeoIpDatabases::default();
+        databases.add_database(build_test_database()).expect("test database should load");
+
+        let enrichment = databases.lookup("192.0.2.25".parse().unwrap()).expect("address should be found");
+        assert_eq!(enrichment.asn, Some(64500));
+        assert_eq!(enrichment.as_organization.as_deref(), Some("Contoso Networks"));
+        assert_eq!(enrichment.country_code.as_deref(), Some("DE"));
+        assert_eq!(enrichment.country.as_deref(), Some("Germany"));
+        assert_eq!(enrichment.city.as_deref(), Some("Frankfurt"));
+        assert_eq!(outside_tenant_region(&enrichment, "NA"), Some(true));
+        assert_eq!(outside_tenant_region(&enrichment, "EU"), Some(false));
+        assert_eq!(outside_tenant_region(&enrichment, "WW"), None);
+
+        assert!(databases.lookup("198.51.100.1".parse().unwrap()).is_none());
+    }
+
+    #[test]
+    fn test_flag_outside_tenant_region() {
+        let enrichment = |address: &str, asn: u32, continent: &str| IpEnrichment {
+            address: address.to_string(),
+            asn: Some(asn),
+            as_organization: None,
+            country_code: None,
+            country: None,
+            city: None,
+            continent_code: Some(continent.to_string()),
+            outside_tenant_region: None,
+        };
+        let mut hosts = vec![
+            ResolvedHost {
+                hostname: "adfs.contoso.com".to_string(),
+                ipv4: vec!["192.0.2.10".to_string()],
+                ipv6: Vec::new(),
+                service_tag_matches: Vec::new(),
+                enrichment: vec![enrichment("192.0.2.10", 64500, "AS")],
+            },
+            ResolvedHost {
+                hostname: "autodiscover.outlook.com".to_string(),
+                ipv4: vec!["52.97.1.1".to_string()],
+                ipv6: Vec::new(),
+                service_tag_matches: Vec::new(),
+                enrichment: vec![enrichment("52.97.1.1", MICROSOFT_ASN, "AS")],
+            },
+        ];
+
+        assert_eq!(flag_outside_tenant_region(&mut hosts, "EU"), 1);
+        assert_eq!(hosts[0].enrichment[0].outside_tenant_region, Some(true));
+        assert_eq!(hosts[1].enrichment[0].outside_tenant_region, None);
+    }
+
+    #[test]
+    fn test_enrichment_from_ipinfo_record() {
+        let record = json!({
+            "asn": "AS8075",
+            "as_name": "Microsoft Corporation",
+            "country": "NL",
+            "country_name": "Netherlands",
+            "continent": "EU",
+            "city": "Amsterdam"
+        });
+        let enrichment = enrichment_from_record("20.1.2.3".parse().unwrap(), &record);
+        assert_eq!(enrichment.asn, Some(8075));
+        assert_eq!(enrichment.as_organization.as_deref(), Some("Microsoft Corporation"));
+        assert_eq!(enrichment.country_code.as_deref(), Some("NL"));
+        assert_eq!(enrichment.city.as_deref(), Some("Amsterdam"));
+        assert_eq!(enrichment.continent_code.as_deref(), Some("EU"));
+    }
+}
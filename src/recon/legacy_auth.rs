@@ -0,0 +1,384 @@
+use crate::config::CloudConfig;
+use crate::error::ReconError;
+use crate::models::{AuthEndpoint, AuthProtocol, DnsResults, HostingType};
+use crate::recon::common::EXCHANGE_HOST_LABELS;
+use crate::recon::dns::classify_hosting;
+use crate::recon::smtp::{submission_auth_mechanisms, tls_client_config, SUBMISSION_PORT};
+use futures::future::join_all;
+use reqwest::header::WWW_AUTHENTICATE;
+use reqwest::{Client, Method};
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_rustls::rustls::pki_types::ServerName;
+use tokio_rustls::TlsConnector;
+use tracing::{debug, info};
+
+/// Implicit-TLS IMAP port.
+const IMAPS_PORT: u16 = 993;
+
+/// Implicit-TLS POP3 port.
+const POP3S_PORT: u16 = 995;
+
+/// SASL mechanisms that carry a reusable password.
+const PASSWORD_MECHANISMS: &[&str] = &["PLAIN", "LOGIN"];
+
+/// Probes Exchange Online and discovered on-premises Exchange endpoints without credentials
+/// and reports the authentication schemes each one offers.
+///
+/// The Exchange Online endpoints are shared by every tenant and answer the same way for any
+/// caller, so their challenges are informational; only on-premises endpoints say something about
+/// the target (see `basic_auth_offered`).
+///
+/// HTTP endpoints (EWS, ActiveSync, Autodiscover) are always probed. SMTP AUTH, POP and IMAP
+/// need direct mail-protocol connections and are only probed when `mail_protocols` is set.
+pub async fn check_legacy_auth(
+    client: Client,
+    domain: String,
+    config: CloudConfig,
+    dns_results: Option<DnsResults>,
+    mail_protocols: bool,
+    probe_timeout: Duration,
+) -> Result<Vec<AuthEndpoint>, ReconError> {
+    let on_prem_hosts = self_hosted_hosts(&domain, dns_results.as_ref(), EXCHANGE_HOST_LABELS);
+    debug!(domain = domain.as_str(), hosts = ?on_prem_hosts, "Probing authentication schemes");
+
+    let mut http_targets: Vec<(AuthProtocol, String, bool)> = vec![
+        (AuthProtocol::Ews, format!("https://{}/EWS/Exchange.asmx", config.ews_endpoint_host), false),
+        (
+            AuthProtocol::ActiveSync,
+            format!("https://{}/Microsoft-Server-ActiveSync", config.activesync_endpoint_host),
+            false,
+        ),
+    ];
+    if !config.autodiscover_endpoint.is_empty() {
+        http_targets.push((AuthProtocol::Autodiscover, config.autodiscover_endpoint.clone(), false));
+    }
+    for host in &on_prem_hosts {
+        http_targets.push((AuthProtocol::Ews, format!("https://{}/EWS/Exchange.asmx", host), true));
+        http_targets.push((AuthProtocol::ActiveSync, format!("https://{}/Microsoft-Server-ActiveSync", host), true));
+        http_targets.push((AuthProtocol::Autodiscover, format!("https://{}/autodiscover/autodiscover.xml", host), true));
+    }
+    let http_probes = http_targets
+        .into_iter()
+        .map(|(protocol, url, on_premises)| probe_http_auth(&client, protocol, url, on_premises));
+    let mut endpoints: Vec<AuthEndpoint> = join_all(http_probes).await;
+
+    if mail_protocols {
+        let mut mail_targets: Vec<(AuthProtocol, String, u16, bool)> = Vec::new();
+        if !config.smtp_submission_host.is_empty() {
+            mail_targets.push((AuthProtocol::SmtpAuth, config.smtp_submission_host.clone(), SUBMISSION_PORT, false));
+        }
+        mail_targets.push((AuthProtocol::Imap, config.ews_endpoint_host.clone(), IMAPS_PORT, false));
+        mail_targets.push((AuthProtocol::Pop, config.ews_endpoint_host.clone(), POP3S_PORT, false));
+        for host in &on_prem_hosts {
+            mail_targets.push((AuthProtocol::SmtpAuth, host.clone(), SUBMISSION_PORT, true));
+            mail_targets.push((AuthProtocol::Imap, host.clone(), IMAPS_PORT, true));
+            mail_targets.push((AuthProtocol::Pop, host.clone(), POP3S_PORT, true));
+        }
+        let mail_probes = mail_targets
+            .into_iter()
+            .map(|(protocol, host, port, on_premises)| probe_mail_auth(protocol, host, port, on_premises, probe_timeout));
+        endpoints.extend(join_all(mail_probes).await);
+    }
+
+    info!(
+        domain = domain.as_str(),
+        endpoints = endpoints.len(),
+        basic_auth = endpoints.iter().filter(|endpoint| endpoint.basic_auth).count(),
+        "Finished authentication scheme checks"
+    );
+    Ok(endpoints)
+}
+
+/// Whether the target's own (on-premises) endpoints of `protocol` offer Basic auth; `None` when
+/// none of them was reachable. Shared Exchange Online endpoints are not tenant-specific and
+/// do not count.
+pub fn basic_auth_offered(endpoints: &[AuthEndpoint], protocol: AuthProtocol) -> Option<bool> {
+    let own: Vec<&AuthEndpoint> = endpoints
+        .iter()
+        .filter(|endpoint| endpoint.protocol == protocol && endpoint.on_premises && endpoint.reachable)
+        .collect();
+    (!own.is_empty()).then(|| own.iter().any(|endpoint| endpoint.basic_auth))
+}
+
+/// Sends an unauthenticated request and reads the schemes from the `WWW-Authenticate` challenges.
+pub async fn probe_http_auth(client: &Client, protocol: AuthProtocol, url: String, on_premises: bool) -> AuthEndpoint {
+    // ActiveSync clients start with OPTIONS; the other endpoints answer GET with a challenge
+    let method = if protocol == AuthProtocol::ActiveSync { Method::OPTIONS } else { Method::GET };
+    let mut endpoint = AuthEndpoint {
+        protocol,
+        endpoint: url,
+        on_premises,
+        reachable: false,
+        status: None,
+        schemes: Vec::new(),
+        basic_auth: false,
+        error: None,
+    };
+
+    match client.request(method, &endpoint.endpoint).send().await {
+        Ok(response) => {
+            let challenges: Vec<&str> = response
+                .headers()
+                .get_all(WWW_AUTHENTICATE)
+                .iter()
+                .filter_map(|value| value.to_str().ok())
+                .collect();
+            endpoint.schemes = parse_auth_schemes(&challenges);
+            endpoint.basic_auth = endpoint.schemes.iter().any(|scheme| scheme == "Basic");
+            endpoint.reachable = true;
+            endpoint.status = Some(response.status().as_u16());
+            debug!(url = endpoint.endpoint.as_str(), status = %response.status(), schemes = ?endpoint.schemes, "Authentication challenge");
+        }
+        Err(e) => {
+            debug!(url = endpoint.endpoint.as_str(), error = %e, "Authentication probe failed");
+            endpoint.error = Some(e.to_string());
+        }
+    }
+    endpoint
+}
+
+/// Lists the schemes of `WWW-Authenticate` challenges, e.g.
+/// `Bearer client_id="00000002-...", trusted_issuers="..."` and `Basic realm="..."` give `Bearer, Basic`.
+fn parse_auth_schemes(challenges: &[&str]) -> Vec<String> {
+    let mut schemes: Vec<String> = Vec::new();
+    for challenge in challenges {
+        for part in split_unquoted_commas(challenge) {
+            let part = part.trim();
+            let token_end = part.find(|c: char| c.is_whitespace() || c == '=').unwrap_or(part.len());
+            let (token, rest) = part.split_at(token_end);
+            // "name=value" is a parameter of the previous challenge, not a new scheme
+            if token.is_empty() || rest.trim_start().starts_with('=') {
+                continue;
+            }
+            let scheme = match token.to_lowercase().as_str() {
+                "basic" => "Basic".to_string(),
+                "ntlm" => "NTLM".to_string(),
+                "negotiate" => "Negotiate".to_string(),
+                "bearer" => "Bearer".to_string(),
+                _ => token.to_string(),
+            };
+            if !schemes.contains(&scheme) {
+                schemes.push(scheme);
+            }
+        }
+    }
+    schemes
+}
+
+/// Splits a header value on commas outside quoted strings.
+fn split_unquoted_commas(value: &str) -> Vec<&str> {
+    let mut parts = Vec::new();
+    let mut in_quotes = false;
+    let mut start = 0;
+    for (index, c) in value.char_indices() {
+        match c {
+            '"' => in_quotes = !in_quotes,
+            ',' if !in_quotes => {
+                parts.push(&value[start..index]);
+                start = index + 1;
+            }
+            _ => {}
+        }
+    }
+    parts.push(&value[start..]);
+    parts
+}
+
+/// Reads the SASL mechanisms (and password login support) of an SMTP submission, IMAP or POP server.
+async fn probe_mail_auth(protocol: AuthProtocol, host: String, port: u16, on_premises: bool, probe_timeout: Duration) -> AuthEndpoint {
+    let mut endpoint = AuthEndpoint {
+        protocol: protocol.clone(),
+        endpoint: format!("{}:{}", host, port),
+        on_premises,
+        reachable: false,
+        status: None,
+        schemes: Vec::new(),
+        basic_auth: false,
+        error: None,
+    };
+
+    match timeout(probe_timeout, mail_capabilities(&protocol, &host, port)).await {
+        Ok(Ok((schemes, basic_auth))) => {
+            endpoint.reachable = true;
+            endpoint.schemes = schemes;
+            endpoint.basic_auth = basic_auth;
+        }
+        Ok(Err(e)) => {
+            debug!(endpoint = endpoint.endpoint.as_str(), error = %e, "Mail authentication probe failed");
+            endpoint.error = Some(e.to_string());
+        }
+        Err(_) => endpoint.error = Some("Timed out".to_string()),
+    }
+    endpoint
+}
+
+/// Returns the mechanisms offered, and whether a password can be sent, for a mail protocol.
+async fn mail_capabilities(protocol: &AuthProtocol, host: &str, port: u16) -> Result<(Vec<String>, bool), ReconError> {
+    match protocol {
+        AuthProtocol::SmtpAuth => {
+            let mechanisms = submission_auth_mechanisms(host, port).await?;
+            let basic_auth = mechanisms.iter().any(|mechanism| PASSWORD_MECHANISMS.contains(&mechanism.as_str()));
+            Ok((mechanisms, basic_auth))
+        }
+        AuthProtocol::Imap => Ok(parse_imap_capabilities(&tls_capabilities(host, port, "a1 CAPABILITY", "a1 ").await?)),
+        _ => Ok(parse_pop_capabilities(&tls_capabilities(host, port, "CAPA", ".").await?)),
+    }
+}
+
+/// Connects over implicit TLS, reads the greeting, sends `command` and returns the lines up to
+/// and including the one starting with `terminator`.
+async fn tls_capabilities(host: &str, port: u16, command: &str, terminator: &str) -> Result<Vec<String>, ReconError> {
+    let tcp = TcpStream::connect((host, port)).await?;
+    let connector = TlsConnector::from(Arc::new(tls_client_config()?));
+    let server_name = ServerName::try_from(host.to_string())
+        .map_err(|e| ReconError::check_failed("Mail Authentication", e.to_string()))?;
+    let mut stream = BufStream::new(connector.connect(server_name, tcp).await?);
+
+    let mut lines = Vec::new();
+    let mut greeting = String::new();
+    stream.read_line(&mut greeting).await?;
+    lines.push(greeting.trim_end().to_string());
+
+    stream.write_all(format!("{}\r\n", command).as_bytes()).await?;
+    stream.flush().await?;
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            return Err(ReconError::check_failed("Mail Authentication", "Connection closed by server"));
+        }
+        let line = line.trim_end().to_string();
+        let done = line.starts_with(terminator) || line.starts_with("-ERR");
+        lines.push(line);
+        if done {
+            return Ok(lines);
+        }
+    }
+}
+
+/// Extracts `AUTH=` mechanisms from IMAP capabilities; password login is possible unless `LOGINDISABLED`.
+fn parse_imap_capabilities(lines: &[String]) -> (Vec<String>, bool) {
+    let mut mechanisms: Vec<String> = Vec::new();
+    let mut login_disabled = false;
+    for capability in lines.iter().flat_map(|line| line.split_whitespace()) {
+        let capability = capability.trim_matches(|c| c == '[' || c == ']').to_uppercase();
+        if capability == "LOGINDISABLED" {
+            login_disabled = true;
+        } else if let Some(mechanism) = capability.strip_prefix("AUTH=") {
+            if !mechanisms.iter().any(|existing| existing == mechanism) {
+                mechanisms.push(mechanism.to_string());
+            }
+        }
+    }
+    let basic_auth = !login_disabled || mechanisms.iter().any(|mechanism| PASSWORD_MECHANISMS.contains(&mechanism.as_str()));
+    (mechanisms, basic_auth)
+}
+
+/// Extracts SASL mechanisms from a POP3 `CAPA` reply; `USER` means plain user/password login.
+fn parse_pop_capabilities(lines: &[String]) -> (Vec<String>, bool) {
+    let mut mechanisms: Vec<String> = Vec::new();
+    let mut user = false;
+    for line in lines {
+        let mut words = line.split_whitespace();
+        match words.next().map(str::to_uppercase).as_deref() {
+            Some("SASL") => mechanisms.extend(words.map(str::to_uppercase)),
+            Some("USER") => user = true,
+            _ => {}
+        }
+    }
+    let basic_auth = user || mechanisms.iter().any(|mechanism| PASSWORD_MECHANISMS.contains(&mechanism.as_str()));
+    (mechanisms, basic_auth)
+}
+
+/// Resolved host names under the domain whose first label is one of `labels` and that are not
+/// hosted by Microsoft, e.g., self-hosted Exchange front ends.
+pub fn self_hosted_hosts(domain: &str, dns_results: Option<&DnsResults>, labels: &[&str]) -> Vec<String> {
+    let Some(dns) = dns_results else {
+        return Vec::new();
+    };
+    let suffix = format!(".{}", domain.trim_end_matches('.').to_lowercase());
+    let mut hosts: Vec<String> = Vec::new();
+    for host in dns.resolved_hosts.as_deref().unwrap_or_default() {
+        let label = host.hostname.split('.').next().unwrap_or_default();
+        if !host.hostname.ends_with(&suffix) || !labels.contains(&label) {
+            continue;
+        }
+        // Names that alias Microsoft 365 (e.g., autodiscover -> autodiscover.outlook.com) are not on-premises
+        let microsoft_hosted = !host.service_tag_matches.is_empty()
+            || dns
+                .cname_chains
+                .as_deref()
+                .unwrap_or_default()
+                .iter()
+                .find(|chain| chain.hostname == host.hostname)
+                .and_then(|chain| chain.chain.last())
+                .is_some_and(|target| classify_hosting(target) == HostingType::MicrosoftHosted);
+        if !microsoft_hosted && !hosts.contains(&host.hostname) {
+            hosts.push(host.hostname.clone());
+        }
+    }
+    hosts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_basic_auth_offered() {
+        let endpoint = |endpoint: &str, on_premises: bool, basic_auth: bool| AuthEndpoint {
+            protocol: AuthProtocol::Ews,
+            endpoint: endpoint.to_string(),
+            on_premises,
+            reachable: true,
+            status: Some(401),
+            schemes: if basic_auth { vec!["Basic".to_string()] } else { vec!["NTLM".to_string()] },
+            basic_auth,
+            error: None,
+        };
+        let cloud = endpoint("https://outlook.office365.com/EWS/Exchange.asmx", false, true);
+        assert_eq!(basic_auth_offered(std::slice::from_ref(&cloud), AuthProtocol::Ews), None);
+        assert_eq!(basic_auth_offered(std::slice::from_ref(&cloud), AuthProtocol::ActiveSync), None);
+
+        let on_premises = endpoint("https://mail.contoso.com/EWS/Exchange.asmx", true, false);
+        assert_eq!(basic_auth_offered(&[cloud.clone(), on_premises], AuthProtocol::Ews), Some(false));
+        let on_premises_basic = endpoint("https://mail.contoso.com/EWS/Exchange.asmx", true, true);
+        assert_eq!(basic_auth_offered(&[cloud, on_premises_basic], AuthProtocol::Ews), Some(true));
+    }
+
+    #[test]
+    fn test_parse_auth_schemes() {
+        let challenges = [
+            r#"Bearer client_id="00000002-0000-0ff1-ce00-000000000000", trusted_issuers="00000001-0000-0000-c000-000000000000@*", token_types="app_asserted_user_v1 service_asserted_app_v1", authorization_uri="https://login.microsoftonline.com/common/oauth2/authorize""#,
+            r#"Basic Realm="",Negotiate"#,
+            "NTLM",
+            "NTLM TlRMTVNTUAACAAAA",
+        ];
+        assert_eq!(parse_auth_schemes(&challenges), vec!["Bearer", "Basic", "Negotiate", "NTLM"]);
+        assert!(parse_auth_schemes(&[]).is_empty());
+    }
+
+    #[test]
+    fn test_parse_mail_capabilities() {
+        let imap: Vec<String> = [
+            "* OK The Microsoft Exchange IMAP4 service is ready.",
+            "* CAPABILITY IMAP4 IMAP4rev1 AUTH=PLAIN AUTH=XOAUTH2 SASL-IR UIDPLUS",
+            "a1 OK CAPABILITY completed.",
+        ]
+        .iter()
+        .map(|line| line.to_string())
+        .collect();
+        assert_eq!(parse_imap_capabilities(&imap), (vec!["PLAIN".to_string(), "XOAUTH2".to_string()], true));
+        let oauth_only = vec!["* CAPABILITY IMAP4rev1 LOGINDISABLED AUTH=XOAUTH2".to_string()];
+        assert_eq!(parse_imap_capabilities(&oauth_only), (vec!["XOAUTH2".to_string()], false));
+
+        let pop: Vec<String> = ["+OK ready", "+OK", "TOP", "UIDL", "SASL XOAUTH2", "."]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(parse_pop_capabilities(&pop), (vec!["XOAUTH2".to_string()], false));
+    }
+}
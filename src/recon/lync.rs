@@ -0,0 +1,193 @@
+use crate::config::CloudConfig;
+use crate::error::ReconError;
+use crate::models::{DnsResults, HostingType, LyncDeployment, LyncDiscoverResults};
+use crate::recon::dns::classify_hosting;
+use reqwest::{Client, Url};
+use serde::Deserialize;
+use tracing::{debug, info};
+
+/// Lync/Skype for Business Autodiscover root, relative to the discovery host.
+const LYNCDISCOVER_PATH: &str = "/autodiscover/autodiscoverservice.svc/root";
+
+/// Redirect links followed before giving up.
+const MAX_REDIRECTS: usize = 3;
+
+/// Lyncdiscover response, e.g.
+/// `{"_links":{"self":{"href":"..."},"user":{"href":"..."},"xframe":{"href":"..."}}}`.
+#[derive(Debug, Deserialize)]
+struct LyncDiscoverResponse {
+    #[serde(rename = "_links", default)]
+    links: LyncDiscoverLinks,
+}
+
+#[derive(Debug, Default, Deserialize)]
+struct LyncDiscoverLinks {
+    user: Option<LyncDiscoverLink>,
+    xframe: Option<LyncDiscoverLink>,
+    redirect: Option<LyncDiscoverLink>,
+}
+
+#[derive(Debug, Deserialize)]
+struct LyncDiscoverLink {
+    href: String,
+}
+
+/// User and XFrame links of a lyncdiscover answer, after following redirect links.
+#[derive(Debug, Clone, PartialEq)]
+pub struct LyncDiscoverLinkSet {
+    pub url: String, // Endpoint that returned the links
+    pub user: Option<String>,
+    pub xframe: Option<String>,
+}
+
+/// Fetches the domain's lyncdiscover JSON and classifies the Skype for Business / Teams deployment.
+///
+/// `lyncdiscover.<domain>` is queried when it resolves; the online discovery host is asked
+/// about the domain as well, since a CNAME to Microsoft usually fails certificate validation.
+/// Returns `None` when no endpoint answered with links.
+pub async fn check_lync_discover(
+    client: Client,
+    domain: String,
+    config: CloudConfig,
+    dns_results: Option<DnsResults>,
+) -> Result<Option<LyncDiscoverResults>, ReconError> {
+    let record_present = dns_results
+        .as_ref()
+        .and_then(|dns| dns.lyncdiscover_present)
+        .unwrap_or(false);
+
+    let domain_answer = if record_present {
+        fetch_lync_discover(&client, &format!("https://lyncdiscover.{}{}", domain, LYNCDISCOVER_PATH)).await
+    } else {
+        None
+    };
+    let online_answer = if config.lyncdiscover_host.is_empty() {
+        None
+    } else {
+        let mut url = Url::parse(&format!("https://{}{}", config.lyncdiscover_host, LYNCDISCOVER_PATH))
+            .map_err(|e| ReconError::check_failed("Lyncdiscover", e.to_string()))?;
+        url.query_pairs_mut().append_pair("originalDomain", &domain);
+        fetch_lync_discover(&client, url.as_str()).await
+    };
+
+    let results = classify_lync_deployment(domain_answer, online_answer, &config.lyncdiscover_host, record_present);
+    info!(
+        domain = domain.as_str(),
+        deployment = ?results.as_ref().map(|results| &results.deployment),
+        "Finished lyncdiscover checks"
+    );
+    Ok(results)
+}
+
+/// Requests a lyncdiscover endpoint and follows its redirect links.
+pub async fn fetch_lync_discover(client: &Client, url: &str) -> Option<LyncDiscoverLinkSet> {
+    let mut url = url.to_string();
+    for _ in 0..=MAX_REDIRECTS {
+        debug!(url = url.as_str(), "Querying lyncdiscover");
+        let response = match client.get(&url).header("Accept", "application/json").send().await {
+            Ok(response) => response,
+            Err(e) => {
+                debug!(url = url.as_str(), error = %e, "Lyncdiscover request failed");
+                return None;
+            }
+        };
+        let links = match response.json::<LyncDiscoverResponse>().await {
+            Ok(body) => body.links,
+            Err(e) => {
+                debug!(url = url.as_str(), error = %e, "Unexpected lyncdiscover response");
+                return None;
+            }
+        };
+        match links {
+            LyncDiscoverLinks { user: None, xframe: None, redirect: Some(redirect) } => url = redirect.href,
+            LyncDiscoverLinks { user: None, xframe: None, .. } => return None,
+            LyncDiscoverLinks { user, xframe, .. } => {
+                return Some(LyncDiscoverLinkSet {
+                    url,
+                    user: user.map(|link| link.href),
+                    xframe: xframe.map(|link| link.href),
+                })
+            }
+        }
+    }
+    None
+}
+
+/// Classifies the deployment from the answers of `lyncdiscover.<domain>` and the online discovery host.
+///
+/// The online host answers every query, but only points domains it knows at a regional pool
+/// (e.g., webdir2a.online.lync.com), so links back to the generic host are not evidence.
+fn classify_lync_deployment(
+    domain_answer: Option<LyncDiscoverLinkSet>,
+    online_answer: Option<LyncDiscoverLinkSet>,
+    online_host: &str,
+    record_present: bool,
+) -> Option<LyncDiscoverResults> {
+    let link_host = |links: &LyncDiscoverLinkSet| {
+        links
+            .user
+            .as_deref()
+            .or(links.xframe.as_deref())
+            .and_then(|href| Url::parse(href).ok())
+            .and_then(|url| url.host_str().map(str::to_lowercase))
+    };
+    let results = |links: LyncDiscoverLinkSet, deployment: LyncDeployment, pool_fqdn: Option<String>| LyncDiscoverResults {
+        url: links.url,
+        deployment,
+        user_link: links.user,
+        xframe_link: links.xframe,
+        pool_fqdn,
+    };
+
+    if let Some(links) = domain_answer {
+        if let Some(host) = link_host(&links) {
+            return Some(match classify_hosting(&host) {
+                HostingType::OnPremises => results(links, LyncDeployment::OnPremises, Some(host)),
+                HostingType::MicrosoftHosted => results(links, LyncDeployment::SkypeOnline, None),
+            });
+        }
+    }
+    let links = online_answer?;
+    let host = link_host(&links)?;
+    if host.eq_ignore_ascii_case(online_host) || classify_hosting(&host) != HostingType::MicrosoftHosted {
+        return None;
+    }
+    let deployment = if record_present { LyncDeployment::SkypeOnline } else { LyncDeployment::TeamsOnly };
+    Some(results(links, deployment, None))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn links(user: &str) -> LyncDiscoverLinkSet {
+        LyncDiscoverLinkSet {
+            url: "https://lyncdiscover.contoso.com/autodiscover/autodiscoverservice.svc/root".to_string(),
+            user: Some(user.to_string()),
+            xframe: None,
+        }
+    }
+
+    #[test]
+    fn test_classify_lync_deployment() {
+        let online_host = "webdir.online.lync.com";
+        let on_premises = classify_lync_deployment(
+            Some(links("https://lyncweb.contoso.com/Autodiscover/AutodiscoverService.svc/root/oauth/user")),
+            None,
+            online_host,
+            true,
+        )
+        .unwrap();
+        assert_eq!(on_premises.deployment, LyncDeployment::OnPremises);
+        assert_eq!(on_premises.pool_fqdn.as_deref(), Some("lyncweb.contoso.com"));
+
+        let regional = links("https://webdir2a.online.lync.com/Autodiscover/AutodiscoverService.svc/root/oauth/user");
+        let online = classify_lync_deployment(None, Some(regional.clone()), online_host, true).unwrap();
+        assert_eq!(online.deployment, LyncDeployment::SkypeOnline);
+        let teams_only = classify_lync_deployment(None, Some(regional), online_host, false).unwrap();
+        assert_eq!(teams_only.deployment, LyncDeployment::TeamsOnly);
+
+        let generic = links("https://webdir.online.lync.com/Autodiscover/AutodiscoverService.svc/root/oauth/user");
+        assert_eq!(classify_lync_deployment(None, Some(generic), online_host, false), None);
+    }
+}
@@ -1,7 +1,17 @@
 use crate::config::CloudConfig;
 use crate::error::ReconError;
-use crate::models::{DnsResults, M365Results};
-use reqwest::Client;
+use crate::models::{AuthProtocol, DnsResults, LyncDeployment, M365Results, MailRouting, SharePointResults, SharePointSite, SharePointSiteKind, TenantInfo};
+use crate::recon::autodiscover::{check_autodiscover_endpoints, exchange_topology};
+use crate::recon::common::tenant_label_candidates;
+use crate::recon::dynamics::check_dynamics;
+use crate::recon::legacy_auth::{basic_auth_offered, check_legacy_auth};
+use crate::recon::lync::check_lync_discover;
+use crate::recon::mx::determine_mail_routing;
+use crate::recon::power_apps::check_power_apps;
+use crate::recon::ReconOptions;
+use futures::future::join_all;
+use reqwest::header::HeaderMap;
+use reqwest::{Client, StatusCode, Url};
 use tracing::{debug, info, warn};
 
 /// Performs M365 service checks.
@@ -11,15 +21,29 @@ pub async fn run_m365_checks(
     domain: String, // Pass owned domain
     cloud_config: CloudConfig, // Pass cloned config
     dns_results: Option<DnsResults>, // Pass owned/cloned Option<DnsResults>
+    tenant_info: Option<TenantInfo>, // onmicrosoft name and tenant GUID, if already discovered
+    name_space_type: Option<String>, // getuserrealm NameSpaceType (Managed, Federated, Unknown)
+    options: ReconOptions,
 ) -> Result<M365Results, ReconError> {
     info!(target = domain, "Running M365 service checks...");
+    let tenant_name = tenant_info.as_ref().and_then(|tenant| tenant.tenant_name.clone());
+    let tenant_id = tenant_info.and_then(|tenant| tenant.tenant_id);
+
+    // Mail routing comes from the classified MX hosts rather than assuming Exchange Online
+    let (mail_routing, mail_gateway) = match dns_results.as_ref().map(determine_mail_routing) {
+        Some((routing, gateway)) => (Some(routing), gateway),
+        None => (None, None),
+    };
+    let m365_evidence = matches!(name_space_type.as_deref(), Some("Managed" | "Federated"))
+        || matches!(mail_routing, Some(MailRouting::ExchangeOnlineDirect | MailRouting::GatewayToExchangeOnline));
 
     // Spawn tasks for independent checks
     let client_clone1 = client.clone();
     let domain_clone1 = domain.clone();
     let config_clone1 = cloud_config.clone();
+    let tenant_name_clone1 = tenant_name.clone();
     let sharepoint_handle = tokio::spawn(async move {
-        check_sharepoint(client_clone1, domain_clone1, config_clone1).await
+        check_sharepoint(client_clone1, domain_clone1, tenant_name_clone1, config_clone1).await
     });
     
     let client_clone2 = client.clone();
@@ -32,20 +56,49 @@ pub async fn run_m365_checks(
     let client_clone3 = client.clone();
     let domain_clone3 = domain.clone();
     let config_clone3 = cloud_config.clone();
-    let ews_handle = tokio::spawn(async move {
-        check_legacy_auth_ews(client_clone3, domain_clone3, config_clone3).await
-    });
-    
-    let client_clone4 = client.clone();
-    let domain_clone4 = domain.clone();
-    let config_clone4 = cloud_config.clone();
-    let activesync_handle = tokio::spawn(async move {
-        check_legacy_auth_activesync(client_clone4, domain_clone4, config_clone4).await
-    });
-    // Add handles for power apps etc. later
+    let dns_clone3 = dns_results.clone();
+    // SMTP AUTH, POP and IMAP connections follow the --smtp opt-in
+    let legacy_auth_handle = tokio::spawn(check_legacy_auth(
+        client_clone3,
+        domain_clone3,
+        config_clone3,
+        dns_clone3,
+        options.smtp_enabled,
+        options.probe_timeout,
+    ));
+
+    let autodiscover_handle = tokio::spawn(check_autodiscover_endpoints(
+        client.clone(),
+        domain.clone(),
+        cloud_config.clone(),
+        dns_results.clone(),
+        m365_evidence,
+    ));
+
+    let lync_handle = tokio::spawn(check_lync_discover(
+        client.clone(),
+        domain.clone(),
+        cloud_config.clone(),
+        dns_results.clone(),
+    ));
+
+    let power_apps_handle = tokio::spawn(check_power_apps(
+        client.clone(),
+        domain.clone(),
+        tenant_name.clone(),
+        cloud_config.clone(),
+    ));
+
+    let dynamics_handle = tokio::spawn(check_dynamics(
+        client.clone(),
+        domain.clone(),
+        tenant_name,
+        tenant_id,
+        cloud_config.clone(),
+    ));
 
     // Await results
-    let sharepoint_detected = sharepoint_handle.await.unwrap_or_else(|e| {
+    let sharepoint = sharepoint_handle.await.unwrap_or_else(|e| {
         warn!(domain = domain.as_str(), error = %e, "SharePoint check task failed");
         Err(ReconError::check_failed("SharePoint Check", e.to_string()))
     })?;
@@ -53,23 +106,44 @@ pub async fn run_m365_checks(
         warn!(domain = domain.as_str(), error = %e, "Tenant Branding check task failed");
         Err(ReconError::check_failed("Tenant Branding Check", e.to_string()))
     })?;
-    let ews_enabled = ews_handle.await.unwrap_or_else(|e| {
-        warn!(domain = domain.as_str(), error = %e, "Legacy Auth (EWS) check task failed");
-        Err(ReconError::check_failed("Legacy Auth (EWS) Check", e.to_string()))
+    let auth_endpoints = legacy_auth_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "Legacy Auth check task failed");
+        Err(ReconError::check_failed("Legacy Auth Check", e.to_string()))
     })?;
-    let activesync_enabled = activesync_handle.await.unwrap_or_else(|e| {
-        warn!(domain = domain.as_str(), error = %e, "Legacy Auth (ActiveSync) check task failed");
-        Err(ReconError::check_failed("Legacy Auth (ActiveSync) Check", e.to_string()))
+    let autodiscover = autodiscover_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "Autodiscover check task failed");
+        Err(ReconError::check_failed("Autodiscover Check", e.to_string()))
+    })?;
+    let lync_discover = lync_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "Lyncdiscover check task failed");
+        Err(ReconError::check_failed("Lyncdiscover Check", e.to_string()))
+    })?;
+    let power_pages = power_apps_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "Power Apps check task failed");
+        Err(ReconError::check_failed("Power Apps Check", e.to_string()))
+    })?;
+    let dynamics = dynamics_handle.await.unwrap_or_else(|e| {
+        warn!(domain = domain.as_str(), error = %e, "Dynamics 365 check task failed");
+        Err(ReconError::check_failed("Dynamics 365 Check", e.to_string()))
     })?;
 
     // Combine results
     let results = M365Results {
-        sharepoint_detected: Some(sharepoint_detected),
-        // Determine Teams presence based on DNS results (already gathered)
-        teams_detected: Some(check_teams_dns(dns_results.as_ref())),
+        sharepoint_detected: Some(sharepoint.tenant_name.is_some()),
+        sharepoint: Some(sharepoint),
+        // Teams presence comes from an online deployment behind lyncdiscover rather than DNS alone
+        teams_detected: Some(lync_discover.as_ref().is_some_and(|lync| lync.deployment != LyncDeployment::OnPremises)),
+        lync_discover,
         tenant_branding_accessible: Some(branding_accessible),
-        legacy_auth_ews_enabled: Some(ews_enabled),
-        legacy_auth_activesync_enabled: Some(activesync_enabled),
+        legacy_auth_ews_enabled: basic_auth_offered(&auth_endpoints, AuthProtocol::Ews),
+        legacy_auth_activesync_enabled: basic_auth_offered(&auth_endpoints, AuthProtocol::ActiveSync),
+        auth_endpoints,
+        exchange_topology: exchange_topology(&autodiscover),
+        autodiscover: Some(autodiscover),
+        mail_routing,
+        mail_gateway,
+        power_pages: Some(power_pages),
+        dynamics: Some(dynamics),
     };
 
     info!(target = domain.as_str(), "Finished M365 service checks");
@@ -83,31 +157,109 @@ fn get_tenant_name(domain: &str) -> String {
     domain.split('.').next().unwrap_or(domain).to_string()
 }
 
-/// Check if SharePoint Online is accessible for the domain
-async fn check_sharepoint(
-    client: Client,
-    domain: String,
-    config: CloudConfig,
-) -> Result<bool, ReconError> {
-    let tenant_name = get_tenant_name(&domain);
-    let url = format!("https://{}{}", tenant_name, config.sharepoint_host_suffix);
-    debug!(domain = domain.as_str(), url = url.as_str(), "Checking SharePoint Online");
-    
+/// Candidate SharePoint tenant names, most likely first.
+///
+/// A discovered onmicrosoft name is authoritative; otherwise the names are guessed from the domain.
+fn sharepoint_tenant_candidates(tenant_name: Option<&str>, domain: &str) -> Vec<String> {
+    let mut candidates = tenant_label_candidates(tenant_name, domain);
+    if tenant_name.and_then(|name| name.split('.').next()).is_some_and(|label| !label.is_empty()) {
+        candidates.truncate(1);
+    }
+    candidates
+}
+
+/// Decides from the final response whether a SharePoint site exists.
+///
+/// Existing sites send anonymous visitors to the sign-in page, or answer on their own host
+/// with SharePoint headers; unknown tenants answer 404 or are sent elsewhere.
+fn sharepoint_site_exists(status: StatusCode, final_url: &Url, headers: &HeaderMap, site_host: &str, login_host: &str) -> bool {
+    match final_url.host_str() {
+        Some(host) if host.eq_ignore_ascii_case(login_host) => true,
+        Some(host) if host.eq_ignore_ascii_case(site_host) => {
+            status != StatusCode::NOT_FOUND
+                && (headers.contains_key("microsoftsharepointteamservices") || headers.contains_key("sprequestguid"))
+        }
+        _ => false,
+    }
+}
+
+/// Requests a SharePoint host and classifies the response.
+async fn probe_sharepoint_site(client: &Client, kind: SharePointSiteKind, host: String, login_host: &str) -> SharePointSite {
+    let url = format!("https://{}/", host);
+    debug!(url = url.as_str(), "Checking SharePoint Online site");
     match client.get(&url).send().await {
         Ok(response) => {
             let status = response.status();
-            debug!(domain = domain.as_str(), status = %status, "SharePoint response");
-            // SharePoint returns 200 or 302 if tenant exists, even if not authenticated
-            Ok(status.is_success() || status.is_redirection())
+            let final_url = response.url().clone();
+            let exists = sharepoint_site_exists(status, &final_url, response.headers(), &host, login_host);
+            debug!(url = url.as_str(), status = %status, final_url = %final_url, exists, "SharePoint response");
+            SharePointSite {
+                kind,
+                url,
+                exists,
+                status: Some(status.as_u16()),
+                redirect: (final_url.host_str() != Some(host.as_str())).then(|| final_url.to_string()),
+            }
         }
         Err(e) => {
             // Connection error could be firewall block or no such tenant
-            debug!(domain = domain.as_str(), error = %e, "SharePoint request failed");
-            Ok(false) // Consider this as "not detected" rather than error
+            debug!(url = url.as_str(), error = %e, "SharePoint request failed");
+            SharePointSite {
+                kind,
+                url,
+                exists: false,
+                status: None,
+                redirect: None,
+            }
         }
     }
 }
 
+/// Finds the tenant's SharePoint Online root site, then probes its OneDrive (`-my`) and admin (`-admin`) hosts.
+async fn check_sharepoint(
+    client: Client,
+    domain: String,
+    tenant_name: Option<String>,
+    config: CloudConfig,
+) -> Result<SharePointResults, ReconError> {
+    let candidates = sharepoint_tenant_candidates(tenant_name.as_deref(), &domain);
+    let mut results = SharePointResults {
+        tenant_name_discovered: tenant_name.is_some(),
+        ..Default::default()
+    };
+    let login_host = config.login_microsoftonline_host.as_str();
+
+    for candidate in candidates {
+        results.candidates_tried.push(candidate.clone());
+        let root = probe_sharepoint_site(
+            &client,
+            SharePointSiteKind::Root,
+            format!("{}{}", candidate, config.sharepoint_host_suffix),
+            login_host,
+        )
+        .await;
+        if !root.exists {
+            continue;
+        }
+
+        let variants = [(SharePointSiteKind::OneDrive, "-my"), (SharePointSiteKind::Admin, "-admin")].map(|(kind, suffix)| {
+            let host = format!("{}{}{}", candidate, suffix, config.sharepoint_host_suffix);
+            probe_sharepoint_site(&client, kind, host, login_host)
+        });
+        results.sites.push(root);
+        results.sites.extend(join_all(variants).await);
+        results.tenant_name = Some(candidate);
+        break;
+    }
+
+    info!(
+        domain = domain.as_str(),
+        tenant_name = results.tenant_name.as_deref().unwrap_or("not found"),
+        "Finished SharePoint Online checks"
+    );
+    Ok(results)
+}
+
 /// Check if tenant branding is accessible
 async fn check_tenant_branding(
     client: Client, 
@@ -134,67 +286,41 @@ async fn check_tenant_branding(
     }
 }
 
-/// Check if legacy auth (EWS) is allowed
-async fn check_legacy_auth_ews(
-    client: Client,
-    domain: String,
-    config: CloudConfig,
-) -> Result<bool, ReconError> {
-    // This is a simplified check - real validation would need actual EWS API calls
-    let url = format!("https://{}{}/EWS/Exchange.asmx", 
-                      domain, 
-                      config.ews_endpoint_host);
-    
-    debug!(domain = domain.as_str(), url = url.as_str(), "Checking legacy auth (EWS)");
-    
-    match client.get(&url).send().await {
-        Ok(response) => {
-            let status = response.status();
-            debug!(domain = domain.as_str(), status = %status, "EWS response");
-            // 401 Unauthorized is expected for EWS endpoint if it exists
-            // 200 OK would be unusual but indicates it exists
-            Ok(status.is_client_error() || status.is_success())
-        }
-        Err(e) => {
-            debug!(domain = domain.as_str(), error = %e, "EWS request failed");
-            Ok(false) // Consider this as "not enabled" rather than error
-        }
-    }
-}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use reqwest::header::HeaderValue;
 
-/// Check if legacy auth (ActiveSync) is allowed
-async fn check_legacy_auth_activesync(
-    client: Client,
-    domain: String,
-    config: CloudConfig,
-) -> Result<bool, ReconError> {
-    let url = format!("https://{}{}/Microsoft-Server-ActiveSync", 
-                     domain, 
-                     config.activesync_endpoint_host);
-                     
-    debug!(domain = domain.as_str(), url = url.as_str(), "Checking legacy auth (ActiveSync)");
-    
-    match client.get(&url).send().await {
-        Ok(response) => {
-            let status = response.status();
-            debug!(domain = domain.as_str(), status = %status, "ActiveSync response");
-            // 401 Unauthorized is expected for ActiveSync endpoint if it exists
-            // 403 Forbidden might indicate it exists but is blocked
-            // 200 OK would be unusual but indicates it exists
-            Ok(status.is_client_error() || status.is_success())
-        }
-        Err(e) => {
-            debug!(domain = domain.as_str(), error = %e, "ActiveSync request failed");
-            Ok(false) // Consider this as "not enabled" rather than error
-        }
+    #[test]
+    fn test_sharepoint_tenant_candidates() {
+        assert_eq!(
+            sharepoint_tenant_candidates(Some("contosoltd.onmicrosoft.com"), "contoso-corp.co.uk"),
+            vec!["contosoltd"]
+        );
+        assert_eq!(
+            sharepoint_tenant_candidates(None, "mail.contoso-corp.co.uk"),
+            vec!["contosocorp", "contoso-corp"]
+        );
+        assert_eq!(sharepoint_tenant_candidates(None, "contoso.com"), vec!["contoso"]);
     }
-}
 
-/// Checks for Teams presence using DNS records.
-fn check_teams_dns(dns_results: Option<&DnsResults>) -> bool {
-    dns_results.as_ref().map_or(false, |dns| {
-        dns.lyncdiscover_present.unwrap_or(false) || dns.sip_cname_or_a_present.unwrap_or(false)
-    })
-}
+    #[test]
+    fn test_sharepoint_site_exists() {
+        let site_host = "contoso.sharepoint.com";
+        let login_host = "login.microsoftonline.com";
+        let mut sharepoint_headers = HeaderMap::new();
+        sharepoint_headers.insert("sprequestguid", HeaderValue::from_static("a1b2c3"));
+
+        // Anonymous visitors to an existing site are sent to the sign-in page
+        let sign_in = Url::parse("https://login.microsoftonline.com/contoso.onmicrosoft.com/oauth2/authorize?client_id=1").unwrap();
+        assert!(sharepoint_site_exists(StatusCode::OK, &sign_in, &HeaderMap::new(), site_host, login_host));
 
-// TODO: Implement check_power_apps
\ No newline at end of file
+        let site = Url::parse("https://contoso.sharepoint.com/_layouts/15/Authenticate.aspx").unwrap();
+        assert!(sharepoint_site_exists(StatusCode::FORBIDDEN, &site, &sharepoint_headers, site_host, login_host));
+        // "Tenant not found" answers on the SharePoint host, or responses without SharePoint headers
+        assert!(!sharepoint_site_exists(StatusCode::NOT_FOUND, &site, &sharepoint_headers, site_host, login_host));
+        assert!(!sharepoint_site_exists(StatusCode::OK, &site, &HeaderMap::new(), site_host, login_host));
+        let elsewhere = Url::parse("https://www.microsoft.com/microsoft-365/sharepoint").unwrap();
+        assert!(!sharepoint_site_exists(StatusCode::OK, &elsewhere, &sharepoint_headers, site_host, login_host));
+    }
+}
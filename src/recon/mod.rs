@@ -1,27 +1,140 @@
 // This file declares the submodules within the `recon` module and the main orchestrator.
 
 pub mod aad;
+pub mod autodiscover;
 pub mod azure_svc;
 pub mod client;
+pub mod common;
+pub mod ct;
+pub mod dkim;
+pub mod dmarc;
 pub mod dns;
+pub mod dnssec;
+pub mod dynamics;
+pub mod email_security;
+pub mod exchange;
+pub mod geoip;
+pub mod legacy_auth;
+pub mod lync;
 pub mod m365;
 pub mod mdi;
+pub mod mx;
+pub mod ntlm;
+pub mod nameservers;
+pub mod power_apps;
+pub mod provisioning;
+pub mod saml;
+pub mod service_tags;
+pub mod smtp;
+pub mod spf;
+pub mod subdomains;
+pub mod takeover;
 pub mod tenant;
 
 use reqwest::Client;
+use std::sync::Arc;
+use std::time::Duration;
 use tracing::{error, info, warn}; // Import tracing macros
+use trust_dns_resolver::config::{ResolverConfig, ResolverOpts};
+use trust_dns_resolver::TokioAsyncResolver;
 
-use crate::config::CloudConfig;
+use crate::cli::Cli;
+use crate::config::{AppConfig, CloudConfig, DEFAULT_CT_LOG_URL, DEFAULT_DKIM_SELECTORS, DEFAULT_SUBDOMAIN_QPS};
 use crate::error::ReconError;
-use crate::models::ReconResults;
+use crate::models::{ReconResults, TenantInfo};
 
 // Import check functions from submodules
-use aad::{check_aad_connect_status, get_azure_ad_config};
+use aad::{check_aad_connect_status, get_azure_ad_config, tenant_id_from_config};
 use azure_svc::run_azure_service_checks;
+use common::QueryLimiter;
+use client::new_no_redirect_client;
 use dns::run_dns_checks;
+use exchange::check_exchange_versions;
+use geoip::{flag_outside_tenant_region, load_geoip_databases, GeoIpDatabases};
 use m365::run_m365_checks;
+use ntlm::check_ntlm_disclosure;
+use provisioning::check_provisioning_endpoints;
+use saml::check_saml_endpoints;
+use service_tags::{load_service_tags, resolve_hosts, ServiceTags};
+use smtp::{probe_smtp_hosts, SMTP_PORT};
+use subdomains::{bundled_wordlist, parse_wordlist};
 use tenant::get_federation_info;
 
+/// Runtime options shared by the reconnaissance modules.
+///
+/// Combines settings that are not specific to a cloud environment.
+#[derive(Debug, Clone, Default)]
+pub struct ReconOptions {
+    pub dkim_selectors: Vec<String>,
+    pub smtp_enabled: bool,
+    pub probe_timeout: Duration, // Direct connections to the target's servers (SMTP, authoritative DNS)
+    pub subdomain_words: Vec<String>, // Bundled plus user-supplied words; empty when enumeration is off
+    pub subdomain_qps: u32,
+    pub ct_log_url: Option<String>, // Set only when CT logs should be queried
+    pub axfr_enabled: bool,
+    pub service_tags: Arc<ServiceTags>, // Empty unless service tag files were given
+    pub geoip: Arc<GeoIpDatabases>, // Empty unless MMDB files were given
+    pub no_redirect_client: Client, // For fetches that must not follow redirects (MTA-STS policy)
+}
+
+/// Builds the runtime options for the reconnaissance modules.
+///
+/// SMTP probing requires `--smtp` and is refused when `no_smtp` is set in the configuration.
+/// Fails if a user-supplied subdomain wordlist, service tags file or MMDB database cannot be read.
+pub fn build_recon_options(app_config: &AppConfig, cli: &Cli) -> Result<ReconOptions, ReconError> {
+    let dkim_selectors = app_config
+        .dkim_selectors
+        .clone()
+        .unwrap_or_else(|| DEFAULT_DKIM_SELECTORS.iter().map(|s| s.to_string()).collect());
+
+    let smtp_enabled = cli.smtp && !app_config.no_smtp.unwrap_or(false);
+    let probe_timeout = Duration::from_secs(app_config.request_timeout_seconds.unwrap_or(30));
+
+    let mut subdomain_words = Vec::new();
+    if cli.subdomains || !cli.wordlist.is_empty() {
+        subdomain_words.extend(bundled_wordlist());
+    }
+    for path in &cli.wordlist {
+        let contents = std::fs::read_to_string(path)
+            .map_err(|e| ReconError::cli_error(format!("Cannot read wordlist {}: {}", path.display(), e)))?;
+        for word in parse_wordlist(&contents) {
+            if !subdomain_words.contains(&word) {
+                subdomain_words.push(word);
+            }
+        }
+    }
+    // Files given on the command line replace those in the configuration
+    let service_tags = if cli.service_tags.is_empty() {
+        load_service_tags(app_config.service_tags_files.as_deref().unwrap_or_default())?
+    } else {
+        load_service_tags(&cli.service_tags)?
+    };
+    let geoip = if cli.mmdb.is_empty() {
+        load_geoip_databases(app_config.mmdb_files.as_deref().unwrap_or_default())?
+    } else {
+        load_geoip_databases(&cli.mmdb)?
+    };
+    let ct_log_url = cli.ct.then(|| {
+        app_config
+            .ct_log_url
+            .clone()
+            .unwrap_or_else(|| DEFAULT_CT_LOG_URL.to_string())
+    });
+
+    Ok(ReconOptions {
+        dkim_selectors,
+        smtp_enabled,
+        probe_timeout,
+        subdomain_words,
+        subdomain_qps: app_config.subdomain_qps.unwrap_or(DEFAULT_SUBDOMAIN_QPS),
+        ct_log_url,
+        axfr_enabled: cli.axfr,
+        service_tags: Arc::new(service_tags),
+        geoip: Arc::new(geoip),
+        no_redirect_client: new_no_redirect_client(app_config)?,
+    })
+}
+
 /// Orchestrates all reconnaissance checks.
 ///
 /// Runs checks sequentially or concurrently where appropriate,
@@ -31,12 +144,13 @@ pub async fn run_all_checks(
     client: Client,
     domain: String, // Accept owned String
     cloud_config: CloudConfig,
+    options: ReconOptions,
 ) -> Result<ReconResults, ReconError> {
     info!(target = domain.as_str(), "Starting all reconnaissance checks...");
     let mut results = ReconResults::new(domain.clone()); // Initialize results with cloned domain
 
     // --- DNS Checks (Run first, as some later checks might depend on it) ---
-    let dns_results_result = run_dns_checks(&domain).await;
+    let dns_results_result = run_dns_checks(&client, &domain, &cloud_config, &options).await;
     match dns_results_result {
         Ok(dns_res) => {
             info!(target = domain.as_str(), "DNS checks completed successfully.");
@@ -48,6 +162,26 @@ pub async fn run_all_checks(
             results.dns_results = None;
         }
     };
+    // The M365 DKIM selector CNAME reveals the tenant's onmicrosoft name
+    if let Some(tenant_name) = results
+        .dns_results
+        .as_ref()
+        .and_then(|dns| dns.dkim.as_ref())
+        .and_then(|dkim| dkim.m365_tenant_name.clone())
+    {
+        info!(target = domain.as_str(), tenant_name = tenant_name.as_str(), "Tenant name found via DKIM");
+        results.tenant_info = Some(TenantInfo {
+            domain: domain.clone(),
+            tenant_name: Some(tenant_name),
+            ..Default::default()
+        });
+    }
+
+    // --- SMTP Probe (opt-in; connects to the target's mail servers) ---
+    if options.smtp_enabled {
+        run_smtp_probe(&mut results, &options).await;
+    }
+
     // Clone DNS results *after* handling the Result, if needed by subsequent tasks
     let dns_results_clone = results.dns_results.clone();
 
@@ -83,24 +217,52 @@ pub async fn run_all_checks(
         Ok(Ok(aad_config)) => Some(aad_config),
         _ => None,
     };
-    
+    // The tenant's OpenID issuer carries its tenant ID (used to confirm tenant-named resources)
+    if let Some(tenant_id) = results.azure_ad_config.as_ref().and_then(tenant_id_from_config) {
+        info!(target = domain.as_str(), tenant_id = tenant_id.as_str(), "Tenant ID found via OpenID configuration");
+        results
+            .tenant_info
+            .get_or_insert_with(|| TenantInfo {
+                domain: domain.clone(),
+                ..Default::default()
+            })
+            .tenant_id = Some(tenant_id);
+    }
+
     results.aad_connect_status = match aad_connect_status_result {
         Ok(Ok(status)) => Some(status),
         _ => None,
     };
 
+    // --- Federation host resolution and region checks (needs DNS and tenant results) ---
+    run_region_checks(&mut results, &options).await;
+
     // --- Service Checks (Can run concurrently, may depend on DNS/Tenant) ---
     // Pass DNS results if needed
     let client_clone4 = client.clone();
     let domain_clone4 = domain.to_string();
     let config_clone4 = cloud_config.clone();
     // Pass the cloned Option<DnsResults> from before
-    let m365_handle = tokio::spawn(run_m365_checks(client_clone4, domain_clone4, config_clone4, dns_results_clone));
+    let name_space_type = results.federation_info.as_ref().and_then(|federation| federation.name_space_type.clone());
+    let m365_handle = tokio::spawn(run_m365_checks(
+        client_clone4,
+        domain_clone4,
+        config_clone4,
+        dns_results_clone,
+        results.tenant_info.clone(),
+        name_space_type,
+        options.clone(),
+    ));
 
     let client_clone5 = client.clone();
     let domain_clone5 = domain.to_string();
     let config_clone5 = cloud_config.clone();
-    let azure_svc_handle = tokio::spawn(run_azure_service_checks(client_clone5, domain_clone5, config_clone5));
+    let azure_svc_handle = tokio::spawn(run_azure_service_checks(
+        client_clone5,
+        domain_clone5,
+        config_clone5,
+        results.dns_results.clone(),
+    ));
 
     // Await Service results
     match m365_handle.await {
@@ -137,6 +299,142 @@ pub async fn run_all_checks(
         }
     }
 
+    // --- NTLM challenge disclosure (needs the authentication schemes from the M365 checks) ---
+    run_ntlm_checks(&mut results, &client).await;
+
+    // --- Self-hosted Exchange build fingerprinting (needs Autodiscover and MX results) ---
+    run_exchange_checks(&mut results, &client).await;
+
+    // --- Intune/MDM enrollment and device registration (needs DNS results) ---
+    match check_provisioning_endpoints(client.clone(), domain.clone(), cloud_config.clone(), results.dns_results.clone()).await {
+        Ok(provisioning) => results.provisioning = Some(provisioning),
+        Err(e) => warn!(target = domain.as_str(), "Provisioning endpoint checks failed: {}", e),
+    }
+
+    // --- SAML / WS-Federation / WS-Trust endpoints (needs tenant and federation results) ---
+    let tenant_id = results.tenant_info.as_ref().and_then(|tenant| tenant.tenant_id.clone());
+    match check_saml_endpoints(client.clone(), domain.clone(), cloud_config.clone(), tenant_id, results.federation_info.clone()).await {
+        Ok(saml) => results.saml_results = Some(saml),
+        Err(e) => warn!(target = domain.as_str(), "SAML endpoint checks failed: {}", e),
+    }
+
     info!(target = domain.as_str(), "All reconnaissance checks finished.");
     Ok(results)
-}
\ No newline at end of file
+}
+
+/// Probes the MX hosts, and the EOP host when it bypasses a gateway, over SMTP.
+async fn run_smtp_probe(results: &mut ReconResults, options: &ReconOptions) {
+    let Some(dns) = results.dns_results.as_mut() else {
+        return;
+    };
+    let mut hosts: Vec<String> = dns
+        .mx_hosts
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .map(|mx| mx.host.clone())
+        .collect();
+    let bypass_host = dns
+        .eop_direct_delivery
+        .as_ref()
+        .filter(|eop| eop.gateway_bypass_exposed)
+        .map(|eop| eop.eop_host.clone());
+    if let Some(host) = &bypass_host {
+        hosts.push(host.clone());
+    }
+    if hosts.is_empty() {
+        return;
+    }
+
+    info!(target = results.domain.as_str(), hosts = hosts.len(), "Probing SMTP hosts");
+    match probe_smtp_hosts(hosts, SMTP_PORT, options.probe_timeout).await {
+        Ok(probes) => {
+            if let (Some(host), Some(eop)) = (&bypass_host, dns.eop_direct_delivery.as_mut()) {
+                eop.smtp_accepts_connections = probes.iter().find(|probe| &probe.host == host).map(|probe| probe.connected);
+            }
+            results.smtp_results = Some(probes);
+        }
+        Err(e) => warn!(target = results.domain.as_str(), "SMTP probe failed: {}", e),
+    }
+}
+
+/// Resolves the federation (e.g., ADFS) host and flags self-hosted servers outside the tenant's region.
+async fn run_region_checks(results: &mut ReconResults, options: &ReconOptions) {
+    let Some(dns) = results.dns_results.as_mut() else {
+        return;
+    };
+    let resolved_hosts = dns.resolved_hosts.get_or_insert_with(Vec::new);
+
+    let federation_host = results
+        .federation_info
+        .as_ref()
+        .and_then(|federation| federation.auth_url.as_deref())
+        .and_then(|auth_url| reqwest::Url::parse(auth_url).ok())
+        .and_then(|url| url.host_str().map(str::to_lowercase));
+    if let Some(host) = federation_host.filter(|host| !resolved_hosts.iter().any(|resolved| &resolved.hostname == host)) {
+        let resolver = TokioAsyncResolver::tokio(ResolverConfig::google(), ResolverOpts::default());
+        let limiter = Arc::new(QueryLimiter::new(options.subdomain_qps));
+        match resolve_hosts(resolver, vec![host], limiter, options.service_tags.clone(), options.geoip.clone()).await {
+            Ok(hosts) => resolved_hosts.extend(hosts),
+            Err(e) => warn!(target = results.domain.as_str(), "Federation host resolution failed: {}", e),
+        }
+    }
+
+    if let Some(scope) = results
+        .azure_ad_config
+        .as_ref()
+        .and_then(|config| config.tenant_region_scope.as_deref())
+    {
+        let flagged = flag_outside_tenant_region(resolved_hosts, scope);
+        info!(target = results.domain.as_str(), flagged, "Finished tenant region checks");
+    }
+}
+
+/// Decodes the NTLM challenges of self-hosted endpoints that offer Integrated Windows Authentication.
+async fn run_ntlm_checks(results: &mut ReconResults, client: &Client) {
+    let auth_endpoints = results
+        .m365_results
+        .as_ref()
+        .map(|m365| m365.auth_endpoints.clone())
+        .unwrap_or_default();
+    let federation_host = results
+        .federation_info
+        .as_ref()
+        .and_then(|federation| federation.auth_url.as_deref())
+        .and_then(|auth_url| reqwest::Url::parse(auth_url).ok())
+        .and_then(|url| url.host_str().map(str::to_lowercase));
+
+    match check_ntlm_disclosure(
+        client.clone(),
+        results.domain.clone(),
+        results.dns_results.clone(),
+        auth_endpoints,
+        federation_host,
+    )
+    .await
+    {
+        Ok(disclosures) => results.ntlm_results = Some(disclosures),
+        Err(e) => warn!(target = results.domain.as_str(), "NTLM checks failed: {}", e),
+    }
+}
+
+/// Fingerprints self-hosted Exchange front ends found by Autodiscover, MX and host discovery.
+async fn run_exchange_checks(results: &mut ReconResults, client: &Client) {
+    let (autodiscover, auth_endpoints) = match &results.m365_results {
+        Some(m365) => (m365.autodiscover.clone(), m365.auth_endpoints.clone()),
+        None => (None, Vec::new()),
+    };
+
+    match check_exchange_versions(
+        client.clone(),
+        results.domain.clone(),
+        results.dns_results.clone(),
+        autodiscover,
+        auth_endpoints,
+    )
+    .await
+    {
+        Ok(fingerprints) => results.exchange_results = Some(fingerprints),
+        Err(e) => warn!(target = results.domain.as_str(), "Exchange version checks failed: {}", e),
+    }
+}
@@ -0,0 +1,205 @@
+use crate::error::ReconError;
+use crate::models::{DnsResults, EopDirectDelivery, MailProvider, MailRouting, MxHost};
+use tracing::{debug, info, warn};
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// MX host signatures: (dot-anchored host suffix, provider, is a secure email gateway).
+/// A suffix also matches the host named by the suffix itself (e.g., `smtp.google.com`).
+const MX_SIGNATURES: &[(&str, MailProvider, bool)] = &[
+    (".mail.protection.outlook.com", MailProvider::ExchangeOnline, false),
+    (".mail.protection.office365.us", MailProvider::ExchangeOnline, false),
+    (".mail.protection.partner.outlook.cn", MailProvider::ExchangeOnline, false),
+    (".mx.microsoft", MailProvider::ExchangeOnline, false), // DANE-enabled EXO MX hosts
+    (".aspmx.l.google.com", MailProvider::GoogleWorkspace, false),
+    (".googlemail.com", MailProvider::GoogleWorkspace, false),
+    (".smtp.google.com", MailProvider::GoogleWorkspace, false),
+    (".zoho.com", MailProvider::Zoho, false),
+    (".zoho.eu", MailProvider::Zoho, false),
+    (".pphosted.com", MailProvider::Proofpoint, true),
+    (".ppe-hosted.com", MailProvider::Proofpoint, true),
+    (".mimecast.com", MailProvider::Mimecast, true),
+    (".mimecast.co.za", MailProvider::Mimecast, true),
+    (".mimecast-offshore.com", MailProvider::Mimecast, true),
+    (".barracudanetworks.com", MailProvider::Barracuda, true),
+    (".iphmx.com", MailProvider::CiscoSecureEmail, true),
+    (".sophos.com", MailProvider::Sophos, true),
+    (".trendmicro.com", MailProvider::TrendMicro, true),
+    (".trendmicro.eu", MailProvider::TrendMicro, true),
+    (".messagelabs.com", MailProvider::Symantec, true),
+    (".mailcontrol.com", MailProvider::Forcepoint, true),
+    (".hornetsecurity.com", MailProvider::Hornetsecurity, true),
+];
+
+/// Classifies an MX host against the provider signature table.
+pub fn classify_mx_host(host: &str, priority: u16) -> MxHost {
+    let host = host.trim_end_matches('.').to_lowercase();
+    let (provider, secure_email_gateway) = MX_SIGNATURES
+        .iter()
+        .find(|(suffix, _, _)| host.ends_with(suffix) || host == suffix.trim_start_matches('.'))
+        .map(|(_, provider, gateway)| (provider.clone(), *gateway))
+        .unwrap_or((MailProvider::OnPremises, false));
+
+    MxHost {
+        host,
+        priority,
+        provider,
+        secure_email_gateway,
+    }
+}
+
+/// Derives the tenant's EOP host names, e.g. `contoso.co.uk` -> `contoso-co-uk.mail.protection.outlook.com`.
+///
+/// Domains that already contain hyphens also get a candidate with the hyphens removed,
+/// which is how Exchange Online names some of those hosts.
+fn eop_host_candidates(domain: &str, eop_host_suffix: &str) -> Vec<String> {
+    let domain = domain.trim_end_matches('.').to_lowercase();
+    let mut candidates = vec![format!("{}{}", domain.replace('.', "-"), eop_host_suffix)];
+    if domain.contains('-') {
+        candidates.push(format!("{}{}", domain.replace('-', "").replace('.', "-"), eop_host_suffix));
+    }
+    candidates
+}
+
+/// Checks whether the tenant's EOP host accepts mail directly while MX points elsewhere.
+///
+/// When a third-party gateway fronts Exchange Online, the EOP host usually still
+/// resolves, letting senders bypass the gateway's filtering.
+/// Returns `None` if no EOP suffix is configured for the selected cloud.
+pub async fn check_eop_direct_delivery(
+    resolver: TokioAsyncResolver,
+    domain: String,
+    eop_host_suffix: String,
+    mx_hosts: Vec<MxHost>,
+) -> Result<Option<EopDirectDelivery>, ReconError> {
+    if eop_host_suffix.is_empty() {
+        debug!(domain = domain.as_str(), "EOP direct delivery check skipped: no suffix in config");
+        return Ok(None);
+    }
+
+    let candidates = eop_host_candidates(&domain, &eop_host_suffix);
+    let mut eop_host = candidates[0].clone();
+    let mut addresses = Vec::new();
+    for candidate in candidates {
+        debug!(domain = domain.as_str(), host = candidate.as_str(), "Resolving EOP host");
+        if let Ok(response) = resolver.lookup_ip(candidate.as_str()).await {
+            addresses = response.iter().map(|ip| ip.to_string()).collect();
+            if !addresses.is_empty() {
+                eop_host = candidate;
+                break;
+            }
+        }
+    }
+
+    let resolves = !addresses.is_empty();
+    let mx_points_elsewhere = !mx_hosts.is_empty()
+        && !mx_hosts.iter().any(|mx| mx.provider == MailProvider::ExchangeOnline);
+    let gateway_bypass_exposed = resolves && mx_points_elsewhere;
+    if gateway_bypass_exposed {
+        warn!(domain = domain.as_str(), host = eop_host.as_str(), "EOP host resolves while MX points elsewhere (gateway bypass)");
+    } else {
+        info!(domain = domain.as_str(), resolves, "Finished EOP direct delivery check");
+    }
+
+    Ok(Some(EopDirectDelivery {
+        eop_host,
+        resolves,
+        addresses,
+        mx_points_elsewhere,
+        gateway_bypass_exposed,
+        smtp_accepts_connections: None,
+    }))
+}
+
+/// Determines how inbound mail reaches the tenant from the classified MX hosts.
+///
+/// A gateway is only reported as fronting Exchange Online when other DNS evidence
+/// (EOP host, SPF include, M365 DKIM selectors or Autodiscover) shows the tenant uses it.
+/// Returns the routing and the primary (lowest-priority) gateway, if any.
+pub fn determine_mail_routing(dns: &DnsResults) -> (MailRouting, Option<MailProvider>) {
+    let mx_hosts = dns.mx_hosts.as_deref().unwrap_or_default();
+    let Some(primary) = mx_hosts.iter().min_by_key(|mx| mx.priority) else {
+        return (MailRouting::NoMx, None);
+    };
+
+    if primary.provider == MailProvider::ExchangeOnline {
+        return (MailRouting::ExchangeOnlineDirect, None);
+    }
+    if !primary.secure_email_gateway {
+        return (MailRouting::OtherProvider, None);
+    }
+
+    let exchange_online_evidence = mx_hosts.iter().any(|mx| mx.provider == MailProvider::ExchangeOnline)
+        || dns.eop_direct_delivery.as_ref().is_some_and(|eop| eop.resolves)
+        || dns
+            .spf_analysis
+            .as_ref()
+            .and_then(|spf| spf.includes_exchange_online)
+            .unwrap_or(false)
+        || dns
+            .dkim
+            .as_ref()
+            .is_some_and(|dkim| dkim.selectors.iter().any(|selector| selector.microsoft_365))
+        || dns
+            .autodiscover_cname_or_a
+            .as_deref()
+            .is_some_and(|target| target.trim_end_matches('.').ends_with("outlook.com"));
+
+    let routing = if exchange_online_evidence {
+        MailRouting::GatewayToExchangeOnline
+    } else {
+        MailRouting::ThirdPartyGateway
+    };
+    (routing, Some(primary.provider.clone()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_classify_mx_host() {
+        let exo = classify_mx_host("contoso-com.mail.protection.outlook.com.", 0);
+        assert_eq!(exo.provider, MailProvider::ExchangeOnline);
+        assert!(!exo.secure_email_gateway);
+        assert_eq!(exo.host, "contoso-com.mail.protection.outlook.com");
+
+        let proofpoint = classify_mx_host("mx0a-001b2d01.pphosted.com", 10);
+        assert_eq!(proofpoint.provider, MailProvider::Proofpoint);
+        assert!(proofpoint.secure_email_gateway);
+
+        assert_eq!(classify_mx_host("aspmx.l.google.com", 1).provider, MailProvider::GoogleWorkspace);
+        assert_eq!(classify_mx_host("alt1.aspmx.l.google.com.", 5).provider, MailProvider::GoogleWorkspace);
+        assert_eq!(classify_mx_host("smtp.google.com", 1).provider, MailProvider::GoogleWorkspace);
+        assert_eq!(classify_mx_host("notsmtp.google.com", 1).provider, MailProvider::OnPremises);
+        assert_eq!(classify_mx_host("mail.contoso.com", 5).provider, MailProvider::OnPremises);
+    }
+
+    #[test]
+    fn test_determine_mail_routing() {
+        let mut dns = DnsResults {
+            mx_hosts: Some(vec![classify_mx_host("eu-smtp-inbound-1.mimecast.com", 10)]),
+            ..Default::default()
+        };
+        assert_eq!(determine_mail_routing(&dns), (MailRouting::ThirdPartyGateway, Some(MailProvider::Mimecast)));
+
+        dns.autodiscover_cname_or_a = Some("autodiscover.outlook.com.".to_string());
+        assert_eq!(determine_mail_routing(&dns).0, MailRouting::GatewayToExchangeOnline);
+
+        assert_eq!(determine_mail_routing(&DnsResults::default()), (MailRouting::NoMx, None));
+    }
+
+    #[test]
+    fn test_eop_host_candidates() {
+        assert_eq!(
+            eop_host_candidates("contoso.co.uk", ".mail.protection.outlook.com"),
+            vec!["contoso-co-uk.mail.protection.outlook.com"]
+        );
+        assert_eq!(
+            eop_host_candidates("contoso-corp.com.", ".mail.protection.outlook.com"),
+            vec![
+                "contoso-corp-com.mail.protection.outlook.com",
+                "contosocorp-com.mail.protection.outlook.com"
+            ]
+        );
+    }
+}
@@ -0,0 +1,363 @@
+use crate::error::ReconError;
+use crate::models::{DnsProvider, NameServer, NameServerResults, SoaRecord, ZoneTransferResult};
+use crate::recon::common::random_u64;
+use futures::future::join_all;
+use std::net::{IpAddr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tracing::{debug, info, warn};
+use trust_dns_resolver::error::ResolveErrorKind;
+use trust_dns_resolver::proto::op::{Message, MessageType, OpCode, Query, ResponseCode};
+use trust_dns_resolver::proto::rr::{Name, RData, Record, RecordType};
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Name server host name patterns: (pattern, provider). Patterns are matched as substrings.
+const DNS_PROVIDER_SIGNATURES: &[(&str, DnsProvider)] = &[
+    (".azure-dns.", DnsProvider::AzureDns),
+    (".azuredns-cloud.net", DnsProvider::AzureDns),
+    (".bdm.microsoftonline.com", DnsProvider::Microsoft365),
+    (".awsdns-", DnsProvider::Route53),
+    (".ns.cloudflare.com", DnsProvider::Cloudflare),
+    (".googledomains.com", DnsProvider::GoogleCloudDns),
+    (".akam.net", DnsProvider::Akamai),
+    (".ultradns.", DnsProvider::UltraDns),
+    (".nsone.net", DnsProvider::Ns1),
+    (".dynect.net", DnsProvider::OracleDyn),
+    (".domaincontrol.com", DnsProvider::GoDaddy),
+    (".registrar-servers.com", DnsProvider::Namecheap),
+];
+
+/// Port used for direct queries to authoritative servers.
+const DNS_PORT: u16 = 53;
+
+/// Most records kept from a successful zone transfer.
+const MAX_AXFR_RECORDS: usize = 500;
+
+/// Most messages read from a single zone transfer.
+const MAX_AXFR_MESSAGES: usize = 1000;
+
+/// Resolves the NS and SOA records of the zone, identifies the DNS provider and queries
+/// each authoritative server directly to compare their answers.
+///
+/// With `attempt_axfr`, a zone transfer is also attempted against each server.
+/// Returns `None` if the domain has no NS records of its own.
+pub async fn check_nameservers(
+    resolver: TokioAsyncResolver,
+    domain: String,
+    attempt_axfr: bool,
+    query_timeout: Duration,
+) -> Result<Option<NameServerResults>, ReconError> {
+    debug!(domain = domain.as_str(), "Querying NS records");
+    let mut ns_hosts: Vec<String> = match resolver.ns_lookup(domain.as_str()).await {
+        Ok(response) => response.iter().map(|ns| normalize(&ns.0.to_string())).collect(),
+        Err(e) if matches!(e.kind(), ResolveErrorKind::NoRecordsFound { .. }) => {
+            debug!(domain = domain.as_str(), "No NS records");
+            return Ok(None);
+        }
+        Err(e) => return Err(e.into()),
+    };
+    ns_hosts.sort();
+    ns_hosts.dedup();
+
+    let soa = match resolver.soa_lookup(domain.as_str()).await {
+        Ok(response) => response.iter().next().map(|soa| SoaRecord {
+            primary_ns: normalize(&soa.mname().to_string()),
+            responsible_mailbox: normalize(&soa.rname().to_string()),
+            serial: soa.serial(),
+            refresh: soa.refresh(),
+            retry: soa.retry(),
+            expire: soa.expire(),
+            minimum: soa.minimum(),
+        }),
+        Err(e) => {
+            debug!(domain = domain.as_str(), error = %e, "SOA lookup failed");
+            None
+        }
+    };
+
+    let checks = ns_hosts.iter().map(|host| {
+        let resolver = resolver.clone();
+        let domain = domain.clone();
+        async move { query_nameserver(&resolver, host, &domain, attempt_axfr, query_timeout).await }
+    });
+    let nameservers: Vec<NameServer> = join_all(checks).await;
+
+    let mut providers: Vec<DnsProvider> = Vec::new();
+    for nameserver in &nameservers {
+        if !providers.contains(&nameserver.provider) {
+            providers.push(nameserver.provider.clone());
+        }
+    }
+    let issues = consistency_issues(&nameservers, &ns_hosts);
+    for nameserver in &nameservers {
+        if nameserver.zone_transfer.as_ref().is_some_and(|axfr| axfr.allowed) {
+            warn!(domain = domain.as_str(), nameserver = nameserver.host.as_str(), "Zone transfer (AXFR) allowed");
+        }
+    }
+
+    info!(domain = domain.as_str(), nameservers = nameservers.len(), "Finished name server checks");
+    Ok(Some(NameServerResults {
+        consistent: issues.is_empty(),
+        nameservers,
+        soa,
+        providers,
+        issues,
+    }))
+}
+
+/// Queries one authoritative server directly for the zone's SOA and NS records.
+async fn query_nameserver(
+    resolver: &TokioAsyncResolver,
+    host: &str,
+    domain: &str,
+    attempt_axfr: bool,
+    query_timeout: Duration,
+) -> NameServer {
+    let addresses: Vec<IpAddr> = match resolver.lookup_ip(host).await {
+        Ok(response) => response.iter().collect(),
+        Err(e) => {
+            debug!(nameserver = host, error = %e, "Name server address lookup failed");
+            Vec::new()
+        }
+    };
+    // Prefer IPv4; many networks cannot reach authoritative servers over IPv6
+    let server = addresses
+        .iter()
+        .find(|ip| ip.is_ipv4())
+        .or_else(|| addresses.first())
+        .map(|ip| SocketAddr::new(*ip, DNS_PORT));
+
+    let mut nameserver = NameServer {
+        host: host.to_string(),
+        addresses: addresses.iter().map(|ip| ip.to_string()).collect(),
+        provider: classify_dns_provider(host, domain),
+        responded: false,
+        soa_serial: None,
+        ns_records: Vec::new(),
+        zone_transfer: None,
+    };
+    let Some(server) = server else {
+        return nameserver;
+    };
+
+    if let Ok(responses) = query_server(server, domain, RecordType::SOA, query_timeout).await {
+        nameserver.responded = true;
+        nameserver.soa_serial = answers(&responses).find_map(|record| match record.data() {
+            Some(RData::SOA(soa)) => Some(soa.serial()),
+            _ => None,
+        });
+    }
+    if let Ok(responses) = query_server(server, domain, RecordType::NS, query_timeout).await {
+        nameserver.responded = true;
+        let mut ns_records: Vec<String> = answers(&responses)
+            .filter_map(|record| match record.data() {
+                Some(RData::NS(ns)) => Some(normalize(&ns.0.to_string())),
+                _ => None,
+            })
+            .collect();
+        ns_records.sort();
+        nameserver.ns_records = ns_records;
+    }
+
+    if attempt_axfr {
+        nameserver.zone_transfer = Some(attempt_zone_transfer(server, domain, query_timeout).await);
+    }
+    nameserver
+}
+
+/// Attempts an AXFR zone transfer of `zone` from the server at `server`.
+pub async fn attempt_zone_transfer(server: SocketAddr, zone: &str, query_timeout: Duration) -> ZoneTransferResult {
+    debug!(server = %server, zone = zone, "Attempting zone transfer");
+    match query_server(server, zone, RecordType::AXFR, query_timeout).await {
+        Ok(responses) => {
+            let records: Vec<&Record> = answers(&responses).collect();
+            // A complete transfer starts and ends with the SOA record
+            let soa_count = records.iter().filter(|record| record.record_type() == RecordType::SOA).count();
+            if soa_count < 2 {
+                return ZoneTransferResult {
+                    error: Some("Transfer refused or incomplete".to_string()),
+                    ..Default::default()
+                };
+            }
+            ZoneTransferResult {
+                allowed: true,
+                record_count: records.len(),
+                records: records.iter().take(MAX_AXFR_RECORDS).map(|record| record.to_string()).collect(),
+                error: None,
+            }
+        }
+        Err(e) => ZoneTransferResult {
+            error: Some(e.to_string()),
+            ..Default::default()
+        },
+    }
+}
+
+/// Sends a query over TCP to a single server and reads its response messages.
+///
+/// AXFR responses span several messages; they are read until the closing SOA.
+async fn query_server(
+    server: SocketAddr,
+    name: &str,
+    record_type: RecordType,
+    query_timeout: Duration,
+) -> Result<Vec<Message>, ReconError> {
+    let name = Name::from_str(name).map_err(|e| ReconError::check_failed("Name Server Query", e.to_string()))?;
+    let mut query = Message::new();
+    query
+        .set_id(random_u64() as u16)
+        .set_message_type(MessageType::Query)
+        .set_op_code(OpCode::Query)
+        .set_recursion_desired(false)
+        .add_query(Query::query(name, record_type));
+    let bytes = query
+        .to_vec()
+        .map_err(|e| ReconError::check_failed("Name Server Query", e.to_string()))?;
+
+    let exchange = async {
+        let mut stream = TcpStream::connect(server).await?;
+        stream.write_u16(bytes.len() as u16).await?;
+        stream.write_all(&bytes).await?;
+
+        let mut responses = Vec::new();
+        let mut soa_count = 0;
+        while responses.len() < MAX_AXFR_MESSAGES {
+            let length = stream.read_u16().await?;
+            let mut buffer = vec![0u8; usize::from(length)];
+            stream.read_exact(&mut buffer).await?;
+            let response =
+                Message::from_vec(&buffer).map_err(|e| ReconError::check_failed("Name Server Query", e.to_string()))?;
+            if response.response_code() != ResponseCode::NoError {
+                return Err(ReconError::check_failed(
+                    "Name Server Query",
+                    format!("{} answered {}", server, response.response_code()),
+                ));
+            }
+            soa_count += response
+                .answers()
+                .iter()
+                .filter(|record| record.record_type() == RecordType::SOA)
+                .count();
+            let finished = record_type != RecordType::AXFR || soa_count >= 2 || response.answers().is_empty();
+            responses.push(response);
+            if finished {
+                break;
+            }
+        }
+        Ok(responses)
+    };
+
+    timeout(query_timeout, exchange)
+        .await
+        .map_err(|_| ReconError::check_failed("Name Server Query", format!("{} timed out", server)))?
+}
+
+/// Iterates over the answer records of all response messages.
+fn answers(responses: &[Message]) -> impl Iterator<Item = &Record> {
+    responses.iter().flat_map(|response| response.answers().iter())
+}
+
+/// Identifies the DNS hosting provider from a name server host name.
+fn classify_dns_provider(host: &str, domain: &str) -> DnsProvider {
+    let host = normalize(host);
+    let domain = normalize(domain);
+    if let Some((_, provider)) = DNS_PROVIDER_SIGNATURES.iter().find(|(pattern, _)| host.contains(pattern)) {
+        return provider.clone();
+    }
+    if host.ends_with(&format!(".{}", domain)) {
+        DnsProvider::SelfHosted
+    } else {
+        DnsProvider::Other
+    }
+}
+
+/// Compares the answers of each authoritative server against the delegation and each other.
+fn consistency_issues(nameservers: &[NameServer], delegated: &[String]) -> Vec<String> {
+    let mut issues = Vec::new();
+    for nameserver in nameservers.iter().filter(|nameserver| !nameserver.responded) {
+        issues.push(format!("{} did not answer for the zone", nameserver.host));
+    }
+
+    let responding: Vec<&NameServer> = nameservers.iter().filter(|nameserver| nameserver.responded).collect();
+    let mut serials: Vec<u32> = responding.iter().filter_map(|nameserver| nameserver.soa_serial).collect();
+    serials.sort_unstable();
+    serials.dedup();
+    if serials.len() > 1 {
+        let details: Vec<String> = responding
+            .iter()
+            .map(|nameserver| {
+                let serial = nameserver.soa_serial.map_or("none".to_string(), |serial| serial.to_string());
+                format!("{}={}", nameserver.host, serial)
+            })
+            .collect();
+        issues.push(format!("SOA serials differ between name servers ({})", details.join(", ")));
+    }
+
+    for nameserver in responding {
+        if !nameserver.ns_records.is_empty() && nameserver.ns_records != delegated {
+            issues.push(format!(
+                "{} serves an NS set ({}) that differs from the delegation ({})",
+                nameserver.host,
+                nameserver.ns_records.join(", "),
+                delegated.join(", ")
+            ));
+        }
+    }
+    issues
+}
+
+/// Lower-cases a DNS name and removes its trailing dot.
+fn normalize(name: &str) -> String {
+    name.trim_end_matches('.').to_lowercase()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nameserver(host: &str, serial: u32, ns_records: &[&str]) -> NameServer {
+        NameServer {
+            host: host.to_string(),
+            addresses: Vec::new(),
+            provider: DnsProvider::Other,
+            responded: true,
+            soa_serial: Some(serial),
+            ns_records: ns_records.iter().map(|ns| ns.to_string()).collect(),
+            zone_transfer: None,
+        }
+    }
+
+    #[test]
+    fn test_classify_dns_provider() {
+        assert_eq!(classify_dns_provider("ns1-05.azure-dns.com.", "contoso.com"), DnsProvider::AzureDns);
+        assert_eq!(classify_dns_provider("ns-1536.awsdns-00.co.uk", "contoso.com"), DnsProvider::Route53);
+        assert_eq!(classify_dns_provider("kim.ns.cloudflare.com", "contoso.com"), DnsProvider::Cloudflare);
+        assert_eq!(classify_dns_provider("ns1.bdm.microsoftonline.com", "contoso.com"), DnsProvider::Microsoft365);
+        assert_eq!(classify_dns_provider("dns1.contoso.com", "contoso.com"), DnsProvider::SelfHosted);
+        assert_eq!(classify_dns_provider("ns1.example.net", "contoso.com"), DnsProvider::Other);
+    }
+
+    #[test]
+    fn test_consistency_issues() {
+        let delegated = vec!["ns1.contoso.com".to_string(), "ns2.contoso.com".to_string()];
+        let consistent = vec![
+            nameserver("ns1.contoso.com", 2024010101, &["ns1.contoso.com", "ns2.contoso.com"]),
+            nameserver("ns2.contoso.com", 2024010101, &["ns1.contoso.com", "ns2.contoso.com"]),
+        ];
+        assert!(consistency_issues(&consistent, &delegated).is_empty());
+
+        let mut stale = consistent.clone();
+        stale[1].soa_serial = Some(2023120101);
+        stale[1].ns_records = vec!["ns1.contoso.com".to_string(), "ns3.contoso.com".to_string()];
+        let issues = consistency_issues(&stale, &delegated);
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].contains("SOA serials differ"));
+
+        let mut silent = consistent;
+        silent[0].responded = false;
+        assert_eq!(consistency_issues(&silent, &delegated), vec!["ns1.contoso.com did not answer for the zone"]);
+    }
+}
@@ -0,0 +1,329 @@
+use crate::error::ReconError;
+use crate::models::{AuthEndpoint, DnsResults, NtlmDisclosure, NtlmTargetInfo};
+use crate::recon::common::{ADFS_HOST_LABELS, EXCHANGE_HOST_LABELS, RD_GATEWAY_HOST_LABELS, SKYPE_HOST_LABELS};
+use crate::recon::legacy_auth::self_hosted_hosts;
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use futures::future::join_all;
+use reqwest::header::{AUTHORIZATION, WWW_AUTHENTICATE};
+use reqwest::{Client, Url};
+use tracing::{debug, info, warn};
+
+/// NTLMSSP message signature.
+const NTLMSSP_SIGNATURE: &[u8; 8] = b"NTLMSSP\0";
+
+/// NegotiateFlags bit indicating the Version field is populated.
+const NTLMSSP_NEGOTIATE_VERSION: u32 = 0x0200_0000;
+
+/// Seconds between the FILETIME epoch (1601-01-01) and the Unix epoch.
+const FILETIME_UNIX_OFFSET: u64 = 11_644_473_600;
+
+/// Paths of Microsoft services that commonly offer NTLM (Integrated Windows Authentication): (service, path).
+const NTLM_PATHS: &[(&str, &str)] = &[
+    ("Exchange", "/EWS/Exchange.asmx"),
+    ("Exchange", "/rpc/rpcproxy.dll"),
+    ("Exchange", "/autodiscover/autodiscover.xml"),
+    ("Exchange", "/Microsoft-Server-ActiveSync"),
+    ("Skype/Lync", "/WebTicket/WebTicketService.svc"),
+    ("Skype/Lync", "/abs/"),
+    ("ADFS", "/adfs/services/trust/2005/windowstransport"),
+    ("RD Gateway", "/RDWeb/"),
+    ("RD Gateway", "/remoteDesktopGateway/"),
+];
+
+/// Windows builds as reported in the NTLM Version field: (major, minor, build, release).
+const WINDOWS_BUILDS: &[(u8, u8, u16, &str)] = &[
+    (6, 1, 7601, "Windows Server 2008 R2"),
+    (6, 2, 9200, "Windows Server 2012"),
+    (6, 3, 9600, "Windows Server 2012 R2"),
+    (10, 0, 14393, "Windows Server 2016"),
+    (10, 0, 17763, "Windows Server 2019"),
+    (10, 0, 20348, "Windows Server 2022"),
+    (10, 0, 26100, "Windows Server 2025"),
+];
+
+/// URL to probe, with the service behind it and the scheme to send the negotiate message with.
+type NtlmCandidate = (String, &'static str, &'static str);
+
+/// Sends an NTLM negotiate message to self-hosted Microsoft endpoints and decodes the internal
+/// Active Directory details from each server's challenge.
+///
+/// Candidates are the on-premises endpoints that offered NTLM or Negotiate during the
+/// authentication scheme checks, plus well-known paths on self-hosted and federation hosts.
+/// One disclosure is reported per host.
+pub async fn check_ntlm_disclosure(
+    client: Client,
+    domain: String,
+    dns_results: Option<DnsResults>,
+    auth_endpoints: Vec<AuthEndpoint>,
+    federation_host: Option<String>,
+) -> Result<Vec<NtlmDisclosure>, ReconError> {
+    // Candidates grouped by host, in probing order
+    let mut candidates: Vec<(String, Vec<NtlmCandidate>)> = Vec::new();
+    let mut add = |url: String, service: &'static str, scheme: &'static str| {
+        let Some(host) = Url::parse(&url).ok().and_then(|url| url.host_str().map(str::to_lowercase)) else {
+            return;
+        };
+        match candidates.iter_mut().find(|(existing, _)| *existing == host) {
+            Some((_, urls)) if !urls.iter().any(|(existing, _, _)| *existing == url) => urls.push((url, service, scheme)),
+            Some(_) => {}
+            None => candidates.push((host, vec![(url, service, scheme)])),
+        }
+    };
+
+    for endpoint in auth_endpoints.iter().filter(|endpoint| endpoint.on_premises && endpoint.reachable) {
+        let scheme = if endpoint.schemes.iter().any(|scheme| scheme == "NTLM") {
+            "NTLM"
+        } else if endpoint.schemes.iter().any(|scheme| scheme == "Negotiate") {
+            "Negotiate"
+        } else {
+            continue;
+        };
+        add(endpoint.endpoint.clone(), service_for_url(&endpoint.endpoint), scheme);
+    }
+    let labels = [EXCHANGE_HOST_LABELS, SKYPE_HOST_LABELS, ADFS_HOST_LABELS, RD_GATEWAY_HOST_LABELS].concat();
+    let mut hosts = self_hosted_hosts(&domain, dns_results.as_ref(), &labels);
+    hosts.extend(federation_host);
+    for host in hosts {
+        for (service, path) in NTLM_PATHS {
+            add(format!("https://{}{}", host, path), service, "NTLM");
+        }
+    }
+    debug!(domain = domain.as_str(), hosts = candidates.len(), "Probing NTLM challenges");
+
+    let probes = candidates.iter().map(|(_, urls)| {
+        let client = client.clone();
+        async move {
+            for (url, service, scheme) in urls {
+                if let Some(target_info) = probe_ntlm_endpoint(&client, url, scheme).await {
+                    return Some(NtlmDisclosure {
+                        url: url.clone(),
+                        service: service.to_string(),
+                        target_info,
+                    });
+                }
+            }
+            None
+        }
+    });
+    let disclosures: Vec<NtlmDisclosure> = join_all(probes).await.into_iter().flatten().collect();
+    for disclosure in &disclosures {
+        warn!(
+            url = disclosure.url.as_str(),
+            dns_domain = disclosure.target_info.dns_domain.as_deref().unwrap_or("unknown"),
+            server = disclosure.target_info.dns_computer.as_deref().unwrap_or("unknown"),
+            "NTLM challenge discloses internal domain details"
+        );
+    }
+    info!(domain = domain.as_str(), disclosures = disclosures.len(), "Finished NTLM challenge checks");
+    Ok(disclosures)
+}
+
+/// Sends an NTLM Type 1 message to `url` and decodes the Type 2 challenge, if the server answers with one.
+pub async fn probe_ntlm_endpoint(client: &Client, url: &str, scheme: &str) -> Option<NtlmTargetInfo> {
+    let authorization = format!("{} {}", scheme, STANDARD.encode(negotiate_message()));
+    let response = match client.get(url).header(AUTHORIZATION, authorization).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            debug!(url, error = %e, "NTLM negotiate request failed");
+            return None;
+        }
+    };
+
+    for challenge in response.headers().get_all(WWW_AUTHENTICATE).iter().filter_map(|value| value.to_str().ok()) {
+        let Some((challenge_scheme, token)) = challenge.trim().split_once(' ') else {
+            continue;
+        };
+        if !challenge_scheme.eq_ignore_ascii_case("NTLM") && !challenge_scheme.eq_ignore_ascii_case("Negotiate") {
+            continue;
+        }
+        let Ok(message) = STANDARD.decode(token.trim()) else {
+            continue;
+        };
+        match decode_challenge(&message) {
+            Ok(target_info) => return Some(target_info),
+            Err(e) => debug!(url, error = %e, "Undecodable NTLM challenge"),
+        }
+    }
+    None
+}
+
+/// Builds an NTLM Type 1 (negotiate) message requesting the target info and version.
+pub fn negotiate_message() -> Vec<u8> {
+    // UNICODE | OEM | REQUEST_TARGET | NTLM | ALWAYS_SIGN | EXTENDED_SESSIONSECURITY | 128 | 56
+    let flags: u32 = 0xa208_8207;
+    let mut message = NTLMSSP_SIGNATURE.to_vec();
+    message.extend_from_slice(&1u32.to_le_bytes());
+    message.extend_from_slice(&flags.to_le_bytes());
+    message.extend_from_slice(&[0u8; 16]); // Empty domain and workstation fields
+    message.extend_from_slice(&[6, 1, 0xb1, 0x1d, 0, 0, 0, 0x0f]); // Version 6.1.7601, NTLM revision 15
+    message
+}
+
+/// Decodes the target info and version of an NTLM Type 2 (challenge) message.
+pub fn decode_challenge(message: &[u8]) -> Result<NtlmTargetInfo, ReconError> {
+    if message.len() < 48 || &message[..8] != NTLMSSP_SIGNATURE {
+        return Err(ReconError::check_failed("NTLM", "Not an NTLMSSP message"));
+    }
+    if read_u32(message, 8) != Some(2) {
+        return Err(ReconError::check_failed("NTLM", "Not a challenge (Type 2) message"));
+    }
+    let flags = read_u32(message, 20).unwrap_or_default();
+    let mut info = NtlmTargetInfo::default();
+
+    // The Version field sits between the fixed header and the payload when present
+    let payload_start = [read_u32(message, 16), read_u32(message, 44)]
+        .into_iter()
+        .flatten()
+        .filter(|offset| *offset > 0)
+        .min()
+        .unwrap_or_default() as usize;
+    if flags & NTLMSSP_NEGOTIATE_VERSION != 0 && message.len() >= 56 && payload_start >= 56 {
+        let (major, minor) = (message[48], message[49]);
+        let build = u16::from_le_bytes([message[50], message[51]]);
+        info.os_version = Some(format!("{}.{}.{}", major, minor, build));
+        info.os_name = WINDOWS_BUILDS
+            .iter()
+            .find(|(known_major, known_minor, known_build, _)| (*known_major, *known_minor, *known_build) == (major, minor, build))
+            .map(|(_, _, _, name)| name.to_string());
+    }
+
+    let length = read_u16(message, 40).unwrap_or_default() as usize;
+    let offset = read_u32(message, 44).unwrap_or_default() as usize;
+    let target_info = message
+        .get(offset..offset + length)
+        .ok_or_else(|| ReconError::check_failed("NTLM", "Target info outside the message"))?;
+
+    // AV_PAIR list: AvId (u16), AvLen (u16), value; terminated by MsvAvEOL
+    let mut position = 0;
+    while let (Some(id), Some(length)) = (read_u16(target_info, position), read_u16(target_info, position + 2)) {
+        let start = position + 4;
+        let Some(value) = target_info.get(start..start + length as usize) else {
+            break;
+        };
+        match id {
+            0 => break, // MsvAvEOL
+            1 => info.netbios_computer = decode_utf16(value),
+            2 => info.netbios_domain = decode_utf16(value),
+            3 => info.dns_computer = decode_utf16(value),
+            4 => info.dns_domain = decode_utf16(value),
+            5 => info.dns_forest = decode_utf16(value),
+            7 => {
+                info.timestamp = value
+                    .try_into()
+                    .ok()
+                    .map(u64::from_le_bytes)
+                    .and_then(filetime_to_rfc3339)
+            }
+            _ => {}
+        }
+        position = start + length as usize;
+    }
+    Ok(info)
+}
+
+fn read_u16(bytes: &[u8], offset: usize) -> Option<u16> {
+    bytes.get(offset..offset + 2).map(|b| u16::from_le_bytes([b[0], b[1]]))
+}
+
+fn read_u32(bytes: &[u8], offset: usize) -> Option<u32> {
+    bytes.get(offset..offset + 4).map(|b| u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+}
+
+fn decode_utf16(bytes: &[u8]) -> Option<String> {
+    let units: Vec<u16> = bytes.chunks_exact(2).map(|unit| u16::from_le_bytes([unit[0], unit[1]])).collect();
+    String::from_utf16(&units).ok().filter(|value| !value.is_empty())
+}
+
+/// Formats a Windows FILETIME (100 ns intervals since 1601) as an RFC 3339 UTC timestamp.
+fn filetime_to_rfc3339(filetime: u64) -> Option<String> {
+    let seconds = (filetime / 10_000_000).checked_sub(FILETIME_UNIX_OFFSET)?;
+    let (days, remainder) = ((seconds / 86_400) as i64, seconds % 86_400);
+
+    // Civil date from days since 1970-01-01 (Howard Hinnant's algorithm)
+    let z = days + 719_468;
+    let era = z.div_euclid(146_097);
+    let day_of_era = z.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1_460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    Some(format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        remainder / 3_600,
+        remainder % 3_600 / 60,
+        remainder % 60
+    ))
+}
+
+/// Names the service behind a URL from its path.
+fn service_for_url(url: &str) -> &'static str {
+    let path = Url::parse(url).map(|url| url.path().to_lowercase()).unwrap_or_default();
+    NTLM_PATHS
+        .iter()
+        .find(|(_, known)| path.starts_with(&known.to_lowercase()))
+        .map(|(service, _)| *service)
+        .unwrap_or("Exchange")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// CHALLENGE_MESSAGE from the MS-NLMP specification (section 4.2.4.3).
+    const SPEC_CHALLENGE: &[u8] = &[
+        0x4e, 0x54, 0x4c, 0x4d, 0x53, 0x53, 0x50, 0x00, 0x02, 0x00, 0x00, 0x00, 0x0c, 0x00, 0x0c, 0x00, //
+        0x38, 0x00, 0x00, 0x00, 0x33, 0x82, 0x8a, 0xe2, 0x01, 0x23, 0x45, 0x67, 0x89, 0xab, 0xcd, 0xef, //
+        0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x00, 0x24, 0x00, 0x24, 0x00, 0x44, 0x00, 0x00, 0x00, //
+        0x06, 0x00, 0x70, 0x17, 0x00, 0x00, 0x00, 0x0f, 0x53, 0x00, 0x65, 0x00, 0x72, 0x00, 0x76, 0x00, //
+        0x65, 0x00, 0x72, 0x00, 0x02, 0x00, 0x0c, 0x00, 0x44, 0x00, 0x6f, 0x00, 0x6d, 0x00, 0x61, 0x00, //
+        0x69, 0x00, 0x6e, 0x00, 0x01, 0x00, 0x0c, 0x00, 0x53, 0x00, 0x65, 0x00, 0x72, 0x00, 0x76, 0x00, //
+        0x65, 0x00, 0x72, 0x00, 0x00, 0x00, 0x00, 0x00,
+    ];
+
+    /// Challenge as sent by an Exchange 2019 server on Windows Server 2019, with contoso names.
+    const EXCHANGE_CHALLENGE: &str = "TlRMTVNTUAACAAAADgAOADgAAAAVgoriESIzRFVmd4gAAAAAAAAAAKIAogBGAAAACgBjRQAAAA9DAE8ATgBUAE8AUwBPAAIADgBDAE8ATgBUAE8AUwBPAAEADABFAFgAQwBIADAAMQAEACAAYwBvAHIAcAAuAGMAbwBuAHQAbwBzAG8ALgBjAG8AbQADAC4ARQBYAEMASAAwADEALgBjAG8AcgBwAC4AYwBvAG4AdABvAHMAbwAuAGMAbwBtAAUAFgBjAG8AbgB0AG8AcwBvAC4AYwBvAG0ABwAIAACE4MqdR9oBAAAAAA==";
+
+    #[test]
+    fn test_decode_spec_challenge() {
+        let info = decode_challenge(SPEC_CHALLENGE).unwrap();
+        assert_eq!(info.netbios_domain.as_deref(), Some("Domain"));
+        assert_eq!(info.netbios_computer.as_deref(), Some("Server"));
+        assert_eq!(info.dns_domain, None);
+        assert_eq!(info.os_version.as_deref(), Some("6.0.6000"));
+        assert_eq!(info.os_name, None);
+        assert_eq!(info.timestamp, None);
+    }
+
+    #[test]
+    fn test_decode_exchange_challenge() {
+        let info = decode_challenge(&STANDARD.decode(EXCHANGE_CHALLENGE).unwrap()).unwrap();
+        assert_eq!(info.netbios_domain.as_deref(), Some("CONTOSO"));
+        assert_eq!(info.netbios_computer.as_deref(), Some("EXCH01"));
+        assert_eq!(info.dns_domain.as_deref(), Some("corp.contoso.com"));
+        assert_eq!(info.dns_forest.as_deref(), Some("contoso.com"));
+        assert_eq!(info.dns_computer.as_deref(), Some("EXCH01.corp.contoso.com"));
+        assert_eq!(info.os_version.as_deref(), Some("10.0.17763"));
+        assert_eq!(info.os_name.as_deref(), Some("Windows Server 2019"));
+        assert_eq!(info.timestamp.as_deref(), Some("2024-01-15T10:30:00Z"));
+    }
+
+    #[test]
+    fn test_decode_rejects_malformed_messages() {
+        assert!(decode_challenge(&negotiate_message()).is_err()); // Type 1, not Type 2
+        assert!(decode_challenge(&SPEC_CHALLENGE[..40]).is_err());
+        let mut truncated = SPEC_CHALLENGE.to_vec();
+        truncated.truncate(80); // Target info runs past the end
+        assert!(decode_challenge(&truncated).is_err());
+        assert_eq!(
+            STANDARD.encode(negotiate_message()),
+            "TlRMTVNTUAABAAAAB4IIogAAAAAAAAAAAAAAAAAAAAAGAbEdAAAADw=="
+        );
+    }
+}
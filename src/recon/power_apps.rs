@@ -0,0 +1,179 @@
+use crate::config::CloudConfig;
+use crate::error::ReconError;
+use crate::models::{PowerPagesResults, PowerPagesSite};
+use crate::recon::common::tenant_label_candidates;
+use futures::future::join_all;
+use reqwest::header::SET_COOKIE;
+use reqwest::{Client, StatusCode, Url};
+use tracing::{debug, info, warn};
+
+/// Suffixes commonly appended to the organisation name in portal names.
+const PORTAL_NAME_SUFFIXES: &[&str] = &["", "-portal", "portal", "-support", "-customer"];
+
+/// Markers present on every Power Pages page: the analytics cookie or portal resource paths.
+const PORTAL_COOKIE: &str = "Dynamics365PortalAnalytics";
+const PORTAL_BODY_MARKERS: &[&str] = &["/_portal/", "/_layout/tokenhtml"];
+
+/// Tables requested through the Web API to test anonymous read access. `$top=0` keeps the
+/// probe from returning any rows.
+const WEB_API_TABLES: &[&str] = &["contacts", "accounts", "incidents", "leads"];
+
+/// Looks for Power Apps portals / Power Pages sites named after the tenant or domain and checks
+/// whether they expose table data anonymously.
+pub async fn check_power_apps(
+    client: Client,
+    domain: String,
+    tenant_name: Option<String>,
+    config: CloudConfig,
+) -> Result<PowerPagesResults, ReconError> {
+    let hosts: Vec<String> = power_pages_candidates(tenant_name.as_deref(), &domain)
+        .iter()
+        .flat_map(|name| config.power_pages_host_suffixes.iter().map(move |suffix| format!("{}{}", name, suffix)))
+        .collect();
+    debug!(domain = domain.as_str(), hosts = hosts.len(), "Checking Power Pages candidates");
+
+    let probes = hosts.iter().map(|host| probe_power_pages_site(&client, format!("https://{}/", host)));
+    let sites: Vec<PowerPagesSite> = join_all(probes).await.into_iter().flatten().collect();
+    for site in sites.iter().filter(|site| site.anonymous_data_access) {
+        warn!(
+            url = site.url.as_str(),
+            tables = ?site.exposed_tables,
+            feeds = ?site.odata_feeds,
+            "Power Pages site exposes data anonymously"
+        );
+    }
+    info!(domain = domain.as_str(), found = sites.len(), "Finished Power Pages checks");
+    Ok(PowerPagesResults {
+        candidates_tried: hosts,
+        sites,
+    })
+}
+
+/// Candidate portal names: the tenant name candidates, each with the suffixes organisations
+/// commonly add.
+fn power_pages_candidates(tenant_name: Option<&str>, domain: &str) -> Vec<String> {
+    let mut candidates: Vec<String> = Vec::new();
+    for base in tenant_label_candidates(tenant_name, domain) {
+        for suffix in PORTAL_NAME_SUFFIXES {
+            let candidate = format!("{}{}", base, suffix);
+            if !candidates.contains(&candidate) {
+                candidates.push(candidate);
+            }
+        }
+    }
+    candidates
+}
+
+/// Requests a candidate portal's home page and, when it is a Power Pages site, probes its
+/// `/_odata` feeds and `/_api/` Web API for anonymous access.
+pub async fn probe_power_pages_site(client: &Client, url: String) -> Option<PowerPagesSite> {
+    let site_host = Url::parse(&url).ok()?.host_str()?.to_lowercase();
+    let response = match client.get(&url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            debug!(url = url.as_str(), error = %e, "Power Pages request failed");
+            return None;
+        }
+    };
+    let on_site = response.url().host_str().is_some_and(|host| host.eq_ignore_ascii_case(&site_host));
+    let status = response.status();
+    let portal_cookie = response
+        .headers()
+        .get_all(SET_COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .any(|cookie| cookie.starts_with(PORTAL_COOKIE));
+    let body = response.text().await.unwrap_or_default();
+    if !on_site || status == StatusCode::NOT_FOUND || !(portal_cookie || PORTAL_BODY_MARKERS.iter().any(|marker| body.contains(marker))) {
+        return None;
+    }
+
+    let base = url.trim_end_matches('/');
+    let odata_feeds = fetch_odata_feeds(client, &format!("{}/_odata", base)).await;
+    let table_probes = WEB_API_TABLES.iter().map(|table| async move {
+        let table_url = format!("{}/_api/{}?$top=0", base, table);
+        let readable = match client.get(&table_url).send().await {
+            Ok(response) if response.status() == StatusCode::OK => response
+                .json::<serde_json::Value>()
+                .await
+                .is_ok_and(|body| body.get("value").is_some_and(serde_json::Value::is_array)),
+            _ => false,
+        };
+        readable.then(|| table.to_string())
+    });
+    let exposed_tables: Vec<String> = join_all(table_probes).await.into_iter().flatten().collect();
+
+    Some(PowerPagesSite {
+        display_name: html_title(&body),
+        anonymous_data_access: !odata_feeds.is_empty() || !exposed_tables.is_empty(),
+        url,
+        odata_feeds,
+        exposed_tables,
+    })
+}
+
+/// Lists the entity list feeds of a portal's OData service document (JSON or Atom).
+async fn fetch_odata_feeds(client: &Client, url: &str) -> Vec<String> {
+    let body = match client.get(url).send().await {
+        Ok(response) if response.status() == StatusCode::OK => response.text().await.unwrap_or_default(),
+        _ => return Vec::new(),
+    };
+    parse_odata_feeds(&body)
+}
+
+fn parse_odata_feeds(body: &str) -> Vec<String> {
+    if let Ok(document) = serde_json::from_str::<serde_json::Value>(body) {
+        return document
+            .get("value")
+            .and_then(serde_json::Value::as_array)
+            .map(|feeds| {
+                feeds
+                    .iter()
+                    .filter_map(|feed| feed.get("name").or_else(|| feed.get("url")))
+                    .filter_map(|name| name.as_str().map(str::to_string))
+                    .collect()
+            })
+            .unwrap_or_default();
+    }
+    // Atom service document: <collection href="Products"><atom:title>Products</atom:title></collection>
+    body.split("<collection")
+        .skip(1)
+        .filter_map(|collection| {
+            let href = collection.split("href=\"").nth(1)?;
+            href.split('"').next().filter(|href| !href.is_empty()).map(str::to_string)
+        })
+        .collect()
+}
+
+/// Text of the page's `<title>` element, if present.
+fn html_title(body: &str) -> Option<String> {
+    let lower = body.to_ascii_lowercase();
+    let start = lower.find("<title")?;
+    let content_start = start + lower[start..].find('>')? + 1;
+    let content_end = content_start + lower[content_start..].find("</title>")?;
+    let title = body[content_start..content_end].split_whitespace().collect::<Vec<_>>().join(" ");
+    (!title.is_empty()).then_some(title)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_pages_candidates() {
+        let candidates = power_pages_candidates(Some("contosoltd.onmicrosoft.com"), "contoso-corp.co.uk");
+        assert_eq!(&candidates[..2], ["contosoltd", "contosoltd-portal"]);
+        assert!(candidates.contains(&"contosocorp".to_string()));
+        assert!(candidates.contains(&"contoso-corp-support".to_string()));
+        assert_eq!(power_pages_candidates(None, "contoso.com").len(), PORTAL_NAME_SUFFIXES.len());
+    }
+
+    #[test]
+    fn test_parse_odata_feeds_and_title() {
+        let json = r#"{"odata.metadata":"https://contoso.powerappsportals.com/_odata/$metadata","value":[{"name":"Products","url":"Products"}]}"#;
+        assert_eq!(parse_odata_feeds(json), vec!["Products"]);
+        let atom = r#"<service><workspace><atom:title>Default</atom:title><collection href="Cases"><atom:title>Cases</atom:title></collection></workspace></service>"#;
+        assert_eq!(parse_odata_feeds(atom), vec!["Cases"]);
+        assert_eq!(html_title("<html><head><TITLE>\n  Home · Contoso Support\n</TITLE>"), Some("Home · Contoso Support".to_string()));
+    }
+}
@@ -0,0 +1,281 @@
+use crate::config::CloudConfig;
+use crate::error::ReconError;
+use crate::models::{DeviceRegistrationDiscovery, DnsResults, HostingType, MdmDiscovery, ProvisioningResults};
+use crate::recon::common::{NDES_HOST_LABELS, PROBE_USER};
+use crate::recon::dns::classify_hosting;
+use crate::recon::legacy_auth::self_hosted_hosts;
+use crate::recon::tenant::extract_xml_tag_value;
+use futures::future::join_all;
+use reqwest::header::CONTENT_TYPE;
+use reqwest::{Client, StatusCode, Url};
+use serde::Deserialize;
+use tracing::{debug, info};
+
+/// MDM discovery path on `enterpriseenrollment.<domain>`, as requested by Windows during enrollment.
+const MDM_DISCOVERY_PATH: &str = "/EnrollmentServer/Discovery.svc";
+
+/// Device registration service contract path, relative to the DRS host (and domain, in the cloud).
+const DRS_CONTRACT_PATH: &str = "/enrollmentserver/contract?api-version=1.4";
+
+/// Host name suffixes of the Intune enrollment service across clouds.
+const INTUNE_HOST_SUFFIXES: &[&str] = &[".manage.microsoft.com", ".manage.microsoft.us", ".manage.microsoftonline.cn"];
+
+/// DRS contract, e.g. `{"DeviceRegistrationService":{"RegistrationEndpoint":"..."},"IdentityProviderService":{"Federated":false,...}}`.
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DrsContract {
+    device_registration_service: Option<DrsService>,
+    identity_provider_service: Option<DrsIdentityProvider>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DrsService {
+    registration_endpoint: Option<String>,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "PascalCase")]
+struct DrsIdentityProvider {
+    federated: Option<bool>,
+    passive_auth_endpoint: Option<String>,
+}
+
+/// Checks the Intune/MDM enrollment and Windows device registration endpoints of the domain.
+///
+/// `enterpriseenrollment.<domain>` and `enterpriseregistration.<domain>` are classified by where
+/// they point, the MDM discovery service is asked about a synthetic address, and the device
+/// registration contract is requested from the cloud DRS (and a self-hosted one, if published).
+pub async fn check_provisioning_endpoints(
+    client: Client,
+    domain: String,
+    config: CloudConfig,
+    dns_results: Option<DnsResults>,
+) -> Result<ProvisioningResults, ReconError> {
+    let enrollment_host = format!("enterpriseenrollment.{}", domain);
+    let registration_host = format!("enterpriseregistration.{}", domain);
+    let mut results = ProvisioningResults {
+        enrollment_target: dns_target(dns_results.as_ref(), &enrollment_host),
+        registration_target: dns_target(dns_results.as_ref(), &registration_host),
+        ..Default::default()
+    };
+    let is_intune = |host: &str| INTUNE_HOST_SUFFIXES.iter().any(|suffix| host.trim_end_matches('.').ends_with(suffix));
+
+    // MDM discovery: the domain's own endpoint first, then the cloud service for Intune-pointed domains
+    let address = format!("{}@{}", PROBE_USER, domain);
+    if results.enrollment_target.is_some() {
+        let url = format!("https://{}{}", enrollment_host, MDM_DISCOVERY_PATH);
+        results.mdm_discovery = query_mdm_discovery(&client, &url, &address).await;
+    }
+    let enrollment_to_intune = results.enrollment_target.as_deref().is_some_and(is_intune);
+    if results.mdm_discovery.is_none() && enrollment_to_intune && !config.mdm_discovery_url.is_empty() {
+        results.mdm_discovery = query_mdm_discovery(&client, &config.mdm_discovery_url, &address).await;
+    }
+    let enrollment_service_host = results
+        .mdm_discovery
+        .as_ref()
+        .and_then(|discovery| discovery.enrollment_service_url.as_deref())
+        .and_then(|url| Url::parse(url).ok())
+        .and_then(|url| url.host_str().map(str::to_lowercase));
+    results.intune_auto_enrollment = enrollment_to_intune || enrollment_service_host.as_deref().is_some_and(is_intune);
+
+    // Device registration: a self-hosted DRS (AD FS) answers on the domain's own name
+    let registration_on_premises = results
+        .registration_target
+        .as_deref()
+        .is_some_and(|target| is_self_hosted_registration(target, &config.device_registration_host));
+    if registration_on_premises {
+        let url = format!("https://{}{}", registration_host, DRS_CONTRACT_PATH);
+        results.device_registration = query_drs_contract(&client, &url, true).await;
+    }
+    if results.device_registration.is_none() && !config.device_registration_host.is_empty() {
+        let url = format!("https://{}/{}{}", config.device_registration_host, domain, DRS_CONTRACT_PATH);
+        results.device_registration = query_drs_contract(&client, &url, false).await;
+    }
+
+    let ndes_probes = self_hosted_hosts(&domain, dns_results.as_ref(), NDES_HOST_LABELS)
+        .into_iter()
+        .map(|host| probe_ndes(&client, format!("https://{}/certsrv/mscep/mscep.dll", host)));
+    results.ndes_endpoints = join_all(ndes_probes).await.into_iter().flatten().collect();
+
+    let self_hosted_mdm = enrollment_service_host.is_some_and(|host| !is_intune(&host) && classify_hosting(&host) == HostingType::OnPremises);
+    results.on_premises_mdm = self_hosted_mdm || !results.ndes_endpoints.is_empty();
+    // The cloud contract answers for any verified domain; the DNS record or a self-hosted DRS shows deliberate setup
+    results.hybrid_join_infrastructure = results.registration_target.is_some()
+        || results.device_registration.as_ref().is_some_and(|registration| registration.on_premises);
+
+    info!(
+        domain = domain.as_str(),
+        intune = results.intune_auto_enrollment,
+        on_premises_mdm = results.on_premises_mdm,
+        device_registration = results.hybrid_join_infrastructure,
+        "Finished provisioning endpoint checks"
+    );
+    Ok(results)
+}
+
+/// Final CNAME target of `hostname`, or the name itself when it resolves without a CNAME.
+fn dns_target(dns_results: Option<&DnsResults>, hostname: &str) -> Option<String> {
+    let dns = dns_results?;
+    let chain_target = dns
+        .cname_chains
+        .as_deref()
+        .unwrap_or_default()
+        .iter()
+        .find(|chain| chain.hostname.eq_ignore_ascii_case(hostname) && !chain.nxdomain)
+        .and_then(|chain| chain.chain.last().cloned());
+    chain_target.or_else(|| {
+        dns.resolved_hosts
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .find(|host| host.hostname.eq_ignore_ascii_case(hostname) && !(host.ipv4.is_empty() && host.ipv6.is_empty()))
+            .map(|host| host.hostname.clone())
+    })
+}
+
+/// Whether `enterpriseregistration.<domain>` points at a self-hosted DRS rather than the cloud one.
+/// An empty `drs_host` (not configured for this cloud) rules nothing out.
+fn is_self_hosted_registration(target: &str, drs_host: &str) -> bool {
+    let cloud_target = !drs_host.is_empty() && target.trim_end_matches('.').ends_with(drs_host);
+    classify_hosting(target) == HostingType::OnPremises && !cloud_target
+}
+
+/// Sends an MS-MDE2 Discover request for `address` and reads the enrollment endpoints from the answer.
+pub async fn query_mdm_discovery(client: &Client, url: &str, address: &str) -> Option<MdmDiscovery> {
+    let body = format!(
+        concat!(
+            r#"<s:Envelope xmlns:a="http://www.w3.org/2005/08/addressing" xmlns:s="http://www.w3.org/2003/05/soap-envelope">"#,
+            "<s:Header>",
+            r#"<a:Action s:mustUnderstand="1">http://schemas.microsoft.com/windows/management/2012/01/enrollment/IDiscoveryService/Discover</a:Action>"#,
+            "<a:MessageID>urn:uuid:748132ec-a575-4329-b01b-6171a9cf8478</a:MessageID>",
+            "<a:ReplyTo><a:Address>http://www.w3.org/2005/08/addressing/anonymous</a:Address></a:ReplyTo>",
+            r#"<a:To s:mustUnderstand="1">{url}</a:To>"#,
+            "</s:Header>",
+            "<s:Body>",
+            r#"<Discover xmlns="http://schemas.microsoft.com/windows/management/2012/01/enrollment">"#,
+            r#"<request xmlns:i="http://www.w3.org/2001/XMLSchema-instance">"#,
+            "<EmailAddress>{address}</EmailAddress>",
+            "<RequestVersion>4.0</RequestVersion>",
+            "<DeviceType>CIMClient_Windows</DeviceType>",
+            "<ApplicationVersion>10.0.19045.0</ApplicationVersion>",
+            "<OSEdition>48</OSEdition>",
+            "<AuthPolicies><AuthPolicy>OnPremise</AuthPolicy><AuthPolicy>Federated</AuthPolicy></AuthPolicies>",
+            "</request></Discover>",
+            "</s:Body></s:Envelope>"
+        ),
+        url = url,
+        address = address
+    );
+    debug!(url, "Querying MDM discovery service");
+    let response = match client
+        .post(url)
+        .header(CONTENT_TYPE, "application/soap+xml; charset=utf-8")
+        .body(body)
+        .send()
+        .await
+    {
+        Ok(response) if response.status() == StatusCode::OK => response,
+        Ok(response) => {
+            debug!(url, status = %response.status(), "MDM discovery service did not answer");
+            return None;
+        }
+        Err(e) => {
+            debug!(url, error = %e, "MDM discovery request failed");
+            return None;
+        }
+    };
+    let xml = response.text().await.ok()?;
+    let discovery = MdmDiscovery {
+        url: url.to_string(),
+        auth_policy: extract_xml_tag_value(&xml, "AuthPolicy"),
+        enrollment_service_url: extract_xml_tag_value(&xml, "EnrollmentServiceUrl"),
+        authentication_service_url: extract_xml_tag_value(&xml, "AuthenticationServiceUrl"),
+    };
+    (discovery.auth_policy.is_some() || discovery.enrollment_service_url.is_some()).then_some(discovery)
+}
+
+/// Requests a device registration service contract.
+pub async fn query_drs_contract(client: &Client, url: &str, on_premises: bool) -> Option<DeviceRegistrationDiscovery> {
+    debug!(url, "Querying device registration contract");
+    let contract = match client.get(url).send().await {
+        Ok(response) if response.status() == StatusCode::OK => response.json::<DrsContract>().await.ok()?,
+        Ok(response) => {
+            debug!(url, status = %response.status(), "Device registration contract not available");
+            return None;
+        }
+        Err(e) => {
+            debug!(url, error = %e, "Device registration request failed");
+            return None;
+        }
+    };
+    let registration_endpoint = contract.device_registration_service.and_then(|service| service.registration_endpoint);
+    registration_endpoint.as_ref()?;
+    let identity_provider = contract.identity_provider_service;
+    Some(DeviceRegistrationDiscovery {
+        url: url.to_string(),
+        on_premises,
+        registration_endpoint,
+        federated: identity_provider.as_ref().and_then(|provider| provider.federated),
+        passive_auth_endpoint: identity_provider.and_then(|provider| provider.passive_auth_endpoint),
+    })
+}
+
+/// Returns the NDES URL when it answers a SCEP GetCACaps request.
+async fn probe_ndes(client: &Client, url: String) -> Option<String> {
+    let response = client.get(&url).query(&[("operation", "GetCACaps")]).send().await.ok()?;
+    if response.status() != StatusCode::OK {
+        return None;
+    }
+    let body = response.text().await.ok()?;
+    is_scep_capabilities(&body).then_some(url)
+}
+
+/// Whether a body is a SCEP capabilities list (e.g., `POSTPKIOperation\nSHA-256\nRenewal`).
+fn is_scep_capabilities(body: &str) -> bool {
+    const SCEP_CAPABILITIES: &[&str] = &["POSTPKIOperation", "Renewal", "SHA-1", "SHA-256", "SHA-512", "DES3", "AES", "GetNextCACert"];
+    let lines: Vec<&str> = body.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+    !lines.is_empty() && lines.iter().all(|line| SCEP_CAPABILITIES.iter().any(|capability| capability.eq_ignore_ascii_case(line)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::CnameChain;
+
+    #[test]
+    fn test_dns_target() {
+        let dns = DnsResults {
+            cname_chains: Some(vec![CnameChain {
+                hostname: "enterpriseenrollment.contoso.com".to_string(),
+                chain: vec![
+                    "enterpriseenrollment.contoso.com".to_string(),
+                    "enterpriseenrollment-s.manage.microsoft.com".to_string(),
+                ],
+                addresses: vec!["52.168.117.168".to_string()],
+                nxdomain: false,
+                loop_detected: false,
+            }]),
+            ..Default::default()
+        };
+        assert_eq!(
+            dns_target(Some(&dns), "enterpriseenrollment.contoso.com").as_deref(),
+            Some("enterpriseenrollment-s.manage.microsoft.com")
+        );
+        assert_eq!(dns_target(Some(&dns), "enterpriseregistration.contoso.com"), None);
+    }
+
+    #[test]
+    fn test_is_self_hosted_registration() {
+        assert!(is_self_hosted_registration("adfs.contoso.com", "enterpriseregistration.windows.net"));
+        assert!(is_self_hosted_registration("adfs.contoso.com", ""));
+        assert!(!is_self_hosted_registration("enterpriseregistration.windows.net", "enterpriseregistration.windows.net"));
+    }
+
+    #[test]
+    fn test_is_scep_capabilities() {
+        assert!(is_scep_capabilities("POSTPKIOperation\r\nRenewal\r\nSHA-256\r\nAES\r\n"));
+        assert!(!is_scep_capabilities("<html>Default Web Site</html>"));
+        assert!(!is_scep_capabilities(""));
+    }
+}
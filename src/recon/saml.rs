@@ -0,0 +1,280 @@
+use crate::config::CloudConfig;
+use crate::error::ReconError;
+use crate::models::{FederationInfo, FederationMetadataResults, SamlResults, SsoEndpoint, WsTrustEndpoint, WsTrustKind};
+use futures::future::join_all;
+use quick_xml::events::Event;
+use quick_xml::Reader;
+use reqwest::{Client, StatusCode, Url};
+use tracing::{debug, info, warn};
+
+/// Federation metadata document path, on the login host (after the tenant) and on AD FS.
+const FEDERATION_METADATA_PATH: &str = "/FederationMetadata/2007-06/FederationMetadata.xml";
+
+/// AD FS IdP-initiated sign-on page, disabled by default since AD FS 2016.
+const IDP_INITIATED_SIGNON_PATH: &str = "/adfs/ls/idpinitiatedsignon.aspx";
+
+/// Element IDs present on the IdP-initiated sign-on page only when it is enabled.
+const IDP_INITIATED_SIGNON_MARKERS: &[&str] = &["idp_SignInButton", "idp_SignInThisSiteRadioButton", "idp_RelyingPartyDropDownList"];
+
+/// AD FS WS-Trust metadata exchange path, used when GetUserRealm does not return one.
+const MEX_PATH: &str = "/adfs/services/trust/mex";
+
+/// AD FS WS-Trust endpoints: (path, kind, WS-Trust version).
+const WS_TRUST_PATHS: &[(&str, WsTrustKind, &str)] = &[
+    ("/adfs/services/trust/2005/usernamemixed", WsTrustKind::UsernameMixed, "2005"),
+    ("/adfs/services/trust/13/usernamemixed", WsTrustKind::UsernameMixed, "13"),
+    ("/adfs/services/trust/2005/windowstransport", WsTrustKind::WindowsTransport, "2005"),
+    ("/adfs/services/trust/13/windowstransport", WsTrustKind::WindowsTransport, "13"),
+];
+
+/// Checks the SAML2 and WS-Federation endpoints Entra ID advertises for the tenant and, for
+/// federated domains, the identity provider's metadata, IdP-initiated sign-on and WS-Trust endpoints.
+pub async fn check_saml_endpoints(
+    client: Client,
+    domain: String,
+    config: CloudConfig,
+    tenant_id: Option<String>,
+    federation_info: Option<FederationInfo>,
+) -> Result<SamlResults, ReconError> {
+    let tenant = tenant_id.unwrap_or_else(|| domain.clone());
+    let entra_url = format!("{}/{}{}", config.login_endpoint.trim_end_matches('/'), tenant, FEDERATION_METADATA_PATH);
+    let mut results = SamlResults {
+        entra_metadata: fetch_federation_metadata(&client, &entra_url).await,
+        ..Default::default()
+    };
+
+    let federated = federation_info
+        .as_ref()
+        .filter(|federation| federation.name_space_type.as_deref() == Some("Federated"));
+    let idp_host = federated
+        .and_then(|federation| federation.auth_url.as_deref())
+        .and_then(|auth_url| Url::parse(auth_url).ok())
+        .and_then(|url| url.host_str().map(str::to_lowercase));
+    if let Some(host) = &idp_host {
+        let base_url = format!("https://{}", host);
+        results.idp_metadata = fetch_federation_metadata(&client, &format!("{}{}", base_url, FEDERATION_METADATA_PATH)).await;
+        let mex_url = federated.and_then(|federation| federation.mex_url.as_deref());
+        let (idp_initiated_signon, ws_trust_endpoints) = check_adfs_endpoints(&client, &base_url, mex_url).await;
+        results.idp_initiated_signon = idp_initiated_signon;
+        results.ws_trust_endpoints = ws_trust_endpoints;
+    }
+    results.idp_host = idp_host;
+    results.usernamemixed_exposed = results
+        .ws_trust_endpoints
+        .iter()
+        .any(|endpoint| endpoint.kind == WsTrustKind::UsernameMixed && endpoint.exposed);
+
+    if results.usernamemixed_exposed {
+        warn!(
+            domain = domain.as_str(),
+            idp = results.idp_host.as_deref().unwrap_or_default(),
+            "WS-Trust usernamemixed endpoint exposed (password spraying)"
+        );
+    }
+    info!(domain = domain.as_str(), "Finished SAML/WS-Federation checks");
+    Ok(results)
+}
+
+/// Fetches a federation metadata document and lists its SAML2 and WS-Federation sign-on endpoints.
+pub async fn fetch_federation_metadata(client: &Client, url: &str) -> Option<FederationMetadataResults> {
+    debug!(url, "Fetching federation metadata");
+    let xml = match client.get(url).send().await {
+        Ok(response) if response.status() == StatusCode::OK => response.text().await.ok()?,
+        Ok(response) => {
+            debug!(url, status = %response.status(), "Federation metadata not available");
+            return None;
+        }
+        Err(e) => {
+            debug!(url, error = %e, "Federation metadata request failed");
+            return None;
+        }
+    };
+    let (entity_id, endpoints) = parse_federation_metadata(&xml)?;
+    Some(FederationMetadataResults {
+        url: url.to_string(),
+        entity_id,
+        endpoints,
+    })
+}
+
+/// Reads the entity ID, SAML `SingleSignOnService` bindings and WS-Fed `PassiveRequestorEndpoint`
+/// addresses from federation metadata. `None` when the document is not metadata.
+fn parse_federation_metadata(xml: &str) -> Option<(Option<String>, Vec<SsoEndpoint>)> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut entity_id = None;
+    let mut endpoints: Vec<SsoEndpoint> = Vec::new();
+    let mut is_metadata = false;
+    let mut in_passive_endpoint = false;
+    let mut in_address = false;
+
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) | Ok(Event::Empty(element)) => match element.local_name().as_ref() {
+                b"EntityDescriptor" => {
+                    is_metadata = true;
+                    entity_id = attribute(&element, b"entityID");
+                }
+                b"SingleSignOnService" => {
+                    if let Some(location) = attribute(&element, b"Location") {
+                        let binding = attribute(&element, b"Binding").map(|binding| binding.rsplit(':').next().unwrap_or_default().to_string());
+                        push_endpoint(&mut endpoints, "SAML2", binding, location);
+                    }
+                }
+                b"PassiveRequestorEndpoint" => in_passive_endpoint = true,
+                b"Address" => in_address = in_passive_endpoint,
+                _ => {}
+            },
+            Ok(Event::End(element)) => match element.local_name().as_ref() {
+                b"PassiveRequestorEndpoint" => in_passive_endpoint = false,
+                b"Address" => in_address = false,
+                _ => {}
+            },
+            Ok(Event::Text(text)) if in_address => {
+                if let Ok(location) = text.unescape() {
+                    push_endpoint(&mut endpoints, "WS-Fed", None, location.into_owned());
+                }
+            }
+            Ok(Event::Eof) => break,
+            Err(e) => {
+                debug!(error = %e, "Invalid federation metadata");
+                return None;
+            }
+            _ => {}
+        }
+    }
+    is_metadata.then_some((entity_id, endpoints))
+}
+
+fn attribute(element: &quick_xml::events::BytesStart, name: &[u8]) -> Option<String> {
+    element
+        .attributes()
+        .flatten()
+        .find(|attribute| attribute.key.local_name().as_ref() == name)
+        .and_then(|attribute| attribute.unescape_value().ok().map(|value| value.into_owned()))
+}
+
+fn push_endpoint(endpoints: &mut Vec<SsoEndpoint>, protocol: &str, binding: Option<String>, location: String) {
+    let endpoint = SsoEndpoint {
+        protocol: protocol.to_string(),
+        binding,
+        location,
+    };
+    if !endpoints.contains(&endpoint) {
+        endpoints.push(endpoint);
+    }
+}
+
+/// Checks an AD FS host under `base_url` for IdP-initiated sign-on and exposed WS-Trust endpoints.
+///
+/// An endpoint is exposed when it answers an unauthenticated request and, if the metadata
+/// exchange document could be read, is listed in it.
+pub async fn check_adfs_endpoints(client: &Client, base_url: &str, mex_url: Option<&str>) -> (Option<bool>, Vec<WsTrustEndpoint>) {
+    let base_url = base_url.trim_end_matches('/');
+    let idp_initiated_signon = match client.get(format!("{}{}", base_url, IDP_INITIATED_SIGNON_PATH)).send().await {
+        Ok(response) => {
+            let body = response.text().await.unwrap_or_default();
+            Some(IDP_INITIATED_SIGNON_MARKERS.iter().any(|marker| body.contains(marker)))
+        }
+        Err(e) => {
+            debug!(base_url, error = %e, "IdP-initiated sign-on request failed");
+            None
+        }
+    };
+
+    let mex_url = mex_url.map(str::to_string).unwrap_or_else(|| format!("{}{}", base_url, MEX_PATH));
+    let mex_addresses = match client.get(&mex_url).send().await {
+        Ok(response) if response.status() == StatusCode::OK => Some(parse_mex_addresses(&response.text().await.unwrap_or_default())),
+        _ => None,
+    };
+
+    let probes = WS_TRUST_PATHS.iter().map(|(path, kind, version)| {
+        let url = format!("{}{}", base_url, path);
+        let listed_in_mex = mex_addresses
+            .as_deref()
+            .unwrap_or_default()
+            .iter()
+            .any(|address| address.to_lowercase().ends_with(path));
+        let mex_read = mex_addresses.is_some();
+        async move {
+            let status = client.get(&url).send().await.ok().map(|response| response.status());
+            let answered = status.is_some_and(|status| {
+                status != StatusCode::NOT_FOUND && status != StatusCode::FORBIDDEN && !status.is_server_error()
+            });
+            WsTrustEndpoint {
+                url,
+                kind: kind.clone(),
+                version: version.to_string(),
+                listed_in_mex,
+                status: status.map(|status| status.as_u16()),
+                exposed: answered && (listed_in_mex || !mex_read),
+            }
+        }
+    });
+    (idp_initiated_signon, join_all(probes).await)
+}
+
+/// Endpoint addresses listed in a WS-Trust metadata exchange (WSDL) document.
+fn parse_mex_addresses(xml: &str) -> Vec<String> {
+    let mut reader = Reader::from_str(xml);
+    reader.config_mut().trim_text(true);
+    let mut addresses: Vec<String> = Vec::new();
+    let mut in_address = false;
+    loop {
+        match reader.read_event() {
+            Ok(Event::Start(element)) => in_address = element.local_name().as_ref() == b"Address",
+            Ok(Event::Empty(element)) if element.local_name().as_ref() == b"address" => {
+                // soap12:address location="..." inside wsdl:port
+                addresses.extend(attribute(&element, b"location"));
+            }
+            Ok(Event::End(_)) => in_address = false,
+            Ok(Event::Text(text)) if in_address => addresses.extend(text.unescape().ok().map(|address| address.into_owned())),
+            Ok(Event::Eof) | Err(_) => break,
+            _ => {}
+        }
+    }
+    addresses.dedup();
+    addresses
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_federation_metadata() {
+        let xml = concat!(
+            r#"<EntityDescriptor xmlns="urn:oasis:names:tc:SAML:2.0:metadata" entityID="http://sts.contoso.com/adfs/services/trust">"#,
+            r#"<RoleDescriptor xmlns:fed="http://docs.oasis-open.org/wsfed/federation/200706" xsi:type="fed:SecurityTokenServiceType" xmlns:xsi="http://www.w3.org/2001/XMLSchema-instance">"#,
+            r#"<fed:PassiveRequestorEndpoint><wsa:EndpointReference xmlns:wsa="http://www.w3.org/2005/08/addressing">"#,
+            "<wsa:Address>https://sts.contoso.com/adfs/ls/</wsa:Address>",
+            "</wsa:EndpointReference></fed:PassiveRequestorEndpoint></RoleDescriptor>",
+            r#"<IDPSSODescriptor protocolSupportEnumeration="urn:oasis:names:tc:SAML:2.0:protocol">"#,
+            r#"<SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-Redirect" Location="https://sts.contoso.com/adfs/ls/"/>"#,
+            r#"<SingleSignOnService Binding="urn:oasis:names:tc:SAML:2.0:bindings:HTTP-POST" Location="https://sts.contoso.com/adfs/ls/"/>"#,
+            "</IDPSSODescriptor></EntityDescriptor>"
+        );
+        let (entity_id, endpoints) = parse_federation_metadata(xml).unwrap();
+        assert_eq!(entity_id.as_deref(), Some("http://sts.contoso.com/adfs/services/trust"));
+        let summary: Vec<(&str, Option<&str>)> =
+            endpoints.iter().map(|endpoint| (endpoint.protocol.as_str(), endpoint.binding.as_deref())).collect();
+        assert_eq!(
+            summary,
+            vec![("WS-Fed", None), ("SAML2", Some("HTTP-Redirect")), ("SAML2", Some("HTTP-POST"))]
+        );
+        assert!(parse_federation_metadata("<html><body>Not Found</body></html>").is_none());
+    }
+
+    #[test]
+    fn test_parse_mex_addresses() {
+        let xml = concat!(
+            r#"<wsdl:definitions xmlns:wsdl="http://schemas.xmlsoap.org/wsdl/" xmlns:soap12="http://schemas.xmlsoap.org/wsdl/soap12/">"#,
+            r#"<wsdl:service name="SecurityTokenService"><wsdl:port name="UserNameWSTrustBinding_IWSTrust13Async">"#,
+            r#"<soap12:address location="https://sts.contoso.com/adfs/services/trust/13/usernamemixed"/>"#,
+            r#"<wsa10:EndpointReference xmlns:wsa10="http://www.w3.org/2005/08/addressing">"#,
+            "<wsa10:Address>https://sts.contoso.com/adfs/services/trust/13/usernamemixed</wsa10:Address>",
+            "</wsa10:EndpointReference></wsdl:port></wsdl:service></wsdl:definitions>"
+        );
+        assert_eq!(parse_mex_addresses(xml), vec!["https://sts.contoso.com/adfs/services/trust/13/usernamemixed"]);
+    }
+}
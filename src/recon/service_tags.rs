@@ -0,0 +1,315 @@
+use crate::error::ReconError;
+use crate::recon::common::{unique_hostnames, QueryLimiter, MAX_CONCURRENT_LOOKUPS};
+use crate::recon::geoip::GeoIpDatabases;
+use crate::models::{ResolvedHost, ServiceTagMatch};
+use futures::stream::{self, StreamExt};
+use serde::Deserialize;
+use std::net::IpAddr;
+use std::path::Path;
+use std::sync::Arc;
+use tracing::{debug, info};
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Service tag name covering all of Azure; only reported when nothing more specific matches.
+const AZURE_CLOUD_TAG: &str = "AzureCloud";
+
+/// Azure service tags file (`ServiceTags_Public_*.json`).
+#[derive(Debug, Deserialize)]
+struct ServiceTagsFile {
+    values: Vec<ServiceTagValue>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ServiceTagValue {
+    name: String,
+    properties: ServiceTagProperties,
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ServiceTagProperties {
+    #[serde(default)]
+    region: String,
+    #[serde(default)]
+    system_service: String,
+    #[serde(default)]
+    address_prefixes: Vec<String>,
+}
+
+/// Entry of the Microsoft 365 endpoints file (`endpoints.office.com/endpoints/worldwide`).
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct M365EndpointSet {
+    service_area: String,
+    service_area_display_name: Option<String>,
+    #[serde(default)]
+    ips: Vec<String>,
+}
+
+/// An IPv4 or IPv6 network in CIDR notation.
+#[derive(Debug, Clone, PartialEq)]
+struct IpNetwork {
+    address: IpAddr,
+    prefix_len: u8,
+}
+
+impl IpNetwork {
+    fn parse(cidr: &str) -> Option<Self> {
+        let (address, prefix_len) = match cidr.split_once('/') {
+            Some((address, prefix_len)) => (address.parse::<IpAddr>().ok()?, prefix_len.parse::<u8>().ok()?),
+            None => {
+                let address = cidr.parse::<IpAddr>().ok()?;
+                (address, if address.is_ipv4() { 32 } else { 128 })
+            }
+        };
+        let max = if address.is_ipv4() { 32 } else { 128 };
+        (prefix_len <= max).then_some(Self { address, prefix_len })
+    }
+
+    fn contains(&self, ip: &IpAddr) -> bool {
+        match (self.address, ip) {
+            (IpAddr::V4(network), IpAddr::V4(ip)) => {
+                let mask = u32::MAX.checked_shl(32 - u32::from(self.prefix_len)).unwrap_or(0);
+                u32::from(network) & mask == u32::from(*ip) & mask
+            }
+            (IpAddr::V6(network), IpAddr::V6(ip)) => {
+                let mask = u128::MAX.checked_shl(128 - u32::from(self.prefix_len)).unwrap_or(0);
+                u128::from(network) & mask == u128::from(*ip) & mask
+            }
+            _ => false,
+        }
+    }
+}
+
+/// A published range with the tag it belongs to.
+#[derive(Debug, Clone)]
+struct TaggedRange {
+    network: IpNetwork,
+    prefix: String,
+    tag: String,
+    system_service: Option<String>,
+    region: Option<String>,
+}
+
+/// IP ranges from Azure service tag and Microsoft 365 endpoint files.
+#[derive(Debug, Clone, Default)]
+pub struct ServiceTags {
+    ranges: Vec<TaggedRange>,
+}
+
+impl ServiceTags {
+    /// Adds the ranges of a service tags or M365 endpoints JSON document.
+    pub fn add_json(&mut self, json: &str) -> Result<(), ReconError> {
+        if let Ok(file) = serde_json::from_str::<ServiceTagsFile>(json) {
+            for value in file.values {
+                let properties = value.properties;
+                for prefix in properties.address_prefixes {
+                    self.push(
+                        prefix,
+                        &value.name,
+                        non_empty(properties.system_service.clone()),
+                        non_empty(properties.region.clone()),
+                    );
+                }
+            }
+            return Ok(());
+        }
+        let endpoint_sets: Vec<M365EndpointSet> = serde_json::from_str(json)?;
+        for set in endpoint_sets {
+            let tag = set.service_area_display_name.unwrap_or_else(|| set.service_area.clone());
+            for prefix in set.ips {
+                self.push(prefix, &tag, Some(set.service_area.clone()), None);
+            }
+        }
+        Ok(())
+    }
+
+    fn push(&mut self, prefix: String, tag: &str, system_service: Option<String>, region: Option<String>) {
+        if let Some(network) = IpNetwork::parse(&prefix) {
+            self.ranges.push(TaggedRange {
+                network,
+                prefix,
+                tag: tag.to_string(),
+                system_service,
+                region,
+            });
+        }
+    }
+
+    /// Finds the service tags containing `ip`, regional tags first.
+    ///
+    /// `AzureCloud` tags are dropped when a service tag matches; the global tags of a
+    /// service (and M365 endpoint sets, which have no region) are kept next to regional ones.
+    pub fn lookup(&self, ip: IpAddr) -> Vec<ServiceTagMatch> {
+        let mut matches: Vec<&TaggedRange> = self.ranges.iter().filter(|range| range.network.contains(&ip)).collect();
+        if matches.iter().any(|range| !is_azure_cloud(range)) {
+            matches.retain(|range| !is_azure_cloud(range));
+        }
+        matches.sort_by_key(|range| range.region.is_none());
+
+        let mut results: Vec<ServiceTagMatch> = Vec::new();
+        for range in matches {
+            if results.iter().any(|existing| existing.tag == range.tag) {
+                continue;
+            }
+            results.push(ServiceTagMatch {
+                address: ip.to_string(),
+                tag: range.tag.clone(),
+                system_service: range.system_service.clone(),
+                region: range.region.clone(),
+                prefix: range.prefix.clone(),
+            });
+        }
+        results
+    }
+}
+
+/// Loads service tag / M365 endpoint JSON files downloaded by the user.
+pub fn load_service_tags<P: AsRef<Path>>(paths: &[P]) -> Result<ServiceTags, ReconError> {
+    let mut tags = ServiceTags::default();
+    for path in paths {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .map_err(|e| ReconError::cli_error(format!("Cannot read service tags {}: {}", path.display(), e)))?;
+        tags.add_json(&json)
+            .map_err(|e| ReconError::cli_error(format!("Invalid service tags {}: {}", path.display(), e)))?;
+        debug!(path = %path.display(), ranges = tags.ranges.len(), "Loaded service tags");
+    }
+    Ok(tags)
+}
+
+/// Resolves IPv4 and IPv6 addresses for each host name, attributes them to service tags
+/// and adds ASN/location from the MMDB databases.
+///
+/// Host names that do not resolve are omitted. Queries are paced by `limiter`.
+pub async fn resolve_hosts(
+    resolver: TokioAsyncResolver,
+    hostnames: Vec<String>,
+    limiter: Arc<QueryLimiter>,
+    service_tags: Arc<ServiceTags>,
+    geoip: Arc<GeoIpDatabases>,
+) -> Result<Vec<ResolvedHost>, ReconError> {
+    let lookups = stream::iter(unique_hostnames(hostnames)).map(|hostname| {
+        let resolver = resolver.clone();
+        let limiter = limiter.clone();
+        let service_tags = service_tags.clone();
+        let geoip = geoip.clone();
+        async move {
+            limiter.wait().await;
+            let ipv4: Vec<IpAddr> = match resolver.ipv4_lookup(hostname.as_str()).await {
+                Ok(response) => response.iter().map(|a| IpAddr::V4(a.0)).collect(),
+                Err(_) => Vec::new(),
+            };
+            limiter.wait().await;
+            let ipv6: Vec<IpAddr> = match resolver.ipv6_lookup(hostname.as_str()).await {
+                Ok(response) => response.iter().map(|aaaa| IpAddr::V6(aaaa.0)).collect(),
+                Err(_) => Vec::new(),
+            };
+            if ipv4.is_empty() && ipv6.is_empty() {
+                return None;
+            }
+            let service_tag_matches = ipv4
+                .iter()
+                .chain(ipv6.iter())
+                .flat_map(|ip| service_tags.lookup(*ip))
+                .collect();
+            let enrichment = ipv4.iter().chain(ipv6.iter()).filter_map(|ip| geoip.lookup(*ip)).collect();
+            Some(ResolvedHost {
+                hostname,
+                ipv4: ipv4.iter().map(|ip| ip.to_string()).collect(),
+                ipv6: ipv6.iter().map(|ip| ip.to_string()).collect(),
+                service_tag_matches,
+                enrichment,
+            })
+        }
+    });
+
+    let resolved: Vec<ResolvedHost> = lookups
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .filter_map(|resolved| async move { resolved })
+        .collect()
+        .await;
+    info!(
+        hosts = resolved.len(),
+        attributed = resolved.iter().filter(|host| !host.service_tag_matches.is_empty()).count(),
+        "Finished host resolution"
+    );
+    Ok(resolved)
+}
+
+fn is_azure_cloud(range: &TaggedRange) -> bool {
+    range.tag == AZURE_CLOUD_TAG || range.tag.starts_with("AzureCloud.")
+}
+
+fn non_empty(value: String) -> Option<String> {
+    (!value.is_empty()).then_some(value)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SERVICE_TAGS_JSON: &str = r#"{
+        "changeNumber": 1,
+        "cloud": "Public",
+        "values": [
+            {"name": "AzureCloud", "id": "AzureCloud", "properties": {"region": "", "systemService": "", "addressPrefixes": ["13.69.0.0/16", "2603:1020::/32"]}},
+            {"name": "AppService", "id": "AppService", "properties": {"region": "", "systemService": "AzureAppService", "addressPrefixes": ["13.69.68.0/23"]}},
+            {"name": "AppService.WestEurope", "id": "AppService.WestEurope", "properties": {"region": "westeurope", "systemService": "AzureAppService", "addressPrefixes": ["13.69.68.0/23", "2603:1020:206::/48"]}},
+            {"name": "AzureFrontDoor.Frontend", "id": "AzureFrontDoor.Frontend", "properties": {"region": "", "systemService": "AzureFrontDoor", "addressPrefixes": ["13.107.246.0/24"]}}
+        ]
+    }"#;
+
+    const M365_ENDPOINTS_JSON: &str = r#"[
+        {"id": 1, "serviceArea": "Exchange", "serviceAreaDisplayName": "Exchange Online", "ips": ["40.92.0.0/15", "2a01:111:f400::/48"], "tcpPorts": "25"}
+    ]"#;
+
+    #[test]
+    fn test_ip_network_contains() {
+        let v4 = IpNetwork::parse("13.69.68.0/23").unwrap();
+        assert!(v4.contains(&"13.69.69.255".parse().unwrap()));
+        assert!(!v4.contains(&"13.69.70.0".parse().unwrap()));
+        let v6 = IpNetwork::parse("2603:1020:206::/48").unwrap();
+        assert!(v6.contains(&"2603:1020:206:1::10".parse().unwrap()));
+        assert!(!v6.contains(&"13.69.68.1".parse().unwrap()));
+        assert!(IpNetwork::parse("0.0.0.0/0").unwrap().contains(&"192.0.2.1".parse().unwrap()));
+        assert_eq!(IpNetwork::parse("10.0.0.0/33"), None);
+    }
+
+    #[test]
+    fn test_lookup_orders_regional_tags_first() {
+        let mut tags = ServiceTags::default();
+        tags.add_json(SERVICE_TAGS_JSON).unwrap();
+        tags.add_json(M365_ENDPOINTS_JSON).unwrap();
+
+        let app_service = tags.lookup("13.69.68.10".parse().unwrap());
+        assert_eq!(app_service.len(), 2);
+        assert_eq!(app_service[0].tag, "AppService.WestEurope");
+        assert_eq!(app_service[0].region.as_deref(), Some("westeurope"));
+        assert_eq!(app_service[1].tag, "AppService");
+        assert_eq!(app_service[1].region, None);
+
+        let front_door = tags.lookup("13.107.246.40".parse().unwrap());
+        assert_eq!(front_door[0].tag, "AzureFrontDoor.Frontend");
+        assert_eq!(front_door[0].region, None);
+
+        assert_eq!(tags.lookup("13.69.200.1".parse().unwrap())[0].tag, "AzureCloud");
+        assert_eq!(tags.lookup("2a01:111:f400::5".parse().unwrap())[0].tag, "Exchange Online");
+        assert!(tags.lookup("192.0.2.1".parse().unwrap()).is_empty());
+    }
+
+    #[test]
+    fn test_lookup_keeps_global_m365_match_next_to_regional_tag() {
+        let mut tags = ServiceTags::default();
+        tags.add_json(M365_ENDPOINTS_JSON).unwrap();
+        tags.add_json(
+            r#"{"values": [{"name": "AzureCloud.westeurope", "properties": {"region": "westeurope", "systemService": "", "addressPrefixes": ["40.92.0.0/16"]}},
+                           {"name": "AzureConnectors.WestEurope", "properties": {"region": "westeurope", "systemService": "AzureConnectors", "addressPrefixes": ["40.92.4.0/24"]}}]}"#,
+        )
+        .unwrap();
+
+        let matches = tags.lookup("40.92.4.5".parse().unwrap());
+        let names: Vec<&str> = matches.iter().map(|found| found.tag.as_str()).collect();
+        assert_eq!(names, vec!["AzureConnectors.WestEurope", "Exchange Online"]);
+    }
+}
@@ -0,0 +1,343 @@
+use crate::error::ReconError;
+use crate::models::{SmtpCertificate, SmtpProbeResult};
+use futures::future::join_all;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufStream};
+use tokio::net::TcpStream;
+use tokio::time::timeout;
+use tokio_rustls::rustls::client::danger::{HandshakeSignatureValid, ServerCertVerified, ServerCertVerifier};
+use tokio_rustls::rustls::crypto::{ring, verify_tls12_signature, verify_tls13_signature, CryptoProvider};
+use tokio_rustls::rustls::pki_types::{CertificateDer, ServerName, UnixTime};
+use tokio_rustls::rustls::{ClientConfig, DigitallySignedStruct, SignatureScheme};
+use tokio_rustls::TlsConnector;
+use tracing::{debug, info};
+use x509_parser::extensions::GeneralName;
+use x509_parser::prelude::{FromDer, X509Certificate};
+
+/// Name announced in our EHLO command.
+const EHLO_NAME: &str = "msft-recon-rs.invalid";
+
+/// Standard SMTP port used for MX hosts.
+pub const SMTP_PORT: u16 = 25;
+
+/// Message submission port, where clients use SMTP AUTH.
+pub const SUBMISSION_PORT: u16 = 587;
+
+/// DNS suffixes that only make sense inside an organization's network.
+const INTERNAL_SUFFIXES: &[&str] = &[".local", ".lan", ".internal", ".corp", ".intra", ".localdomain", ".ad"];
+
+/// Probes each host on the SMTP port concurrently.
+pub async fn probe_smtp_hosts(hosts: Vec<String>, port: u16, probe_timeout: Duration) -> Result<Vec<SmtpProbeResult>, ReconError> {
+    let probes = hosts.iter().map(|host| probe_smtp_host(host, port, probe_timeout));
+    Ok(join_all(probes).await)
+}
+
+/// Connects to an SMTP server, records its banner and EHLO capabilities, and
+/// negotiates STARTTLS to capture the TLS parameters and certificate.
+///
+/// Failures are reported in the result rather than as errors, so one unreachable
+/// host does not hide the others.
+pub async fn probe_smtp_host(host: &str, port: u16, probe_timeout: Duration) -> SmtpProbeResult {
+    let host = host.trim_end_matches('.').to_lowercase();
+    let mut result = SmtpProbeResult {
+        host: host.clone(),
+        port,
+        ..Default::default()
+    };
+    debug!(host = host.as_str(), port, "Probing SMTP server");
+
+    match timeout(probe_timeout, run_session(&host, port, &mut result)).await {
+        Ok(Ok(())) => info!(host = host.as_str(), starttls = result.starttls_offered, "SMTP probe finished"),
+        Ok(Err(e)) => {
+            debug!(host = host.as_str(), error = %e, "SMTP probe failed");
+            result.error = Some(e.to_string());
+        }
+        Err(_) => result.error = Some("Timed out".to_string()),
+    }
+    result
+}
+
+/// Runs the SMTP dialogue, filling in `result` as information becomes available.
+async fn run_session(host: &str, port: u16, result: &mut SmtpProbeResult) -> Result<(), ReconError> {
+    let tcp = TcpStream::connect((host, port)).await?;
+    result.connected = true;
+    let mut stream = BufStream::new(tcp);
+
+    let (code, banner_lines) = read_reply(&mut stream).await?;
+    let banner = banner_lines.join(" ");
+    if code != 220 {
+        return Err(ReconError::check_failed("SMTP Probe", format!("Unexpected greeting: {} {}", code, banner)));
+    }
+    result.banner_hostname = banner.split_whitespace().next().map(str::to_string);
+    result.software = identify_software(&banner);
+    result.banner = Some(banner);
+
+    let ehlo_lines = send_command(&mut stream, &format!("EHLO {}", EHLO_NAME)).await?;
+    // The first line of the EHLO reply is the server's greeting, e.g. "mx.contoso.com Hello [192.0.2.1]"
+    if let Some(greeting_host) = ehlo_lines.first().and_then(|line| line.split_whitespace().next()) {
+        result.leaks_internal_hostname = is_internal_hostname(greeting_host);
+    }
+    if let Some(banner_host) = &result.banner_hostname {
+        result.leaks_internal_hostname |= is_internal_hostname(banner_host);
+    }
+    result.capabilities = ehlo_lines.iter().skip(1).map(|line| line.to_string()).collect();
+    result.starttls_offered = result
+        .capabilities
+        .iter()
+        .any(|capability| capability.eq_ignore_ascii_case("STARTTLS"));
+
+    if !result.starttls_offered {
+        let _ = send_command(&mut stream, "QUIT").await;
+        return Ok(());
+    }
+
+    let (code, lines) = {
+        stream.write_all(b"STARTTLS\r\n").await?;
+        stream.flush().await?;
+        read_reply(&mut stream).await?
+    };
+    if code != 220 {
+        return Err(ReconError::check_failed("SMTP Probe", format!("STARTTLS refused: {} {}", code, lines.join(" "))));
+    }
+
+    let connector = TlsConnector::from(Arc::new(tls_client_config()?));
+    let server_name = ServerName::try_from(host.to_string())
+        .map_err(|e| ReconError::check_failed("SMTP Probe", e.to_string()))?;
+    let mut tls = connector.connect(server_name, stream.into_inner()).await?;
+
+    let (_, connection) = tls.get_ref();
+    result.tls_version = connection.protocol_version().map(|version| format!("{:?}", version));
+    result.tls_cipher = connection
+        .negotiated_cipher_suite()
+        .map(|suite| format!("{:?}", suite.suite()));
+    result.certificate = connection
+        .peer_certificates()
+        .and_then(|certificates| certificates.first())
+        .and_then(|certificate| parse_certificate(certificate.as_ref()));
+
+    let _ = tls.write_all(b"QUIT\r\n").await;
+    let _ = tls.shutdown().await;
+    Ok(())
+}
+
+/// Returns the SASL mechanisms a submission server advertises in its EHLO `AUTH` line.
+///
+/// STARTTLS is negotiated first when offered, as servers such as Exchange Online only
+/// advertise AUTH over TLS.
+pub async fn submission_auth_mechanisms(host: &str, port: u16) -> Result<Vec<String>, ReconError> {
+    let tcp = TcpStream::connect((host, port)).await?;
+    let mut stream = BufStream::new(tcp);
+    let (code, banner) = read_reply(&mut stream).await?;
+    if code != 220 {
+        return Err(ReconError::check_failed("SMTP AUTH", format!("Unexpected greeting: {} {}", code, banner.join(" "))));
+    }
+
+    let ehlo_lines = send_command(&mut stream, &format!("EHLO {}", EHLO_NAME)).await?;
+    if !ehlo_lines.iter().any(|line| line.eq_ignore_ascii_case("STARTTLS")) {
+        let _ = send_command(&mut stream, "QUIT").await;
+        return Ok(auth_mechanisms(&ehlo_lines));
+    }
+    send_command(&mut stream, "STARTTLS").await?;
+
+    let connector = TlsConnector::from(Arc::new(tls_client_config()?));
+    let server_name = ServerName::try_from(host.to_string())
+        .map_err(|e| ReconError::check_failed("SMTP AUTH", e.to_string()))?;
+    let mut tls = BufStream::new(connector.connect(server_name, stream.into_inner()).await?);
+    let ehlo_lines = send_command(&mut tls, &format!("EHLO {}", EHLO_NAME)).await?;
+    let _ = send_command(&mut tls, "QUIT").await;
+    Ok(auth_mechanisms(&ehlo_lines))
+}
+
+/// Extracts the mechanisms from an EHLO reply, e.g. "AUTH LOGIN XOAUTH2" (or the obsolete "AUTH=LOGIN").
+fn auth_mechanisms(ehlo_lines: &[String]) -> Vec<String> {
+    let mut mechanisms: Vec<String> = Vec::new();
+    for line in ehlo_lines {
+        let upper = line.to_uppercase();
+        let Some(rest) = upper.strip_prefix("AUTH").filter(|rest| rest.starts_with([' ', '='])) else {
+            continue;
+        };
+        for mechanism in rest[1..].split_whitespace() {
+            if !mechanisms.iter().any(|existing| existing == mechanism) {
+                mechanisms.push(mechanism.to_string());
+            }
+        }
+    }
+    mechanisms
+}
+
+/// Sends a command and returns the reply lines, failing on a non-2xx reply.
+async fn send_command<S>(stream: &mut S, command: &str) -> Result<Vec<String>, ReconError>
+where
+    S: AsyncBufRead + AsyncWrite + Unpin,
+{
+    stream.write_all(format!("{}\r\n", command).as_bytes()).await?;
+    stream.flush().await?;
+    let (code, lines) = read_reply(stream).await?;
+    if !(200..300).contains(&code) {
+        return Err(ReconError::check_failed("SMTP Probe", format!("{} rejected: {} {}", command, code, lines.join(" "))));
+    }
+    Ok(lines)
+}
+
+/// Reads a (possibly multi-line) SMTP reply, returning the code and the text of each line.
+async fn read_reply<S>(stream: &mut S) -> Result<(u16, Vec<String>), ReconError>
+where
+    S: AsyncBufRead + AsyncRead + Unpin,
+{
+    let mut lines = Vec::new();
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await? == 0 {
+            return Err(ReconError::check_failed("SMTP Probe", "Connection closed by server"));
+        }
+        let line = line.trim_end();
+        let code = line
+            .get(..3)
+            .and_then(|code| code.parse::<u16>().ok())
+            .ok_or_else(|| ReconError::check_failed("SMTP Probe", format!("Malformed reply: {}", line)))?;
+        lines.push(line.get(4..).unwrap_or("").to_string());
+        // "250-" continues a multi-line reply, "250 " ends it
+        if line.as_bytes().get(3) != Some(&b'-') {
+            return Ok((code, lines));
+        }
+    }
+}
+
+/// Guesses the MTA software from its banner.
+fn identify_software(banner: &str) -> Option<String> {
+    let lower = banner.to_lowercase();
+    let software = if lower.contains("microsoft esmtp mail service") {
+        "Microsoft Exchange"
+    } else if lower.contains("postfix") {
+        "Postfix"
+    } else if lower.contains("exim") {
+        "Exim"
+    } else if lower.contains("sendmail") {
+        "Sendmail"
+    } else if lower.contains("mimecast") {
+        "Mimecast"
+    } else if lower.contains("pphosted") || lower.contains("proofpoint") {
+        "Proofpoint"
+    } else {
+        return None;
+    };
+    Some(software.to_string())
+}
+
+/// Checks whether a host name looks like an internal Active Directory or LAN name.
+fn is_internal_hostname(name: &str) -> bool {
+    let name = name.trim_end_matches('.').to_lowercase();
+    // A single label (NetBIOS-style) or a private suffix
+    (!name.contains('.') && !name.starts_with('[')) || INTERNAL_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+}
+
+/// Extracts the fields we report from a DER-encoded certificate.
+fn parse_certificate(der: &[u8]) -> Option<SmtpCertificate> {
+    let (_, certificate) = X509Certificate::from_der(der).ok()?;
+    let subject_alt_names = match certificate.subject_alternative_name() {
+        Ok(Some(extension)) => extension
+            .value
+            .general_names
+            .iter()
+            .filter_map(|name| match name {
+                GeneralName::DNSName(dns) => Some(dns.to_string()),
+                GeneralName::IPAddress(ip) => Some(format!("{:?}", ip)),
+                _ => None,
+            })
+            .collect(),
+        _ => Vec::new(),
+    };
+    let subject = certificate.subject().to_string();
+    let issuer = certificate.issuer().to_string();
+
+    Some(SmtpCertificate {
+        self_signed: subject == issuer,
+        expired: !certificate.validity().is_valid(),
+        not_before: certificate.validity().not_before.to_string(),
+        not_after: certificate.validity().not_after.to_string(),
+        subject,
+        issuer,
+        subject_alt_names,
+    })
+}
+
+/// Builds a TLS client configuration that accepts any certificate.
+///
+/// We are recording what the server presents, not deciding whether to trust it.
+pub fn tls_client_config() -> Result<ClientConfig, ReconError> {
+    let provider = Arc::new(ring::default_provider());
+    let config = ClientConfig::builder_with_provider(provider.clone())
+        .with_safe_default_protocol_versions()
+        .map_err(|e| ReconError::check_failed("SMTP Probe", e.to_string()))?
+        .dangerous()
+        .with_custom_certificate_verifier(Arc::new(AcceptAnyCertificate(provider)))
+        .with_no_client_auth();
+    Ok(config)
+}
+
+/// Certificate verifier that records nothing and accepts everything.
+#[derive(Debug)]
+struct AcceptAnyCertificate(Arc<CryptoProvider>);
+
+impl ServerCertVerifier for AcceptAnyCertificate {
+    fn verify_server_cert(
+        &self,
+        _end_entity: &CertificateDer<'_>,
+        _intermediates: &[CertificateDer<'_>],
+        _server_name: &ServerName<'_>,
+        _ocsp_response: &[u8],
+        _now: UnixTime,
+    ) -> Result<ServerCertVerified, tokio_rustls::rustls::Error> {
+        Ok(ServerCertVerified::assertion())
+    }
+
+    fn verify_tls12_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls12_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn verify_tls13_signature(
+        &self,
+        message: &[u8],
+        cert: &CertificateDer<'_>,
+        dss: &DigitallySignedStruct,
+    ) -> Result<HandshakeSignatureValid, tokio_rustls::rustls::Error> {
+        verify_tls13_signature(message, cert, dss, &self.0.signature_verification_algorithms)
+    }
+
+    fn supported_verify_schemes(&self) -> Vec<SignatureScheme> {
+        self.0.signature_verification_algorithms.supported_schemes()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_internal_hostname_and_software() {
+        assert!(is_internal_hostname("EXCH01.corp.contoso.local"));
+        assert!(is_internal_hostname("EXCH01"));
+        assert!(!is_internal_hostname("mail.contoso.com"));
+        assert_eq!(
+            identify_software("EXCH01.corp.contoso.local Microsoft ESMTP MAIL Service ready at Mon, 1 Jan 2024").as_deref(),
+            Some("Microsoft Exchange")
+        );
+        assert_eq!(identify_software("mx.contoso.com ESMTP"), None);
+    }
+
+    #[test]
+    fn test_auth_mechanisms() {
+        let ehlo: Vec<String> = ["smtp.contoso.com Hello", "SIZE 35882577", "AUTH LOGIN XOAUTH2", "AUTH=LOGIN", "8BITMIME"]
+            .iter()
+            .map(|line| line.to_string())
+            .collect();
+        assert_eq!(auth_mechanisms(&ehlo), vec!["LOGIN", "XOAUTH2"]);
+        assert!(auth_mechanisms(&ehlo[..2]).is_empty());
+    }
+}
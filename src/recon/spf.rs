@@ -0,0 +1,465 @@
+use crate::error::ReconError;
+use crate::models::{SpfAnalysis, SpfNode, SpfQualifier};
+use futures::future::{BoxFuture, FutureExt};
+use std::collections::HashSet;
+use std::net::IpAddr;
+use tracing::{debug, info};
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Maximum number of DNS-querying terms per evaluation (RFC 7208, section 4.6.4).
+const SPF_LOOKUP_LIMIT: u32 = 10;
+/// Maximum number of lookups returning no answer per evaluation (RFC 7208, section 4.6.4).
+const SPF_VOID_LOOKUP_LIMIT: u32 = 2;
+/// Hard stop so that looping or hostile records cannot make us query indefinitely.
+const MAX_TOTAL_LOOKUPS: u32 = 50;
+/// Maximum number of MX hosts expanded for a single `mx` mechanism (RFC 7208, section 4.6.4).
+const MAX_MX_HOSTS: usize = 10;
+
+/// A single parsed SPF term.
+#[derive(Debug, Clone, PartialEq)]
+enum SpfTerm {
+    Mechanism {
+        qualifier: SpfQualifier,
+        name: String,
+        value: Option<String>,
+    },
+    Modifier {
+        name: String,
+        value: String,
+    },
+}
+
+/// How a domain relates to the part of the tree evaluated so far.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum Visit {
+    First,
+    /// Already evaluated through another include (e.g., a diamond); still counts toward the limit.
+    Repeated,
+    /// Already on the current include path, so evaluating it again would never end.
+    Loop,
+}
+
+/// Bookkeeping shared across the whole recursive evaluation.
+#[derive(Debug, Default)]
+struct EvalState {
+    lookups: u32,
+    void_lookups: u32,
+    uses_ptr: bool,
+    seen: HashSet<String>,
+    path: Vec<String>,
+    repeated: Vec<String>,
+}
+
+impl EvalState {
+    /// Counts a DNS-querying term and reports whether we are still allowed to query.
+    fn count_lookup(&mut self) -> bool {
+        self.lookups += 1;
+        self.lookups <= MAX_TOTAL_LOOKUPS
+    }
+
+    /// Records that `domain` is being evaluated; unless it is a loop, it stays on the current
+    /// include path until [`EvalState::leave`].
+    fn enter(&mut self, domain: &str) -> Visit {
+        let domain = domain.to_lowercase();
+        if self.path.contains(&domain) {
+            return Visit::Loop;
+        }
+        let visit = if self.seen.insert(domain.clone()) {
+            Visit::First
+        } else {
+            if !self.repeated.contains(&domain) {
+                self.repeated.push(domain.clone());
+            }
+            Visit::Repeated
+        };
+        self.path.push(domain);
+        visit
+    }
+
+    fn leave(&mut self) {
+        self.path.pop();
+    }
+}
+
+/// Recursively evaluates the SPF policy of `domain`.
+///
+/// Expands `include:`, `redirect=`, `a`, `mx`, `ptr` and `exists`, counting DNS lookups
+/// against the RFC 7208 limit and flagging weak `all` qualifiers.
+/// Returns `None` if the domain publishes no SPF record.
+pub async fn analyze_spf(
+    resolver: TokioAsyncResolver,
+    domain: String,
+    exchange_include: String, // e.g., spf.protection.outlook.com for the selected cloud
+) -> Result<Option<SpfAnalysis>, ReconError> {
+    debug!(domain = domain.as_str(), "Evaluating SPF policy");
+    let mut state = EvalState::default();
+    let tree = evaluate(&resolver, domain.clone(), None, &mut state).await;
+
+    if tree.record.is_none() {
+        debug!(domain = domain.as_str(), "No SPF record to evaluate");
+        return Ok(None);
+    }
+
+    let all_qualifier = effective_all_qualifier(&tree);
+    let mut weaknesses = Vec::new();
+    match all_qualifier {
+        Some(SpfQualifier::Pass) => weaknesses.push("'+all' authorizes any host to send mail for the domain".to_string()),
+        Some(SpfQualifier::Neutral) => weaknesses.push("'?all' gives no protection against spoofing".to_string()),
+        Some(SpfQualifier::SoftFail) => weaknesses.push("'~all' only soft-fails unauthorized senders".to_string()),
+        Some(SpfQualifier::Fail) => {}
+        None => weaknesses.push("No 'all' mechanism; unmatched senders receive a neutral result".to_string()),
+    }
+    if state.lookups > SPF_LOOKUP_LIMIT {
+        weaknesses.push(format!(
+            "{} DNS lookups exceed the RFC 7208 limit of {} (permerror)",
+            state.lookups, SPF_LOOKUP_LIMIT
+        ));
+    }
+    if state.void_lookups > SPF_VOID_LOOKUP_LIMIT {
+        weaknesses.push(format!(
+            "{} void lookups exceed the RFC 7208 limit of {} (permerror)",
+            state.void_lookups, SPF_VOID_LOOKUP_LIMIT
+        ));
+    }
+    if state.uses_ptr {
+        weaknesses.push("Uses the deprecated 'ptr' mechanism".to_string());
+    }
+    for repeated in &state.repeated {
+        weaknesses.push(format!(
+            "{} is included more than once; every include counts toward the lookup limit",
+            repeated
+        ));
+    }
+    collect_errors(&tree, &mut weaknesses);
+
+    let mut ip_ranges = Vec::new();
+    collect_ip_ranges(&tree, &mut ip_ranges);
+
+    let includes_exchange_online = if exchange_include.is_empty() {
+        None
+    } else {
+        Some(tree_contains_domain(&tree, &exchange_include))
+    };
+
+    info!(domain = domain.as_str(), lookups = state.lookups, "Finished SPF evaluation");
+    Ok(Some(SpfAnalysis {
+        dns_lookup_count: state.lookups,
+        void_lookup_count: state.void_lookups,
+        lookup_limit_exceeded: state.lookups > SPF_LOOKUP_LIMIT,
+        all_qualifier,
+        includes_exchange_online,
+        ip_ranges,
+        weaknesses,
+        tree,
+    }))
+}
+
+/// Evaluates the SPF record of a single domain, recursing into `include:` and `redirect=`.
+fn evaluate<'a>(
+    resolver: &'a TokioAsyncResolver,
+    domain: String,
+    reached_via: Option<String>,
+    state: &'a mut EvalState,
+) -> BoxFuture<'a, SpfNode> {
+    async move {
+        let mut node = SpfNode {
+            domain: domain.clone(),
+            reached_via,
+            ..Default::default()
+        };
+
+        if state.enter(&domain) == Visit::Loop {
+            node.error = Some("SPF include loop detected".to_string());
+            return node;
+        }
+
+        let records = fetch_spf_records(resolver, &domain).await;
+        let record = match records.as_slice() {
+            [] => {
+                state.void_lookups += 1;
+                state.leave();
+                node.error = Some("No SPF record found".to_string());
+                return node;
+            }
+            [record] => record.clone(),
+            [record, ..] => {
+                node.error = Some(format!("{} SPF records published (permerror)", records.len()));
+                record.clone()
+            }
+        };
+        node.record = Some(record.clone());
+
+        let mut redirect = None;
+        for term in parse_spf_record(&record) {
+            match term {
+                SpfTerm::Mechanism { qualifier, name, value } => match name.as_str() {
+                    "all" => node.all_qualifier = Some(qualifier),
+                    "ip4" | "ip6" => {
+                        if let Some(range) = value {
+                            node.ip_ranges.push(range);
+                        }
+                    }
+                    "include" => {
+                        let may_query = state.count_lookup();
+                        match value {
+                            Some(target) if may_query && !target.contains('%') => {
+                                let child = evaluate(resolver, target, Some("include".to_string()), state).await;
+                                node.children.push(child);
+                            }
+                            Some(target) if target.contains('%') => {
+                                debug!(domain = domain.as_str(), target = target.as_str(), "Skipping macro include");
+                            }
+                            _ => {}
+                        }
+                    }
+                    "a" | "mx" => {
+                        if !state.count_lookup() {
+                            continue;
+                        }
+                        let (host, v4_cidr, v6_cidr) = split_domain_cidr(value.as_deref());
+                        let host = host.unwrap_or(&domain).to_string();
+                        if host.contains('%') {
+                            continue;
+                        }
+                        let addresses = if name == "a" {
+                            resolve_host(resolver, &host).await
+                        } else {
+                            resolve_mx_hosts(resolver, &host).await
+                        };
+                        if addresses.is_empty() {
+                            state.void_lookups += 1;
+                        }
+                        node.ip_ranges.extend(addresses.into_iter().map(|ip| match (ip, v4_cidr, v6_cidr) {
+                            (IpAddr::V4(ip), Some(cidr), _) => format!("{}/{}", ip, cidr),
+                            (IpAddr::V6(ip), _, Some(cidr)) => format!("{}/{}", ip, cidr),
+                            (ip, _, _) => ip.to_string(),
+                        }));
+                    }
+                    "ptr" => {
+                        state.count_lookup();
+                        state.uses_ptr = true;
+                    }
+                    "exists" => {
+                        // Usually macro-based, so it cannot be expanded without a sender
+                        state.count_lookup();
+                    }
+                    _ => debug!(domain = domain.as_str(), mechanism = name.as_str(), "Unknown SPF mechanism"),
+                },
+                SpfTerm::Modifier { name, value } => {
+                    if name == "redirect" {
+                        redirect = Some(value);
+                    }
+                }
+            }
+        }
+
+        // `redirect=` only applies when the record has no `all` mechanism
+        if let (Some(target), None) = (redirect, node.all_qualifier.as_ref()) {
+            if state.count_lookup() && !target.contains('%') {
+                let child = evaluate(resolver, target, Some("redirect".to_string()), state).await;
+                node.children.push(child);
+            }
+        }
+
+        state.leave();
+        node
+    }
+    .boxed()
+}
+
+/// Returns all `v=spf1` TXT records published at `domain`.
+async fn fetch_spf_records(resolver: &TokioAsyncResolver, domain: &str) -> Vec<String> {
+    match resolver.txt_lookup(domain).await {
+        Ok(response) => response
+            .iter()
+            .map(|txt| txt.iter().map(|bytes| String::from_utf8_lossy(bytes)).collect::<String>())
+            .filter(|txt| is_spf_record(txt))
+            .collect(),
+        Err(e) => {
+            debug!(domain = domain, error = %e, "SPF TXT lookup failed");
+            Vec::new()
+        }
+    }
+}
+
+/// Resolves both A and AAAA records for a host.
+async fn resolve_host(resolver: &TokioAsyncResolver, host: &str) -> Vec<IpAddr> {
+    let mut addresses: Vec<IpAddr> = Vec::new();
+    if let Ok(response) = resolver.ipv4_lookup(host).await {
+        addresses.extend(response.iter().map(|a| IpAddr::V4(a.0)));
+    }
+    if let Ok(response) = resolver.ipv6_lookup(host).await {
+        addresses.extend(response.iter().map(|aaaa| IpAddr::V6(aaaa.0)));
+    }
+    addresses
+}
+
+/// Resolves the addresses of every MX host of `domain`.
+async fn resolve_mx_hosts(resolver: &TokioAsyncResolver, domain: &str) -> Vec<IpAddr> {
+    let exchanges: Vec<String> = match resolver.mx_lookup(domain).await {
+        Ok(response) => response.iter().take(MAX_MX_HOSTS).map(|mx| mx.exchange().to_string()).collect(),
+        Err(_) => return Vec::new(),
+    };
+    let mut addresses = Vec::new();
+    for exchange in exchanges {
+        addresses.extend(resolve_host(resolver, &exchange).await);
+    }
+    addresses
+}
+
+/// Checks whether a TXT record is an SPF version 1 record.
+fn is_spf_record(txt: &str) -> bool {
+    let lower = txt.trim().to_lowercase();
+    lower == "v=spf1" || lower.starts_with("v=spf1 ")
+}
+
+/// Splits an SPF record into its terms, skipping the version tag.
+fn parse_spf_record(record: &str) -> Vec<SpfTerm> {
+    record.split_whitespace().skip(1).filter_map(parse_term).collect()
+}
+
+/// Parses a single SPF term such as `~all`, `include:example.com` or `redirect=example.com`.
+fn parse_term(term: &str) -> Option<SpfTerm> {
+    let (qualifier, rest) = match term.chars().next()? {
+        '+' => (SpfQualifier::Pass, &term[1..]),
+        '-' => (SpfQualifier::Fail, &term[1..]),
+        '~' => (SpfQualifier::SoftFail, &term[1..]),
+        '?' => (SpfQualifier::Neutral, &term[1..]),
+        _ => (SpfQualifier::Pass, term),
+    };
+    let name_end = rest.find([':', '/', '=']).unwrap_or(rest.len());
+    let name = rest[..name_end].to_lowercase();
+    if name.is_empty() {
+        return None;
+    }
+
+    match rest[name_end..].chars().next() {
+        Some('=') => Some(SpfTerm::Modifier {
+            name,
+            value: rest[name_end + 1..].to_string(),
+        }),
+        Some(':') => Some(SpfTerm::Mechanism {
+            qualifier,
+            name,
+            value: Some(rest[name_end + 1..].to_string()),
+        }),
+        // CIDR-only form, e.g. `a/24` or `mx//64`
+        Some(_) => Some(SpfTerm::Mechanism {
+            qualifier,
+            name,
+            value: Some(rest[name_end..].to_string()),
+        }),
+        None => Some(SpfTerm::Mechanism { qualifier, name, value: None }),
+    }
+}
+
+/// Splits an `a`/`mx` argument such as `mail.example.com/24//64` into host and CIDR lengths.
+fn split_domain_cidr(value: Option<&str>) -> (Option<&str>, Option<&str>, Option<&str>) {
+    let Some(value) = value else {
+        return (None, None, None);
+    };
+    let (rest, v6) = value.split_once("//").unwrap_or((value, ""));
+    let (host, v4) = rest.split_once('/').unwrap_or((rest, ""));
+    fn non_empty(s: &str) -> Option<&str> {
+        if s.is_empty() { None } else { Some(s) }
+    }
+    (non_empty(host), non_empty(v4), non_empty(v6))
+}
+
+/// Determines the `all` qualifier that actually applies, following `redirect=`.
+fn effective_all_qualifier(node: &SpfNode) -> Option<SpfQualifier> {
+    node.all_qualifier.clone().or_else(|| {
+        node.children
+            .iter()
+            .find(|child| child.reached_via.as_deref() == Some("redirect"))
+            .and_then(effective_all_qualifier)
+    })
+}
+
+/// Collects the IP ranges of the whole tree, without duplicates.
+fn collect_ip_ranges(node: &SpfNode, ranges: &mut Vec<String>) {
+    for range in &node.ip_ranges {
+        if !ranges.contains(range) {
+            ranges.push(range.clone());
+        }
+    }
+    for child in &node.children {
+        collect_ip_ranges(child, ranges);
+    }
+}
+
+/// Collects evaluation errors (missing includes, loops, duplicates) from the tree.
+fn collect_errors(node: &SpfNode, errors: &mut Vec<String>) {
+    if let Some(error) = &node.error {
+        errors.push(format!("{}: {}", node.domain, error));
+    }
+    for child in &node.children {
+        collect_errors(child, errors);
+    }
+}
+
+/// Checks whether `domain` appears anywhere in the include/redirect tree.
+fn tree_contains_domain(node: &SpfNode, domain: &str) -> bool {
+    node.domain.eq_ignore_ascii_case(domain) || node.children.iter().any(|child| tree_contains_domain(child, domain))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_spf_terms() {
+        let terms = parse_spf_record("v=spf1 ip4:192.0.2.0/24 include:spf.protection.outlook.com mx/24 -all redirect=_spf.example.com");
+        assert_eq!(
+            terms,
+            vec![
+                SpfTerm::Mechanism { qualifier: SpfQualifier::Pass, name: "ip4".into(), value: Some("192.0.2.0/24".into()) },
+                SpfTerm::Mechanism { qualifier: SpfQualifier::Pass, name: "include".into(), value: Some("spf.protection.outlook.com".into()) },
+                SpfTerm::Mechanism { qualifier: SpfQualifier::Pass, name: "mx".into(), value: Some("/24".into()) },
+                SpfTerm::Mechanism { qualifier: SpfQualifier::Fail, name: "all".into(), value: None },
+                SpfTerm::Modifier { name: "redirect".into(), value: "_spf.example.com".into() },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_split_domain_cidr() {
+        assert_eq!(split_domain_cidr(None), (None, None, None));
+        assert_eq!(split_domain_cidr(Some("mail.example.com")), (Some("mail.example.com"), None, None));
+        assert_eq!(split_domain_cidr(Some("/24")), (None, Some("24"), None));
+        assert_eq!(split_domain_cidr(Some("mail.example.com/24//64")), (Some("mail.example.com"), Some("24"), Some("64")));
+        assert_eq!(split_domain_cidr(Some("//64")), (None, None, Some("64")));
+    }
+
+    #[test]
+    fn test_effective_all_follows_redirect() {
+        let tree = SpfNode {
+            domain: "example.com".into(),
+            children: vec![SpfNode {
+                domain: "_spf.example.com".into(),
+                reached_via: Some("redirect".into()),
+                all_qualifier: Some(SpfQualifier::SoftFail),
+                ..Default::default()
+            }],
+            ..Default::default()
+        };
+        assert_eq!(effective_all_qualifier(&tree), Some(SpfQualifier::SoftFail));
+        assert!(is_spf_record("v=spf1 -all"));
+        assert!(!is_spf_record("v=spf10 -all"));
+    }
+
+    #[test]
+    fn test_include_diamond_is_not_a_loop() {
+        let mut state = EvalState::default();
+        // example.com -> a.example.net -> shared.example.org, example.com -> b.example.net -> shared.example.org
+        assert_eq!(state.enter("example.com"), Visit::First);
+        assert_eq!(state.enter("a.example.net"), Visit::First);
+        assert_eq!(state.enter("shared.example.org"), Visit::First);
+        state.leave();
+        state.leave();
+        assert_eq!(state.enter("b.example.net"), Visit::First);
+        assert_eq!(state.enter("Shared.example.org"), Visit::Repeated);
+        assert_eq!(state.enter("example.com"), Visit::Loop);
+        assert_eq!(state.repeated, vec!["shared.example.org"]);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::error::ReconError;
+use crate::models::{DiscoveredSubdomain, DiscoverySource, SubdomainResults};
+use crate::recon::common::{random_u64, QueryLimiter, MAX_CONCURRENT_LOOKUPS};
+use crate::recon::ct::query_ct_logs;
+use crate::recon::dns::classify_hosting;
+use crate::recon::takeover::azure_service;
+use futures::stream::{self, StreamExt};
+use reqwest::Client;
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use trust_dns_resolver::proto::rr::RecordType;
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Microsoft-flavored wordlist bundled with the binary.
+const BUNDLED_WORDLIST: &str = include_str!("../../wordlists/subdomains.txt");
+
+/// Random labels resolved to detect wildcard zones.
+const WILDCARD_PROBES: usize = 3;
+
+/// What a single host name resolved to.
+#[derive(Debug, Default, PartialEq)]
+struct Resolution {
+    cname_target: Option<String>,
+    addresses: Vec<String>,
+}
+
+/// Parses a wordlist: one label per line, ignoring blank lines and `#` comments.
+pub fn parse_wordlist(contents: &str) -> Vec<String> {
+    contents
+        .lines()
+        .map(|line| line.trim().trim_end_matches('.').to_lowercase())
+        .filter(|line| !line.is_empty() && !line.starts_with('#'))
+        .collect()
+}
+
+/// Returns the labels of the bundled wordlist.
+pub fn bundled_wordlist() -> Vec<String> {
+    parse_wordlist(BUNDLED_WORDLIST)
+}
+
+/// Discovers subdomains by resolving `<word>.<domain>` for each word and, when
+/// `ct_log_url` is set, the names found in Certificate Transparency logs.
+/// Queries are paced by `limiter`, shared with the other per-host lookups of the run.
+///
+/// Random labels are resolved first; if the zone has a wildcard record, wordlist hosts
+/// that resolve to the wildcard's targets are dropped as false positives.
+pub async fn discover_subdomains(
+    client: Client,
+    resolver: TokioAsyncResolver,
+    domain: String,
+    words: Vec<String>,
+    ct_log_url: Option<String>,
+    limiter: Arc<QueryLimiter>,
+) -> Result<SubdomainResults, ReconError> {
+    let mut candidates: Vec<(String, DiscoverySource)> = Vec::new();
+    let mut ct_names_found = None;
+    let mut ct_error = None;
+    if let Some(base_url) = ct_log_url {
+        match query_ct_logs(client, base_url, domain.clone()).await {
+            Ok(names) => {
+                ct_names_found = Some(names.len());
+                candidates.extend(names.into_iter().map(|name| (name, DiscoverySource::CertificateTransparency)));
+            }
+            Err(e) => {
+                warn!(domain = domain.as_str(), error = %e, "Certificate Transparency query failed");
+                ct_error = Some(e.to_string());
+            }
+        }
+    }
+    let words_tried = words.len();
+    for word in words {
+        let hostname = format!("{}.{}", word, domain);
+        if !candidates.iter().any(|(existing, _)| *existing == hostname) {
+            candidates.push((hostname, DiscoverySource::Wordlist));
+        }
+    }
+    info!(domain = domain.as_str(), candidates = candidates.len(), "Starting subdomain discovery");
+
+    // Wildcard detection: random labels should not exist
+    let mut wildcard_targets: Vec<String> = Vec::new();
+    for _ in 0..WILDCARD_PROBES {
+        let probe = format!("{}.{}", random_label(), domain);
+        if let Some(resolution) = resolve_host(&resolver, &limiter, &probe).await {
+            debug!(domain = probe.as_str(), "Random label resolved: wildcard zone");
+            for target in resolution.cname_target.into_iter().chain(resolution.addresses) {
+                if !wildcard_targets.contains(&target) {
+                    wildcard_targets.push(target);
+                }
+            }
+        }
+    }
+    let wildcard_detected = !wildcard_targets.is_empty();
+
+    let resolved: Vec<(String, DiscoverySource, Resolution)> = stream::iter(candidates)
+        .map(|(hostname, source)| {
+            let resolver = resolver.clone();
+            let limiter = limiter.clone();
+            async move {
+                let resolution = resolve_host(&resolver, &limiter, &hostname).await;
+                resolution.map(|resolution| (hostname, source, resolution))
+            }
+        })
+        .buffer_unordered(MAX_CONCURRENT_LOOKUPS)
+        .filter_map(|found| async move { found })
+        .collect()
+        .await;
+
+    let mut filtered_wildcard_matches = 0;
+    let mut subdomains: Vec<DiscoveredSubdomain> = Vec::new();
+    for (hostname, source, resolution) in resolved {
+        // CT names come from real certificates, so only guessed names can be wildcard noise
+        if source == DiscoverySource::Wordlist && is_wildcard_match(&resolution, &wildcard_targets) {
+            filtered_wildcard_matches += 1;
+            continue;
+        }
+        let classified_name = resolution.cname_target.as_deref().unwrap_or(hostname.as_str());
+        subdomains.push(DiscoveredSubdomain {
+            azure_service: azure_service(classified_name).map(str::to_string),
+            hosting: classify_hosting(classified_name),
+            hostname,
+            source,
+            cname_target: resolution.cname_target,
+            addresses: resolution.addresses,
+        });
+    }
+    subdomains.sort_by(|a, b| a.hostname.cmp(&b.hostname));
+
+    info!(
+        domain = domain.as_str(),
+        found = subdomains.len(),
+        wildcard = wildcard_detected,
+        "Finished subdomain discovery"
+    );
+    Ok(SubdomainResults {
+        words_tried,
+        ct_names_found,
+        ct_error,
+        wildcard_detected,
+        wildcard_targets,
+        filtered_wildcard_matches,
+        subdomains,
+    })
+}
+
+/// Resolves a host name's CNAME and addresses, or `None` if it does not exist.
+///
+/// The CNAME is queried on its own so dangling CNAMEs (whose address lookup fails) are still found.
+async fn resolve_host(resolver: &TokioAsyncResolver, limiter: &QueryLimiter, hostname: &str) -> Option<Resolution> {
+    limiter.wait().await;
+    let cname_target = match resolver.lookup(hostname, RecordType::CNAME).await {
+        Ok(response) => response
+            .iter()
+            .find_map(|rdata| rdata.as_cname())
+            .map(|target| target.to_string().trim_end_matches('.').to_lowercase()),
+        Err(_) => None,
+    };
+
+    limiter.wait().await;
+    let addresses: Vec<String> = match resolver.lookup_ip(hostname).await {
+        Ok(response) => response.iter().map(|ip| ip.to_string()).collect(),
+        Err(e) => {
+            debug!(domain = hostname, error = %e, "Address lookup failed");
+            Vec::new()
+        }
+    };
+
+    if cname_target.is_none() && addresses.is_empty() {
+        return None;
+    }
+    Some(Resolution { cname_target, addresses })
+}
+
+/// Checks whether a resolution only points at the zone's wildcard targets.
+fn is_wildcard_match(resolution: &Resolution, wildcard_targets: &[String]) -> bool {
+    if wildcard_targets.is_empty() {
+        return false;
+    }
+    match &resolution.cname_target {
+        Some(target) => wildcard_targets.contains(target),
+        None => resolution.addresses.iter().all(|address| wildcard_targets.contains(address)),
+    }
+}
+
+/// Generates a random DNS label that is very unlikely to exist.
+fn random_label() -> String {
+    format!("wc-{:016x}", random_u64())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_wordlist() {
+        let words = parse_wordlist("# comment\nADFS\n\n  owa  \nvpn.\n");
+        assert_eq!(words, vec!["adfs", "owa", "vpn"]);
+        let bundled = bundled_wordlist();
+        assert!(bundled.contains(&"sts".to_string()));
+        assert!(bundled.iter().all(|word| !word.starts_with('#')));
+    }
+
+    #[test]
+    fn test_is_wildcard_match() {
+        let wildcard = vec!["192.0.2.1".to_string(), "parking.example.net".to_string()];
+        let same_address = Resolution {
+            cname_target: None,
+            addresses: vec!["192.0.2.1".to_string()],
+        };
+        let same_cname = Resolution {
+            cname_target: Some("parking.example.net".to_string()),
+            addresses: vec!["198.51.100.7".to_string()],
+        };
+        let real_host = Resolution {
+            cname_target: None,
+            addresses: vec!["192.0.2.1".to_string(), "192.0.2.50".to_string()],
+        };
+        assert!(is_wildcard_match(&same_address, &wildcard));
+        assert!(is_wildcard_match(&same_cname, &wildcard));
+        assert!(!is_wildcard_match(&real_host, &wildcard));
+        assert!(!is_wildcard_match(&same_address, &[]));
+        assert_ne!(random_label(), random_label());
+    }
+}
@@ -0,0 +1,286 @@
+use crate::error::ReconError;
+use crate::models::{CnameChain, TakeoverCandidate};
+use crate::recon::common::{unique_hostnames, QueryLimiter, MAX_CONCURRENT_LOOKUPS};
+use futures::stream::{self, StreamExt};
+use reqwest::{Client, StatusCode};
+use std::sync::Arc;
+use tracing::{debug, info, warn};
+use trust_dns_resolver::error::{ResolveError, ResolveErrorKind};
+use trust_dns_resolver::proto::op::ResponseCode;
+use trust_dns_resolver::proto::rr::RecordType;
+use trust_dns_resolver::TokioAsyncResolver;
+
+/// Longest CNAME chain we follow before giving up.
+const MAX_CNAME_DEPTH: usize = 10;
+
+/// Upper bound on HTTP probes of resolving Azure targets in flight.
+const MAX_CONCURRENT_HTTP_PROBES: usize = 8;
+
+/// Azure suffixes whose resource names are globally unique and can be re-registered
+/// by anyone once the original resource is deleted: (suffix, service name).
+const AZURE_TAKEOVER_SUFFIXES: &[(&str, &str)] = &[
+    (".azurewebsites.net", "App Service"),
+    (".azurewebsites.us", "App Service"),
+    (".chinacloudsites.cn", "App Service"),
+    (".cloudapp.net", "Cloud Services"),
+    (".cloudapp.azure.com", "Virtual Machine DNS label"),
+    (".cloudapp.usgovcloudapi.net", "Virtual Machine DNS label"),
+    (".trafficmanager.net", "Traffic Manager"),
+    (".usgovtrafficmanager.net", "Traffic Manager"),
+    (".blob.core.windows.net", "Storage Account (Blob)"),
+    (".web.core.windows.net", "Storage Account (Static Website)"),
+    (".blob.core.usgovcloudapi.net", "Storage Account (Blob)"),
+    (".blob.core.chinacloudapi.cn", "Storage Account (Blob)"),
+    (".azureedge.net", "CDN"),
+    (".azurefd.net", "Front Door"),
+    (".azure-api.net", "API Management"),
+    (".azurecontainer.io", "Container Instances"),
+    (".azurehdinsight.net", "HDInsight"),
+    (".database.windows.net", "SQL Database"),
+    (".redis.cache.windows.net", "Cache for Redis"),
+    (".search.windows.net", "Cognitive Search"),
+    (".servicebus.windows.net", "Service Bus"),
+];
+
+/// Pages an Azure service serves for a name that no resource claims: (service name, body marker).
+/// Only matched on 404 responses.
+const UNCLAIMED_FINGERPRINTS: &[(&str, &str)] = &[
+    ("App Service", "404 Web Site not found"),
+    ("App Service", "Error 404 - Web app not found"),
+];
+
+/// Follows the CNAME chain of every host name and reports Azure takeover candidates.
+///
+/// Only host names that are CNAMEs are returned as chains; a candidate is reported when
+/// the chain runs into an Azure name that does not exist (NXDOMAIN), or when it resolves
+/// but the service answers the host name with its "not found" page for unclaimed names.
+/// DNS queries are paced by `limiter`; lookups and HTTP probes in flight are bounded.
+pub async fn check_dangling_records(
+    resolver: TokioAsyncResolver,
+    client: Client,
+    hostnames: Vec<String>,
+    limiter: Arc<QueryLimiter>,
+) -> Result<(Vec<CnameChain>, Vec<TakeoverCandidate>), ReconError> {
+    let mut chains: Vec<CnameChain> = stream::iter(unique_hostnames(hostnames))
+        .map(|hostname| {
+            let resolver = resolver.clone();
+            let limiter = limiter.clone();
+            async move { resolve_cname_chain(&resolver, &limiter, &hostname).await }
+        })
+        .buffered(MAX_CONCURRENT_LOOKUPS)
+        .collect()
+        .await;
+    chains.retain(|chain| chain.chain.len() > 1);
+    let mut candidates: Vec<TakeoverCandidate> = chains.iter().filter_map(find_takeover_candidate).collect();
+    let resolving = chains.iter().filter(|chain| !chain.nxdomain && !chain.addresses.is_empty());
+    let unclaimed: Vec<Option<TakeoverCandidate>> = stream::iter(resolving)
+        .map(|chain| probe_unclaimed_resource(&client, format!("http://{}/", chain.hostname), chain))
+        .buffered(MAX_CONCURRENT_HTTP_PROBES)
+        .collect()
+        .await;
+    candidates.extend(unclaimed.into_iter().flatten());
+    for candidate in &candidates {
+        warn!(
+            hostname = candidate.hostname.as_str(),
+            target = candidate.dangling_target.as_str(),
+            "Dangling CNAME to unclaimed Azure resource"
+        );
+    }
+    info!(chains = chains.len(), candidates = candidates.len(), "Finished CNAME chain checks");
+    Ok((chains, candidates))
+}
+
+/// Follows CNAME records from `hostname` until a non-CNAME name, then resolves its addresses.
+/// Every query waits for `limiter`.
+pub async fn resolve_cname_chain(resolver: &TokioAsyncResolver, limiter: &QueryLimiter, hostname: &str) -> CnameChain {
+    let hostname = hostname.trim_end_matches('.').to_lowercase();
+    let mut chain = vec![hostname.clone()];
+    let mut nxdomain = false;
+    let mut loop_detected = false;
+
+    while chain.len() <= MAX_CNAME_DEPTH {
+        let current = chain[chain.len() - 1].clone();
+        debug!(domain = current.as_str(), "Querying CNAME record");
+        limiter.wait().await;
+        match resolver.lookup(current.as_str(), RecordType::CNAME).await {
+            Ok(response) => {
+                let Some(target) = response.iter().find_map(|rdata| rdata.as_cname()) else {
+                    break;
+                };
+                let target = target.to_string().trim_end_matches('.').to_lowercase();
+                if chain.contains(&target) {
+                    loop_detected = true;
+                    break;
+                }
+                chain.push(target);
+            }
+            Err(e) => {
+                nxdomain = is_nxdomain(&e);
+                break;
+            }
+        }
+    }
+
+    let mut addresses = Vec::new();
+    if !nxdomain && !loop_detected {
+        let last = &chain[chain.len() - 1];
+        limiter.wait().await;
+        match resolver.lookup_ip(last.as_str()).await {
+            Ok(response) => addresses = response.iter().map(|ip| ip.to_string()).collect(),
+            Err(e) => {
+                debug!(domain = last.as_str(), error = %e, "Address lookup failed");
+                nxdomain = is_nxdomain(&e);
+            }
+        }
+    }
+
+    CnameChain {
+        hostname,
+        chain,
+        addresses,
+        nxdomain,
+        loop_detected,
+    }
+}
+
+/// Returns the final target of a chain: the last CNAME target, or the first address if there is no CNAME.
+pub fn chain_target(chain: &CnameChain) -> Option<String> {
+    if chain.chain.len() > 1 {
+        chain.chain.last().cloned()
+    } else {
+        chain.addresses.first().cloned()
+    }
+}
+
+/// Checks whether a resolver error is an NXDOMAIN response.
+fn is_nxdomain(error: &ResolveError) -> bool {
+    matches!(
+        error.kind(),
+        ResolveErrorKind::NoRecordsFound { response_code, .. } if *response_code == ResponseCode::NXDomain
+    )
+}
+
+/// Returns the Azure service whose name space contains `host`, if any.
+pub fn azure_service(host: &str) -> Option<&'static str> {
+    AZURE_TAKEOVER_SUFFIXES
+        .iter()
+        .find(|(suffix, _)| host.ends_with(suffix))
+        .map(|(_, service)| *service)
+}
+
+/// Reports a chain that ends in a non-existent name inside an Azure service's name space.
+fn find_takeover_candidate(chain: &CnameChain) -> Option<TakeoverCandidate> {
+    if !chain.nxdomain || chain.chain.len() < 2 {
+        return None;
+    }
+    // The last name in the chain is the one that does not exist
+    let dangling_target = chain.chain.last()?.clone();
+    let azure_service = azure_service(&dangling_target)?;
+
+    Some(TakeoverCandidate {
+        hostname: chain.hostname.clone(),
+        chain: chain.chain.clone(),
+        azure_service: azure_service.to_string(),
+        reason: format!(
+            "{} does not exist (NXDOMAIN); the {} name can be registered by anyone",
+            dangling_target, azure_service
+        ),
+        dangling_target,
+    })
+}
+
+/// Requests `url` (the host name, through its chain) and reports the chain as a takeover
+/// candidate when the Azure service at its end serves its page for unclaimed names.
+pub async fn probe_unclaimed_resource(client: &Client, url: String, chain: &CnameChain) -> Option<TakeoverCandidate> {
+    let target = chain.chain.last()?;
+    let azure_service = azure_service(target)?;
+    if !UNCLAIMED_FINGERPRINTS.iter().any(|(service, _)| *service == azure_service) {
+        return None;
+    }
+    debug!(url = url.as_str(), service = azure_service, "Checking for an unclaimed Azure resource");
+    let response = match client.get(&url).send().await {
+        Ok(response) => response,
+        Err(e) => {
+            debug!(url = url.as_str(), error = %e, "Unclaimed resource check failed");
+            return None;
+        }
+    };
+    let status = response.status();
+    let body = response.text().await.unwrap_or_default();
+    let marker = unclaimed_marker(azure_service, status, &body)?;
+
+    Some(TakeoverCandidate {
+        hostname: chain.hostname.clone(),
+        chain: chain.chain.clone(),
+        azure_service: azure_service.to_string(),
+        reason: format!(
+            "{} answers with \"{}\"; no {} resource claims the host name",
+            target, marker, azure_service
+        ),
+        dangling_target: target.clone(),
+    })
+}
+
+/// The fingerprint of `azure_service` found in a 404 response body, if any.
+fn unclaimed_marker(azure_service: &str, status: StatusCode, body: &str) -> Option<&'static str> {
+    if status != StatusCode::NOT_FOUND {
+        return None;
+    }
+    UNCLAIMED_FINGERPRINTS
+        .iter()
+        .find(|(service, marker)| *service == azure_service && body.contains(marker))
+        .map(|(_, marker)| *marker)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn chain(names: &[&str], nxdomain: bool) -> CnameChain {
+        CnameChain {
+            hostname: names[0].to_string(),
+            chain: names.iter().map(|name| name.to_string()).collect(),
+            addresses: Vec::new(),
+            nxdomain,
+            loop_detected: false,
+        }
+    }
+
+    #[test]
+    fn test_find_takeover_candidate() {
+        let dangling = chain(&["shop.contoso.com", "contoso-shop.azurewebsites.net"], true);
+        let candidate = find_takeover_candidate(&dangling).expect("should be a candidate");
+        assert_eq!(candidate.azure_service, "App Service");
+        assert_eq!(candidate.dangling_target, "contoso-shop.azurewebsites.net");
+        assert_eq!(candidate.chain, dangling.chain);
+
+        // Chain through Traffic Manager into a deleted storage account
+        let nested = chain(
+            &["files.contoso.com", "contoso.trafficmanager.net", "contosofiles.blob.core.windows.net"],
+            true,
+        );
+        assert_eq!(find_takeover_candidate(&nested).unwrap().azure_service, "Storage Account (Blob)");
+
+        // Resolving Azure targets and non-Azure NXDOMAIN targets are not candidates
+        assert!(find_takeover_candidate(&chain(&["shop.contoso.com", "contoso-shop.azurewebsites.net"], false)).is_none());
+        assert!(find_takeover_candidate(&chain(&["old.contoso.com", "gone.example.net"], true)).is_none());
+        assert!(find_takeover_candidate(&chain(&["missing.contoso.com"], true)).is_none());
+    }
+
+    #[test]
+    fn test_unclaimed_marker() {
+        let page = "<h1>Error 404 - Web app not found.</h1>";
+        assert_eq!(unclaimed_marker("App Service", StatusCode::NOT_FOUND, page), Some("Error 404 - Web app not found"));
+        assert_eq!(unclaimed_marker("App Service", StatusCode::OK, page), None);
+        assert_eq!(unclaimed_marker("Storage Account (Blob)", StatusCode::NOT_FOUND, page), None);
+    }
+
+    #[test]
+    fn test_chain_target() {
+        let mut autodiscover = chain(&["autodiscover.contoso.com", "autodiscover.outlook.com"], false);
+        assert_eq!(chain_target(&autodiscover).as_deref(), Some("autodiscover.outlook.com"));
+        autodiscover.chain.truncate(1);
+        autodiscover.addresses = vec!["192.0.2.10".to_string()];
+        assert_eq!(chain_target(&autodiscover).as_deref(), Some("192.0.2.10"));
+    }
+}
@@ -39,6 +39,8 @@ pub async fn get_federation_info(
     // Basic XML parsing using string searching (fragile, but avoids new dependencies for now)
     let name_space_type = extract_xml_tag_value(&body, "NameSpaceType").unwrap_or("Unknown".to_string());
     let federation_brand_name = extract_xml_tag_value(&body, "FederationBrandName");
+    let auth_url = extract_xml_tag_value(&body, "AuthURL"); // Only present for federated domains (e.g., ADFS)
+    let mex_url = extract_xml_tag_value(&body, "MEXURL"); // WS-Trust metadata exchange, federated domains only
     // TODO: Extract CloudInstanceName as well if needed, although config already provides endpoints.
     // let cloud_instance_name = extract_xml_tag_value(&body, "CloudInstanceName");
 
@@ -46,14 +48,15 @@ pub async fn get_federation_info(
         is_federated: true, // If we got here, we have federation info, so it's federated
         name_space_type: Some(name_space_type), // Convert String to Option<String>
         federation_brand_name, // Already an Option<String>
-        auth_url: None, // Could extract from XML if needed
+        auth_url,
+        mex_url,
         cloud_instance_name: None, // Could extract from XML if needed
     })
 }
 
 /// Simple helper to extract the value from an XML tag.
 /// Example: <Tag>Value</Tag> -> "Value"
-fn extract_xml_tag_value(xml: &str, tag_name: &str) -> Option<String> {
+pub fn extract_xml_tag_value(xml: &str, tag_name: &str) -> Option<String> {
     let start_tag = format!("<{}>", tag_name);
     let end_tag = format!("</{}>", tag_name);
 
@@ -0,0 +1,86 @@
+// Integration tests for Autodiscover v2 parsing against a local Exchange Online stand-in
+use msft_recon_rs::models::{AutodiscoverResults, ExchangeTopology};
+use msft_recon_rs::recon::autodiscover::{exchange_topology, query_autodiscover_v2};
+use reqwest::Client;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_autodiscover_v2_hybrid_answer() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/autodiscover/autodiscover.json/v1.0/recon@contoso.com"))
+        .and(query_param("Protocol", "EWS"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "Protocol": "EWS",
+            "Url": "https://mail.contoso.com/EWS/Exchange.asmx"
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/autodiscover/autodiscover.json/v1.0/recon@contoso.com"))
+        .and(query_param("Protocol", "REST"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "Protocol": "REST",
+            "Url": "https://outlook.office.com/api"
+        })))
+        .mount(&server)
+        .await;
+
+    let client = Client::new();
+    let ews = query_autodiscover_v2(&client, &server.uri(), "recon@contoso.com", "EWS").await;
+    assert_eq!(ews.url.as_deref(), Some("https://mail.contoso.com/EWS/Exchange.asmx"));
+    assert!(ews.on_premises);
+    assert!(ews.error.is_none());
+
+    let rest = query_autodiscover_v2(&client, &server.uri(), "recon@contoso.com", "REST").await;
+    assert_eq!(rest.url.as_deref(), Some("https://outlook.office.com/api"));
+    assert!(!rest.on_premises);
+}
+
+#[tokio::test]
+async fn test_autodiscover_v2_error_answer() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/autodiscover/autodiscover.json/v1.0/recon@contoso.com"))
+        .respond_with(ResponseTemplate::new(400).set_body_json(serde_json::json!({
+            "ErrorCode": "InvalidProtocol",
+            "ErrorMessage": "The given protocol value 'Foo' is invalid."
+        })))
+        .mount(&server)
+        .await;
+
+    let answer = query_autodiscover_v2(&Client::new(), &server.uri(), "recon@contoso.com", "Foo").await;
+    assert!(answer.url.is_none());
+    assert!(!answer.on_premises);
+    assert_eq!(
+        answer.error.as_deref(),
+        Some("InvalidProtocol: The given protocol value 'Foo' is invalid.")
+    );
+}
+
+#[tokio::test]
+async fn test_autodiscover_v2_cloud_answer_without_m365_evidence() {
+    // Exchange Online answers with its own URLs for domains it does not host
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/autodiscover/autodiscover.json/v1.0/recon@example.org"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "Protocol": "EWS",
+            "Url": "https://outlook.office365.com/EWS/Exchange.asmx"
+        })))
+        .mount(&server)
+        .await;
+
+    let ews = query_autodiscover_v2(&Client::new(), &server.uri(), "recon@example.org", "EWS").await;
+    assert!(!ews.on_premises);
+    let mut results = AutodiscoverResults {
+        address: "recon@example.org".to_string(),
+        v2: vec![ews],
+        ..Default::default()
+    };
+    assert_eq!(exchange_topology(&results), None);
+
+    results.m365_evidence = true;
+    assert_eq!(exchange_topology(&results), Some(ExchangeTopology::CloudOnly));
+}
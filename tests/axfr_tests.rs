@@ -0,0 +1,95 @@
+// Integration tests for the AXFR zone transfer attempt against a local DNS stand-in
+use msft_recon_rs::recon::nameservers::attempt_zone_transfer;
+use std::net::{Ipv4Addr, SocketAddr};
+use std::str::FromStr;
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use trust_dns_resolver::proto::op::{Message, MessageType, ResponseCode};
+use trust_dns_resolver::proto::rr::rdata::{A, SOA};
+use trust_dns_resolver::proto::rr::{Name, RData, Record};
+
+/// Reads one length-prefixed DNS query from the stream.
+async fn read_query(stream: &mut TcpStream) -> Message {
+    let length = stream.read_u16().await.unwrap();
+    let mut buffer = vec![0u8; usize::from(length)];
+    stream.read_exact(&mut buffer).await.unwrap();
+    Message::from_vec(&buffer).unwrap()
+}
+
+/// Writes one length-prefixed DNS message to the stream.
+async fn write_message(stream: &mut TcpStream, message: &Message) {
+    let bytes = message.to_vec().unwrap();
+    stream.write_u16(bytes.len() as u16).await.unwrap();
+    stream.write_all(&bytes).await.unwrap();
+}
+
+/// Starts a DNS stand-in that either serves the contoso.com zone over AXFR or refuses it.
+async fn start_dns_stand_in(allow_transfer: bool) -> SocketAddr {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let address = listener.local_addr().unwrap();
+
+    tokio::spawn(async move {
+        let (mut stream, _) = listener.accept().await.unwrap();
+        let query = read_query(&mut stream).await;
+        let zone = Name::from_str("contoso.com.").unwrap();
+        let soa = Record::from_rdata(
+            zone.clone(),
+            3600,
+            RData::SOA(SOA::new(
+                Name::from_str("ns1.contoso.com.").unwrap(),
+                Name::from_str("hostmaster.contoso.com.").unwrap(),
+                2024010101,
+                3600,
+                600,
+                86400,
+                300,
+            )),
+        );
+        let host = Record::from_rdata(
+            Name::from_str("adfs.contoso.com.").unwrap(),
+            3600,
+            RData::A(A(Ipv4Addr::new(192, 0, 2, 10))),
+        );
+
+        let mut response = Message::new();
+        response
+            .set_id(query.id())
+            .set_message_type(MessageType::Response)
+            .add_queries(query.queries().to_vec());
+        if !allow_transfer {
+            response.set_response_code(ResponseCode::Refused);
+            write_message(&mut stream, &response).await;
+            return;
+        }
+        // Split the transfer over two messages, as real servers do for large zones
+        let mut first = response.clone();
+        first.add_answer(soa.clone()).add_answer(host);
+        write_message(&mut stream, &first).await;
+        let mut last = response;
+        last.add_answer(soa);
+        write_message(&mut stream, &last).await;
+    });
+    address
+}
+
+#[tokio::test]
+async fn test_zone_transfer_allowed() {
+    let server = start_dns_stand_in(true).await;
+    let result = attempt_zone_transfer(server, "contoso.com", Duration::from_secs(5)).await;
+
+    assert!(result.allowed);
+    assert_eq!(result.error, None);
+    assert_eq!(result.record_count, 3);
+    assert!(result.records.iter().any(|record| record.contains("adfs.contoso.com") && record.contains("192.0.2.10")));
+}
+
+#[tokio::test]
+async fn test_zone_transfer_refused() {
+    let server = start_dns_stand_in(false).await;
+    let result = attempt_zone_transfer(server, "contoso.com", Duration::from_secs(5)).await;
+
+    assert!(!result.allowed);
+    assert_eq!(result.record_count, 0);
+    assert!(result.error.unwrap().contains("Refused"));
+}
@@ -0,0 +1,46 @@
+// Integration tests for Certificate Transparency ingestion against a local crt.sh stand-in
+use msft_recon_rs::recon::ct::query_ct_logs;
+use reqwest::Client;
+use wiremock::matchers::{method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_query_ct_logs_merges_and_deduplicates_names() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/"))
+        .and(query_param("q", "%.contoso.com"))
+        .and(query_param("output", "json"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(
+            r#"[
+                {"issuer_name": "C=US, O=Let's Encrypt, CN=R3", "common_name": "adfs.contoso.com", "name_value": "adfs.contoso.com\nsts.contoso.com"},
+                {"issuer_name": "C=US, O=Microsoft Corporation", "common_name": "*.contoso.com", "name_value": "*.contoso.com\ncontoso.com"},
+                {"issuer_name": "C=US, O=Let's Encrypt, CN=R3", "common_name": "sts.contoso.com", "name_value": "STS.contoso.com\nshop.contoso.com"}
+            ]"#,
+            "application/json",
+        ))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+
+    let names = query_ct_logs(Client::new(), mock_server.uri(), "contoso.com".to_string())
+        .await
+        .expect("CT query should succeed");
+
+    assert_eq!(
+        names,
+        vec!["adfs.contoso.com", "contoso.com", "shop.contoso.com", "sts.contoso.com"]
+    );
+}
+
+#[tokio::test]
+async fn test_query_ct_logs_reports_server_errors() {
+    let mock_server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(502).set_body_string("Bad Gateway"))
+        .mount(&mock_server)
+        .await;
+
+    let result = query_ct_logs(Client::new(), format!("{}/", mock_server.uri()), "contoso.com".to_string()).await;
+    assert!(result.is_err());
+}
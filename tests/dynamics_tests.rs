@@ -0,0 +1,34 @@
+// Integration tests for Dynamics 365 environment detection against a local sign-in redirect stand-in
+use msft_recon_rs::config::DynamicsRegion;
+use msft_recon_rs::recon::dynamics::probe_dynamics_environment;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_probe_dynamics_environment_reads_tenant_from_redirect() {
+    let server = MockServer::start().await;
+    let authorize_path = "/72f988bf-86f1-41af-91ab-2d7cd011db47/oauth2/authorize";
+    Mock::given(method("GET"))
+        .and(path("/main.aspx"))
+        .respond_with(ResponseTemplate::new(302).append_header("Location", format!("{}{}", server.uri(), authorize_path)))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(authorize_path))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html>Sign in to your account</html>"))
+        .mount(&server)
+        .await;
+
+    let region = DynamicsRegion {
+        host_suffix: ".crm4.dynamics.com".to_string(),
+        geography: "EMEA".to_string(),
+    };
+    let url = format!("{}/main.aspx", server.uri());
+    let environment = probe_dynamics_environment(&Client::new(), url.clone(), &region, "127.0.0.1").await.unwrap();
+    assert_eq!(environment.url, url);
+    assert_eq!(environment.geography, "EMEA");
+    assert_eq!(environment.tenant_id.as_deref(), Some("72f988bf-86f1-41af-91ab-2d7cd011db47"));
+
+    assert_eq!(probe_dynamics_environment(&Client::new(), url, &region, "login.microsoftonline.com").await, None);
+}
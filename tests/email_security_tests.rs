@@ -0,0 +1,45 @@
+// Integration tests for MTA-STS policy retrieval against a local policy host stand-in
+use msft_recon_rs::recon::email_security::fetch_mta_sts_policy;
+use reqwest::{redirect, Client};
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+const POLICY: &str = "version: STSv1\nmode: enforce\nmx: contoso-com.mail.protection.outlook.com\nmax_age: 604800\n";
+
+fn no_redirect_client() -> Client {
+    Client::builder().redirect(redirect::Policy::none()).build().unwrap()
+}
+
+#[tokio::test]
+async fn test_fetch_mta_sts_policy() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/.well-known/mta-sts.txt"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(POLICY, "text/plain"))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/.well-known/mta-sts.txt", server.uri());
+    let (body, content_type) = fetch_mta_sts_policy(&no_redirect_client(), &url).await.unwrap();
+    assert_eq!(body, POLICY);
+    assert_eq!(content_type.as_deref(), Some("text/plain"));
+}
+
+#[tokio::test]
+async fn test_fetch_mta_sts_policy_does_not_follow_redirects() {
+    let server = MockServer::start().await;
+    // A catch-all host sending every path to a page that happens to answer 200
+    Mock::given(method("GET"))
+        .and(path("/.well-known/mta-sts.txt"))
+        .respond_with(ResponseTemplate::new(302).append_header("Location", format!("{}/landing", server.uri())))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/landing"))
+        .respond_with(ResponseTemplate::new(200).set_body_raw(POLICY, "text/plain"))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/.well-known/mta-sts.txt", server.uri());
+    assert_eq!(fetch_mta_sts_policy(&no_redirect_client(), &url).await, None);
+}
@@ -0,0 +1,42 @@
+// Integration tests for Exchange build fingerprinting against a local OWA/EWS stand-in
+use msft_recon_rs::recon::exchange::fingerprint_exchange_host;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_fingerprint_prefers_owa_version_header() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/owa/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"<link rel="shortcut icon" href="/owa/auth/15.2.1544/themes/resources/favicon.ico" type="image/x-icon">"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/EWS/Exchange.asmx"))
+        .respond_with(ResponseTemplate::new(401).append_header("X-OWA-Version", "15.2.1544.4"))
+        .mount(&server)
+        .await;
+
+    let (build, source) = fingerprint_exchange_host(&Client::new(), &server.uri()).await.unwrap();
+    assert_eq!(build, "15.2.1544.4");
+    assert_eq!(source, "X-OWA-Version header (/EWS/Exchange.asmx)");
+}
+
+#[tokio::test]
+async fn test_fingerprint_falls_back_to_resource_path() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/ecp/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"<script src="/ecp/15.1.2507.39/scripts/microsoft.exchange.control.js"></script>"#,
+        ))
+        .mount(&server)
+        .await;
+
+    let (build, source) = fingerprint_exchange_host(&Client::new(), &server.uri()).await.unwrap();
+    assert_eq!(build, "15.1.2507.39");
+    assert_eq!(source, "/ecp/ resource path");
+}
@@ -0,0 +1,47 @@
+// Integration tests for WWW-Authenticate scheme detection against a local Exchange stand-in
+use msft_recon_rs::models::AuthProtocol;
+use msft_recon_rs::recon::legacy_auth::probe_http_auth;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_probe_http_auth_reads_challenges() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/EWS/Exchange.asmx"))
+        .respond_with(
+            ResponseTemplate::new(401)
+                .append_header("WWW-Authenticate", "Negotiate")
+                .append_header("WWW-Authenticate", "NTLM")
+                .append_header("WWW-Authenticate", r#"Basic realm="mail.contoso.com""#),
+        )
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/EWS/Exchange.asmx", server.uri());
+    let endpoint = probe_http_auth(&Client::new(), AuthProtocol::Ews, url, true).await;
+    assert!(endpoint.reachable);
+    assert_eq!(endpoint.status, Some(401));
+    assert_eq!(endpoint.schemes, vec!["Negotiate", "NTLM", "Basic"]);
+    assert!(endpoint.basic_auth);
+    assert!(endpoint.on_premises);
+}
+
+#[tokio::test]
+async fn test_probe_http_auth_modern_only() {
+    let server = MockServer::start().await;
+    Mock::given(method("OPTIONS"))
+        .and(path("/Microsoft-Server-ActiveSync"))
+        .respond_with(ResponseTemplate::new(401).append_header(
+            "WWW-Authenticate",
+            r#"Bearer client_id="00000002-0000-0ff1-ce00-000000000000", trusted_issuers="00000001-0000-0000-c000-000000000000@*""#,
+        ))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/Microsoft-Server-ActiveSync", server.uri());
+    let endpoint = probe_http_auth(&Client::new(), AuthProtocol::ActiveSync, url, false).await;
+    assert_eq!(endpoint.schemes, vec!["Bearer"]);
+    assert!(!endpoint.basic_auth);
+}
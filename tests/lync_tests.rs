@@ -0,0 +1,53 @@
+// Integration tests for lyncdiscover JSON parsing against a local Skype for Business stand-in
+use msft_recon_rs::recon::lync::fetch_lync_discover;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_fetch_lync_discover_follows_redirect() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/autodiscover/autodiscoverservice.svc/root"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "_links": {
+                "self": { "href": format!("{}/autodiscover/autodiscoverservice.svc/root", server.uri()) },
+                "redirect": { "href": format!("{}/pool/autodiscover/autodiscoverservice.svc/root", server.uri()) }
+            }
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/pool/autodiscover/autodiscoverservice.svc/root"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "_links": {
+                "self": { "href": "https://lyncweb.contoso.com/Autodiscover/AutodiscoverService.svc/root" },
+                "user": { "href": "https://lyncweb.contoso.com/Autodiscover/AutodiscoverService.svc/root/oauth/user" },
+                "xframe": { "href": "https://lyncweb.contoso.com/Autodiscover/XFrame/XFrame.html" }
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/autodiscover/autodiscoverservice.svc/root", server.uri());
+    let links = fetch_lync_discover(&Client::new(), &url).await.unwrap();
+    assert_eq!(links.url, format!("{}/pool/autodiscover/autodiscoverservice.svc/root", server.uri()));
+    assert_eq!(
+        links.user.as_deref(),
+        Some("https://lyncweb.contoso.com/Autodiscover/AutodiscoverService.svc/root/oauth/user")
+    );
+    assert_eq!(links.xframe.as_deref(), Some("https://lyncweb.contoso.com/Autodiscover/XFrame/XFrame.html"));
+}
+
+#[tokio::test]
+async fn test_fetch_lync_discover_rejects_non_json() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/autodiscover/autodiscoverservice.svc/root"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html>Parked domain</html>"))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/autodiscover/autodiscoverservice.svc/root", server.uri());
+    assert_eq!(fetch_lync_discover(&Client::new(), &url).await, None);
+}
@@ -0,0 +1,37 @@
+// Integration tests for NTLM challenge decoding against a local IIS stand-in
+use msft_recon_rs::recon::ntlm::probe_ntlm_endpoint;
+use reqwest::Client;
+use wiremock::matchers::{header_exists, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+/// Type 2 message for CONTOSO / EXCH01.corp.contoso.com on Windows Server 2019.
+const CHALLENGE: &str = "TlRMTVNTUAACAAAADgAOADgAAAAVgoriESIzRFVmd4gAAAAAAAAAAKIAogBGAAAACgBjRQAAAA9DAE8ATgBUAE8AUwBPAAIADgBDAE8ATgBUAE8AUwBPAAEADABFAFgAQwBIADAAMQAEACAAYwBvAHIAcAAuAGMAbwBuAHQAbwBzAG8ALgBjAG8AbQADAC4ARQBYAEMASAAwADEALgBjAG8AcgBwAC4AYwBvAG4AdABvAHMAbwAuAGMAbwBtAAUAFgBjAG8AbgB0AG8AcwBvAC4AYwBvAG0ABwAIAACE4MqdR9oBAAAAAA==";
+
+#[tokio::test]
+async fn test_probe_ntlm_endpoint_decodes_challenge() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/EWS/Exchange.asmx"))
+        .and(header_exists("Authorization"))
+        .respond_with(ResponseTemplate::new(401).append_header("WWW-Authenticate", format!("NTLM {}", CHALLENGE).as_str()))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/EWS/Exchange.asmx", server.uri());
+    let info = probe_ntlm_endpoint(&Client::new(), &url, "NTLM").await.expect("challenge should decode");
+    assert_eq!(info.netbios_domain.as_deref(), Some("CONTOSO"));
+    assert_eq!(info.dns_computer.as_deref(), Some("EXCH01.corp.contoso.com"));
+    assert_eq!(info.os_name.as_deref(), Some("Windows Server 2019"));
+}
+
+#[tokio::test]
+async fn test_probe_ntlm_endpoint_without_ntlm() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .respond_with(ResponseTemplate::new(401).append_header("WWW-Authenticate", "Bearer realm=\"\""))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/adfs/services/trust/2005/windowstransport", server.uri());
+    assert!(probe_ntlm_endpoint(&Client::new(), &url, "NTLM").await.is_none());
+}
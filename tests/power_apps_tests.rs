@@ -0,0 +1,49 @@
+// Integration tests for Power Pages detection against a local portal stand-in
+use msft_recon_rs::recon::power_apps::probe_power_pages_site;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_probe_power_pages_site_reports_exposed_tables() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .append_header("Set-Cookie", "Dynamics365PortalAnalytics=abc; path=/; secure; HttpOnly")
+                .set_body_string(r#"<html><head><title>Home · Contoso Support</title></head></html>"#),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_api/contacts"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({ "value": [] })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/_api/accounts"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(serde_json::json!({
+            "error": { "code": "90040101", "message": "Attribute * in table account is not enabled for Web Api." }
+        })))
+        .mount(&server)
+        .await;
+
+    let site = probe_power_pages_site(&Client::new(), format!("{}/", server.uri())).await.unwrap();
+    assert_eq!(site.display_name.as_deref(), Some("Home · Contoso Support"));
+    assert_eq!(site.exposed_tables, vec!["contacts"]);
+    assert!(site.odata_feeds.is_empty());
+    assert!(site.anonymous_data_access);
+}
+
+#[tokio::test]
+async fn test_probe_power_pages_site_ignores_other_sites() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html><title>Welcome to nginx!</title></html>"))
+        .mount(&server)
+        .await;
+
+    assert_eq!(probe_power_pages_site(&Client::new(), format!("{}/", server.uri())).await, None);
+}
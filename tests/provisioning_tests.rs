@@ -0,0 +1,71 @@
+// Integration tests for MDM discovery and device registration contracts against local stand-ins
+use msft_recon_rs::recon::provisioning::{query_drs_contract, query_mdm_discovery};
+use reqwest::Client;
+use wiremock::matchers::{body_string_contains, header, method, path, query_param};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_query_mdm_discovery_reads_enrollment_endpoints() {
+    let server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/EnrollmentServer/Discovery.svc"))
+        .and(header("Content-Type", "application/soap+xml; charset=utf-8"))
+        .and(body_string_contains("<EmailAddress>recon@contoso.com</EmailAddress>"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(concat!(
+            r#"<s:Envelope xmlns:s="http://www.w3.org/2003/05/soap-envelope" xmlns:a="http://www.w3.org/2005/08/addressing"><s:Body>"#,
+            r#"<DiscoverResponse xmlns="http://schemas.microsoft.com/windows/management/2012/01/enrollment"><DiscoverResult>"#,
+            "<AuthPolicy>Federated</AuthPolicy><EnrollmentVersion>4.0</EnrollmentVersion>",
+            "<EnrollmentPolicyServiceUrl>https://mdm.contoso.com/EnrollmentServer/Policy.svc</EnrollmentPolicyServiceUrl>",
+            "<EnrollmentServiceUrl>https://mdm.contoso.com/EnrollmentServer/Enrollment.svc</EnrollmentServiceUrl>",
+            "<AuthenticationServiceUrl>https://mdm.contoso.com/AuthenticationService.svc</AuthenticationServiceUrl>",
+            "</DiscoverResult></DiscoverResponse></s:Body></s:Envelope>"
+        )))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/EnrollmentServer/Discovery.svc", server.uri());
+    let discovery = query_mdm_discovery(&Client::new(), &url, "recon@contoso.com").await.unwrap();
+    assert_eq!(discovery.auth_policy.as_deref(), Some("Federated"));
+    assert_eq!(
+        discovery.enrollment_service_url.as_deref(),
+        Some("https://mdm.contoso.com/EnrollmentServer/Enrollment.svc")
+    );
+    assert_eq!(
+        discovery.authentication_service_url.as_deref(),
+        Some("https://mdm.contoso.com/AuthenticationService.svc")
+    );
+}
+
+#[tokio::test]
+async fn test_query_drs_contract() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/contoso.com/enrollmentserver/contract"))
+        .and(query_param("api-version", "1.4"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(serde_json::json!({
+            "DeviceRegistrationService": {
+                "RegistrationEndpoint": "https://enterpriseregistration.windows.net/EnrollmentServer/device/",
+                "RegistrationResourceId": "urn:ms-drs:enterpriseregistration.windows.net",
+                "ServiceVersion": "1.0"
+            },
+            "IdentityProviderService": {
+                "Federated": true,
+                "PassiveAuthEndpoint": "https://adfs.contoso.com/adfs/ls"
+            }
+        })))
+        .mount(&server)
+        .await;
+
+    let url = format!("{}/contoso.com/enrollmentserver/contract?api-version=1.4", server.uri());
+    let registration = query_drs_contract(&Client::new(), &url, false).await.unwrap();
+    assert_eq!(
+        registration.registration_endpoint.as_deref(),
+        Some("https://enterpriseregistration.windows.net/EnrollmentServer/device/")
+    );
+    assert_eq!(registration.federated, Some(true));
+    assert_eq!(registration.passive_auth_endpoint.as_deref(), Some("https://adfs.contoso.com/adfs/ls"));
+    assert!(!registration.on_premises);
+
+    let unknown = format!("{}/fabrikam.com/enrollmentserver/contract?api-version=1.4", server.uri());
+    assert_eq!(query_drs_contract(&Client::new(), &unknown, false).await, None);
+}
@@ -0,0 +1,66 @@
+// Integration tests for AD FS WS-Trust and IdP-initiated sign-on checks against a local stand-in
+use msft_recon_rs::models::WsTrustKind;
+use msft_recon_rs::recon::saml::check_adfs_endpoints;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+#[tokio::test]
+async fn test_check_adfs_endpoints_flags_usernamemixed() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/adfs/ls/idpinitiatedsignon.aspx"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(
+            r#"<form method="post"><input id="idp_SignInThisSiteRadioButton" type="radio"/><input id="idp_SignInButton" type="submit"/></form>"#,
+        ))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/adfs/services/trust/mex"))
+        .respond_with(ResponseTemplate::new(200).set_body_string(format!(
+            concat!(
+                r#"<wsdl:definitions xmlns:wsdl="http://schemas.xmlsoap.org/wsdl/" xmlns:soap12="http://schemas.xmlsoap.org/wsdl/soap12/">"#,
+                r#"<wsdl:service name="SecurityTokenService"><wsdl:port name="UserNameWSTrustBinding_IWSTrust13Async">"#,
+                r#"<soap12:address location="{}/adfs/services/trust/13/usernamemixed"/>"#,
+                "</wsdl:port></wsdl:service></wsdl:definitions>"
+            ),
+            server.uri()
+        )))
+        .mount(&server)
+        .await;
+    // WS-Trust endpoints reject a plain GET without a SOAP envelope
+    Mock::given(method("GET"))
+        .and(path("/adfs/services/trust/13/usernamemixed"))
+        .respond_with(ResponseTemplate::new(400))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/adfs/services/trust/2005/windowstransport"))
+        .respond_with(ResponseTemplate::new(400))
+        .mount(&server)
+        .await;
+
+    let (idp_initiated_signon, endpoints) = check_adfs_endpoints(&Client::new(), &server.uri(), None).await;
+    assert_eq!(idp_initiated_signon, Some(true));
+    let exposed: Vec<(&str, &WsTrustKind)> = endpoints
+        .iter()
+        .filter(|endpoint| endpoint.exposed)
+        .map(|endpoint| (endpoint.version.as_str(), &endpoint.kind))
+        .collect();
+    // windowstransport answers but is not published in the metadata exchange
+    assert_eq!(exposed, vec![("13", &WsTrustKind::UsernameMixed)]);
+}
+
+#[tokio::test]
+async fn test_check_adfs_endpoints_signon_disabled() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/adfs/ls/idpinitiatedsignon.aspx"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<div id=\"errorArea\">An error occurred</div>"))
+        .mount(&server)
+        .await;
+
+    let (idp_initiated_signon, endpoints) = check_adfs_endpoints(&Client::new(), &server.uri(), None).await;
+    assert_eq!(idp_initiated_signon, Some(false));
+    assert!(endpoints.iter().all(|endpoint| !endpoint.exposed));
+}
@@ -0,0 +1,113 @@
+// Integration tests for the SMTP probe against a local SMTP stand-in
+use msft_recon_rs::recon::smtp::probe_smtp_host;
+use rcgen::generate_simple_self_signed;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::io::{AsyncBufReadExt, AsyncRead, AsyncWrite, AsyncWriteExt, BufStream};
+use tokio::net::TcpListener;
+use tokio_rustls::rustls::crypto::ring;
+use tokio_rustls::rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+use tokio_rustls::rustls::ServerConfig;
+use tokio_rustls::TlsAcceptor;
+
+const BANNER: &str = "220 EXCH01.corp.contoso.local Microsoft ESMTP MAIL Service ready at Mon, 1 Jan 2024 00:00:00 +0000\r\n";
+
+/// Reads commands until QUIT (or STARTTLS), answering like a minimal MTA.
+/// Returns true if the client asked for STARTTLS.
+async fn serve_commands<S>(stream: &mut BufStream<S>, offer_starttls: bool) -> bool
+where
+    S: AsyncRead + AsyncWrite + Unpin,
+{
+    loop {
+        let mut line = String::new();
+        if stream.read_line(&mut line).await.unwrap_or(0) == 0 {
+            return false;
+        }
+        let command = line.trim_end().to_uppercase();
+        let reply = if command.starts_with("EHLO") {
+            let starttls = if offer_starttls { "250-STARTTLS\r\n" } else { "" };
+            format!("250-EXCH01.corp.contoso.local Hello [127.0.0.1]\r\n250-SIZE 37748736\r\n{}250 8BITMIME\r\n", starttls)
+        } else if command == "STARTTLS" {
+            stream.write_all(b"220 2.0.0 SMTP server ready\r\n").await.unwrap();
+            stream.flush().await.unwrap();
+            return true;
+        } else if command == "QUIT" {
+            let _ = stream.write_all(b"221 2.0.0 Bye\r\n").await;
+            let _ = stream.flush().await;
+            return false;
+        } else {
+            "500 5.3.3 Unrecognized command\r\n".to_string()
+        };
+        stream.write_all(reply.as_bytes()).await.unwrap();
+        stream.flush().await.unwrap();
+    }
+}
+
+/// Starts an SMTP stand-in on a random local port, optionally offering STARTTLS.
+async fn start_smtp_stand_in(offer_starttls: bool) -> u16 {
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+
+    let certified = generate_simple_self_signed(vec!["localhost".to_string()]).unwrap();
+    let key = PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(certified.key_pair.serialize_der()));
+    let config = ServerConfig::builder_with_provider(Arc::new(ring::default_provider()))
+        .with_safe_default_protocol_versions()
+        .unwrap()
+        .with_no_client_auth()
+        .with_single_cert(vec![certified.cert.der().clone()], key)
+        .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+
+    tokio::spawn(async move {
+        let (tcp, _) = listener.accept().await.unwrap();
+        let mut stream = BufStream::new(tcp);
+        stream.write_all(BANNER.as_bytes()).await.unwrap();
+        stream.flush().await.unwrap();
+        if serve_commands(&mut stream, offer_starttls).await {
+            let tls = acceptor.accept(stream.into_inner()).await.unwrap();
+            let mut tls = BufStream::new(tls);
+            serve_commands(&mut tls, false).await;
+        }
+    });
+    port
+}
+
+#[tokio::test]
+async fn test_smtp_probe_plain_session() {
+    let port = start_smtp_stand_in(false).await;
+    let result = probe_smtp_host("127.0.0.1", port, Duration::from_secs(5)).await;
+
+    assert!(result.connected);
+    assert_eq!(result.error, None);
+    assert_eq!(result.banner_hostname.as_deref(), Some("EXCH01.corp.contoso.local"));
+    assert_eq!(result.software.as_deref(), Some("Microsoft Exchange"));
+    assert!(result.leaks_internal_hostname);
+    assert_eq!(result.capabilities, vec!["SIZE 37748736", "8BITMIME"]);
+    assert!(!result.starttls_offered);
+    assert_eq!(result.certificate, None);
+}
+
+#[tokio::test]
+async fn test_smtp_probe_starttls_session() {
+    let port = start_smtp_stand_in(true).await;
+    let result = probe_smtp_host("localhost", port, Duration::from_secs(5)).await;
+
+    assert_eq!(result.error, None);
+    assert!(result.starttls_offered);
+    assert!(result.tls_version.is_some());
+    assert!(result.tls_cipher.is_some());
+    let certificate = result.certificate.expect("certificate should be captured");
+    assert!(certificate.self_signed);
+    assert!(!certificate.expired);
+    assert_eq!(certificate.subject_alt_names, vec!["localhost"]);
+}
+
+#[tokio::test]
+async fn test_smtp_probe_connection_refused() {
+    // Bind and drop a listener to get a port nothing is listening on
+    let port = TcpListener::bind("127.0.0.1:0").await.unwrap().local_addr().unwrap().port();
+    let result = probe_smtp_host("127.0.0.1", port, Duration::from_secs(5)).await;
+
+    assert!(!result.connected);
+    assert!(result.error.is_some());
+}
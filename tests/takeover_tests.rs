@@ -0,0 +1,56 @@
+// Integration tests for unclaimed Azure resource detection against a local App Service stand-in
+use msft_recon_rs::models::CnameChain;
+use msft_recon_rs::recon::takeover::probe_unclaimed_resource;
+use reqwest::Client;
+use wiremock::matchers::{method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn app_service_chain() -> CnameChain {
+    CnameChain {
+        hostname: "shop.contoso.com".to_string(),
+        chain: vec!["shop.contoso.com".to_string(), "contoso-shop.azurewebsites.net".to_string()],
+        addresses: vec!["192.0.2.10".to_string()],
+        nxdomain: false,
+        loop_detected: false,
+    }
+}
+
+#[tokio::test]
+async fn test_probe_unclaimed_resource_reports_not_found_page() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(404).set_body_string(
+            "<html><head><title>Microsoft Azure Web App - Error 404</title></head><body><h1>404 Web Site not found.</h1></body></html>",
+        ))
+        .mount(&server)
+        .await;
+
+    let chain = app_service_chain();
+    let candidate = probe_unclaimed_resource(&Client::new(), format!("{}/", server.uri()), &chain)
+        .await
+        .expect("should be a candidate");
+    assert_eq!(candidate.hostname, "shop.contoso.com");
+    assert_eq!(candidate.azure_service, "App Service");
+    assert_eq!(candidate.dangling_target, "contoso-shop.azurewebsites.net");
+    assert!(candidate.reason.contains("404 Web Site not found"));
+}
+
+#[tokio::test]
+async fn test_probe_unclaimed_resource_ignores_claimed_site() {
+    let server = MockServer::start().await;
+    Mock::given(method("GET"))
+        .and(path("/"))
+        .respond_with(ResponseTemplate::new(200).set_body_string("<html><title>Contoso Shop</title></html>"))
+        .mount(&server)
+        .await;
+
+    let chain = app_service_chain();
+    assert!(probe_unclaimed_resource(&Client::new(), format!("{}/", server.uri()), &chain).await.is_none());
+
+    // Services without a fingerprint are not requested at all
+    let mut storage = chain.clone();
+    storage.chain[1] = "contosofiles.blob.core.windows.net".to_string();
+    assert!(probe_unclaimed_resource(&Client::new(), format!("{}/", server.uri()), &storage).await.is_none());
+    assert_eq!(server.received_requests().await.unwrap().len(), 1);
+}